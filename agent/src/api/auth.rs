@@ -1,12 +1,13 @@
 use hex;
 use rand::Rng;
 use axum::{ Router, routing::post };
-use axum::extract::{ Json, State };
+use std::net::SocketAddr;
+use axum::extract::{ ConnectInfo, Json, State };
 use axum::http::header::{ HeaderMap, AUTHORIZATION };
-use tracing::error;
-use crate::resources::users;
+use tracing::{ error, warn };
+use crate::resources::{ catalog, connections, users };
 use crate::utils::user_error::UserError;
-use crate::utils::validators::{ parse_authorization_header, sanitize_username };
+use crate::utils::validators::{ parse_authorization_header, sanitize_username, Username };
 use crate::settings;
 use crate::server::state::ServerState;
 use crate::models::auth::{ Authentication, AuthenticationMethod, RefreshToken, SecurityToken, TokenType };
@@ -30,40 +31,87 @@ impl Default for SecurityToken {
 ///
 /// This endpoint is used to authenticate a user and to generate a security token.
 /// As for now it only supports the local user and the password must be empty.
-async fn logon(State(state): State<ServerState>, auth: Json<Authentication>) -> ServerResult<Json<SecurityToken>> {
+///
+/// After too many failures, the client address is locked out for a while, for this username and for any other (see the
+/// settings `auth_max_failures` and `auth_lockout_duration`). The clients connected through a loopback address are
+/// never locked out.
+async fn logon(
+    State(state): State<ServerState>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    auth: Json<Authentication>
+) -> ServerResult<Json<SecurityToken>> {
     match auth.method {
         AuthenticationMethod::UserPassword => {
             // Usernames are case insensitive. We are using the lowercase version to prevent any duplicate issues when the
             // filesystem is case insensitive.
             let username = sanitize_username(auth.credentials.username.as_str())?;
 
-            // As for now, only the local user is supported.
-            if username.ne(USERNAME_LOCAL) {
-                return Err(Error::Forbidden);
+            // The failures are tracked by client address, and by username along with the client address.
+            let client_keys = ServerState::get_auth_failure_keys(
+                connect_info.map(|ConnectInfo(addr)| addr.ip()),
+                Some(&username)
+            );
+            if let Some(retry_after) = client_keys.iter().filter_map(|key| state.get_lockout(key)).max() {
+                warn!("Logon rejected for user `{}`: too many authentication failures.", &username);
+                return Err(Error::TooManyRequests(retry_after));
             }
 
-            if !auth.credentials.password.is_empty() {
-                // As of now, we only support the local user and the password must be empty.
-                return Err(Error::BadRequest("Password must be empty".to_string()));
-            }
-
-            match users::get_user(&username) {
-                Ok(user) => {
-                    let token = state.add_user_session(&username, &user.user_id);
-                    Ok(Json((*token).clone()))
+            let result = logon_user_password(&state, &username, &auth.credentials.password);
+            match &result {
+                Ok(_) => {
+                    client_keys.iter().for_each(|key| state.clear_auth_failures(key));
                 }
-                Err(err) => {
-                    match err.downcast_ref::<UserError>() {
-                        Some(UserError::NotFound(_)) => {
-                            error!("{}", err);
-                            Err(Error::Forbidden)
-                        }
-                        _ => {
-                            error!("Logon error for user `{}`: {}", &username, err);
-                            Err(Error::InternalServerError)
+                Err(Error::Forbidden | Error::BadRequest(_)) => {
+                    for key in client_keys.iter() {
+                        if state.add_auth_failure(key) {
+                            warn!("Too many authentication failures, '{}' is locked out.", key);
                         }
                     }
                 }
+                Err(_) => {}
+            }
+            result
+        }
+    }
+}
+
+/// Authenticate a user with a username and a password.
+fn logon_user_password(
+    state: &ServerState,
+    username: &Username,
+    password: &str
+) -> ServerResult<Json<SecurityToken>> {
+    // As for now, only the local user is supported.
+    if username.ne(USERNAME_LOCAL) {
+        return Err(Error::Forbidden);
+    }
+
+    if !password.is_empty() {
+        // As of now, we only support the local user and the password must be empty.
+        return Err(Error::BadRequest("Password must be empty".to_string()));
+    }
+
+    match users::get_user(username) {
+        Ok(user) => {
+            if let Err(err) = catalog::create_missing_sections(username) {
+                error!("Unable to upgrade the catalog of the user `{}`: {}", username, err);
+            }
+            if let Err(err) = connections::encrypt_plain_text_credentials(username) {
+                error!("Unable to encrypt the credentials of the connections of the user `{}`: {}", username, err);
+            }
+            let token = state.add_user_session(username, &user.user_id);
+            Ok(Json((*token).clone()))
+        }
+        Err(err) => {
+            match err.downcast_ref::<UserError>() {
+                Some(UserError::NotFound(_)) => {
+                    error!("{}", err);
+                    Err(Error::Forbidden)
+                }
+                _ => {
+                    error!("Logon error for user `{}`: {}", username, err);
+                    Err(Error::InternalServerError)
+                }
             }
         }
     }
@@ -157,7 +205,7 @@ mod test {
                 },
             });
             let state = axum::extract::State(ServerState::new());
-            assert!(matches!(logon(state, body).await, Err(Error::Forbidden)));
+            assert!(matches!(logon(state, None, body).await, Err(Error::Forbidden)));
         }
 
         // 2) valid user
@@ -171,7 +219,7 @@ mod test {
                 },
             });
             let state = axum::extract::State(ServerState::new());
-            let result = logon(state, body).await;
+            let result = logon(state, None, body).await;
             assert!(result.is_ok());
             let result = result.unwrap();
             assert_eq!(result.token.len(), 64);
@@ -187,7 +235,7 @@ mod test {
                 },
             });
             let state = axum::extract::State(ServerState::new());
-            assert!(matches!(logon(state, body).await, Err(Error::Forbidden)));
+            assert!(matches!(logon(state, None, body).await, Err(Error::Forbidden)));
         }
 
         // 4) unexpected password (expected empty string)
@@ -200,7 +248,34 @@ mod test {
                 },
             });
             let state = axum::extract::State(ServerState::new());
-            assert!(matches!(logon(state, body).await, Err(Error::BadRequest(_))));
+            assert!(matches!(logon(state, None, body).await, Err(Error::BadRequest(_))));
+        }
+
+        // 5) too many failures for the same username from the same address
+        {
+            let state = ServerState::new();
+            let body = || {
+                Json(Authentication {
+                    method: AuthenticationMethod::UserPassword,
+                    credentials: Credentials {
+                        username: "local".to_string(),
+                        password: "****".to_string(),
+                    },
+                })
+            };
+            let remote = || Some(ConnectInfo(SocketAddr::from(([192, 168, 0, 1], 1234))));
+            for _ in 0..5 {
+                assert!(matches!(logon(State(state.clone()), remote(), body()).await, Err(Error::BadRequest(_))));
+            }
+            assert!(matches!(logon(State(state.clone()), remote(), body()).await, Err(Error::TooManyRequests(_))));
+
+            // the other addresses are not locked out, and the loopback addresses are never locked out
+            let other = Some(ConnectInfo(SocketAddr::from(([192, 168, 0, 2], 1234))));
+            assert!(matches!(logon(State(state.clone()), other, body()).await, Err(Error::BadRequest(_))));
+            let loopback = || Some(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 1234))));
+            for _ in 0..10 {
+                assert!(matches!(logon(State(state.clone()), loopback(), body()).await, Err(Error::BadRequest(_))));
+            }
         }
 
         // cleanup
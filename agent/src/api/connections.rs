@@ -1,9 +1,46 @@
-use crate::{ models::connections::Connection, utils::user_error::UserError };
-use crate::api::error::ServerResult;
+use crate::models::connections::{ Connection, ConnectionFromUriRequest, ConnectionInfo, ConnectionPreviewRequest };
+use crate::models::environments::EnvironmentPreview;
+use crate::models::plans::{ QueryPlanNode, QueryPlanRequest };
+use crate::models::tables::{ ColumnInfo, PeekTableRequest, PeekTableResult, SchemaInfo, TableInfo, TableReference };
+use crate::models::transactions::{ ExecuteStatementRequest, ExecuteStatementResult };
+use crate::resources::{ connections, environments, plans, tables, transactions };
+use crate::utils::constants::{
+    CONNECTIONS_USAGE_FLUSH_INTERVAL,
+    PEEK_DEFAULT_ROWS,
+    PEEK_MAX_ROWS,
+    TRANSACTION_EXPIRY_CHECK_INTERVAL,
+};
+use crate::utils::user_error::UserError;
+use crate::utils::validators;
+use crate::api::error::{ Error, ServerResult };
+use crate::server::context::RequestContext;
 use crate::server::state::ServerState;
+use crate::{ err_conflict, err_not_found, settings };
+use axum::extract::{ Path, Query, State };
 use axum::{ routing::{ get, post }, Json, Router };
-use drivers::factory::{ AnyDriver, DriverFactory };
-use drivers::driver::DriverConnection;
+use drivers::factory::AnyDriver;
+use drivers::driver::{ execute_query, ConnectTimeoutError, DriverConnection };
+use drivers::redact::redact;
+use drivers::sql::OrderBy;
+use futures::future::BoxFuture;
+use std::ops::{ Deref, DerefMut };
+use std::time::Duration;
+use tokio::sync::OwnedMutexGuard;
+use tracing::warn;
+
+/// Convert the error of a request to a datasource into a server error.
+///
+/// The errors raised by the agent (e.g. datasource not found) are kept, the connect timeouts are reported as gateway
+/// timeouts, while the other errors of the driver are reported as invalid parameters (with the credentials redacted).
+fn to_driver_error(e: anyhow::Error) -> Error {
+    if e.downcast_ref::<ConnectTimeoutError>().is_some() {
+        Error::GatewayTimeout
+    } else if e.downcast_ref::<UserError>().is_some() {
+        e.into()
+    } else {
+        UserError::InvalidParameter(redact(&e.to_string())).into()
+    }
+}
 
 /// GET /connections/defaults
 ///
@@ -12,21 +49,484 @@ async fn get_connection_defaults() -> ServerResult<Json<Connection>> {
     Ok(Json(Connection::new("New Connection".to_string())))
 }
 
+/// POST /connections/from-uri
+///
+/// Create an new instance of a connection from a URI (or the path of a SQLite database file), the driver being selected
+/// from the scheme of the URI. The connection is not saved.
+async fn create_connection_from_uri(
+    Json(request): Json<ConnectionFromUriRequest>
+) -> ServerResult<Json<Connection>> {
+    Ok(Json(Connection::from_uri(&request.uri)?))
+}
+
 /// POST /connections/test
 ///
 /// Test if the connection is valid (can connect to the datasource).
-async fn test_connection(Json(conn): Json<Connection>) -> ServerResult<()> {
-    let connection_string = conn.to_connection_string()?;
-    let mut driver = AnyDriver::new(DriverFactory::create(&conn.driver, connection_string)?);
-    match driver.connect().await {
-        Ok(_) => Ok(()),
-        Err(e) => Err(UserError::InvalidParameter(e.to_string()).into()),
+///
+/// Returns the information about the server (version, edition & features) so the client can tailor the SQL it
+/// generates. If the connection has already been saved, the information is saved along with it.
+async fn test_connection(
+    context: ServerResult<RequestContext>,
+    Json(mut conn): Json<Connection>
+) -> ServerResult<Json<ConnectionInfo>> {
+    let context = context?;
+    let username = validators::sanitize_username(context.get_username())?;
+    conn.render(&username)?;
+    let mut driver = conn.create_driver()?;
+    driver.connect().await.map_err(to_driver_error)?;
+    let info = driver.get_info().await;
+    let _ = driver.close().await;
+    let info: ConnectionInfo = info.map_err(to_driver_error)?.into();
+    if let Err(e) = connections::save_connection_info(&username, &conn.id, info.clone()) {
+        warn!("Unable to save the information about the server of the connection '{}': {}", conn.id, e);
+    }
+    Ok(Json(info))
+}
+
+/// POST /connections/preview-uri
+///
+/// Render the connection string of a draft connection with the variables of an environment (the one of the request,
+/// or else the one of the connection), so templated connections can be verified before being saved. The secrets are
+/// masked, the variables that cannot be resolved and the errors in the templates are reported.
+async fn preview_connection_uri(
+    context: ServerResult<RequestContext>,
+    Json(request): Json<ConnectionPreviewRequest>
+) -> ServerResult<Json<EnvironmentPreview>> {
+    let context = context?;
+    let username = validators::sanitize_username(context.get_username())?;
+    let variables = match request.environment.as_ref().or(request.connection.environment.as_ref()) {
+        Some(id) => environments::get_environment(&username, id)?.variables,
+        None => Vec::new(),
+    };
+    match environments::preview_connection(request.connection, &variables, &username) {
+        Ok(preview) => Ok(Json(preview)),
+        Err(e) => Err(to_driver_error(e)),
+    }
+}
+
+/// Record the usage of a connection.
+///
+/// Failing to record the usage must not fail the request, so the error is only logged.
+fn record_usage(username: &validators::Username, id: &str) {
+    if let Err(e) = connections::record_connection_usage(username, id) {
+        warn!("Unable to record the usage of the connection '{}': {}", id, e);
+    }
+}
+
+/// POST /connections/:id/tables/peek
+///
+/// Get the first rows of a table (e.g. to preview the data from the catalog tree).
+/// The number of rows is limited to `PEEK_MAX_ROWS`. If a transaction is in progress on the connection, the rows are
+/// read within the transaction.
+async fn peek_table(
+    State(state): State<ServerState>,
+    context: ServerResult<RequestContext>,
+    Path(id): Path<String>,
+    Json(request): Json<PeekTableRequest>
+) -> ServerResult<Json<PeekTableResult>> {
+    let context = context?;
+    let username = validators::sanitize_username(context.get_username())?;
+    let connection = connections::get_connection(&username, &id)?;
+    let limit = request.limit.unwrap_or(PEEK_DEFAULT_ROWS).min(PEEK_MAX_ROWS);
+    let result = match state.get_transaction(&ServerState::get_transaction_key(&username, &connection.id)) {
+        Some(driver) => tables::fetch_table_rows(&mut *driver.lock().await, &request.table, &[], 0, limit).await,
+        None => tables::peek_table(&connection, &request.table, limit).await,
+    };
+    match result {
+        Ok(rows) => {
+            record_usage(&username, &connection.id);
+            Ok(Json(PeekTableResult { rows }))
+        }
+        Err(e) => Err(to_driver_error(e)),
+    }
+}
+
+/// Query parameters for the rows of a table.
+#[derive(serde::Deserialize)]
+struct TableRowsQueryParameters {
+    schema: Option<String>,
+    #[serde(default)]
+    offset: u64,
+    limit: Option<u64>,
+    order_by: Option<String>,
+}
+
+/// Parse the `order_by` query parameter.
+///
+/// The value is a comma separated list of columns, a column prefixed with `-` is sorted in descending order
+/// (e.g. `name,-created_at`).
+fn parse_order_by(order_by: &str) -> ServerResult<Vec<OrderBy>> {
+    order_by
+        .split(',')
+        .map(|column| {
+            let (column, descending) = match column.trim().strip_prefix('-') {
+                Some(column) => (column, true),
+                None => (column.trim(), false),
+            };
+            if column.is_empty() {
+                return Err(UserError::InvalidParameter(format!("Invalid order_by: '{}'.", order_by)).into());
+            }
+            Ok(OrderBy { column: column.to_string(), descending })
+        })
+        .collect()
+}
+
+/// GET /connections/:id/tables/:table/rows?schema=...&offset=...&limit=...&order_by=...
+///
+/// Get a page of rows from a table.
+/// The paging is pushed down to the datasource using its own dialect and the rows are not stored in the history.
+/// The number of rows is limited to `PEEK_MAX_ROWS`. If a transaction is in progress on the connection, the rows are
+/// read within the transaction.
+async fn get_table_rows(
+    State(state): State<ServerState>,
+    context: ServerResult<RequestContext>,
+    Path((id, table)): Path<(String, String)>,
+    Query(params): Query<TableRowsQueryParameters>
+) -> ServerResult<Json<PeekTableResult>> {
+    let context = context?;
+    let username = validators::sanitize_username(context.get_username())?;
+    let order_by = match params.order_by.as_deref() {
+        Some(order_by) if !order_by.is_empty() => parse_order_by(order_by)?,
+        _ => Vec::new(),
+    };
+    let connection = connections::get_connection(&username, &id)?;
+    let table = TableReference { schema: params.schema, table };
+    let limit = params.limit.unwrap_or(PEEK_DEFAULT_ROWS).min(PEEK_MAX_ROWS);
+    let result = match state.get_transaction(&ServerState::get_transaction_key(&username, &connection.id)) {
+        Some(driver) => {
+            tables::fetch_table_rows(&mut *driver.lock().await, &table, &order_by, params.offset, limit).await
+        }
+        None => tables::get_table_rows(&connection, &table, &order_by, params.offset, limit).await,
+    };
+    match result {
+        Ok(rows) => {
+            record_usage(&username, &connection.id);
+            Ok(Json(PeekTableResult { rows }))
+        }
+        Err(e) => Err(to_driver_error(e)),
+    }
+}
+
+/// POST /connections/:id/plan
+///
+/// Get the plan of a query (without executing it), normalized into the same tree of nodes whatever the driver.
+/// If a transaction is in progress on the connection, the query is explained within the transaction.
+async fn get_query_plan(
+    State(state): State<ServerState>,
+    context: ServerResult<RequestContext>,
+    Path(id): Path<String>,
+    Json(request): Json<QueryPlanRequest>
+) -> ServerResult<Json<QueryPlanNode>> {
+    let context = context?;
+    let username = validators::sanitize_username(context.get_username())?;
+    let connection = connections::get_connection(&username, &id)?;
+    let result = match state.get_transaction(&ServerState::get_transaction_key(&username, &connection.id)) {
+        Some(driver) => plans::fetch_query_plan(&mut *driver.lock().await, &request.query).await,
+        None => plans::get_query_plan(&connection, &request.query).await,
+    };
+    match result {
+        Ok(plan) => {
+            record_usage(&username, &connection.id);
+            Ok(Json(plan))
+        }
+        Err(e) => Err(to_driver_error(e)),
+    }
+}
+
+/// The driver used by a catalog request.
+enum CatalogDriver {
+    /// The driver pinned to the transaction in progress on the connection.
+    Pinned(OwnedMutexGuard<AnyDriver>),
+
+    /// A driver connected for the request only.
+    Connected(AnyDriver),
+}
+
+impl CatalogDriver {
+    /// Release the driver, closing it unless it is pinned to a transaction.
+    async fn close(self) {
+        if let CatalogDriver::Connected(mut driver) = self {
+            let _ = driver.close().await;
+        }
+    }
+}
+
+impl Deref for CatalogDriver {
+    type Target = AnyDriver;
+
+    fn deref(&self) -> &AnyDriver {
+        match self {
+            CatalogDriver::Pinned(driver) => driver,
+            CatalogDriver::Connected(driver) => driver,
+        }
+    }
+}
+
+impl DerefMut for CatalogDriver {
+    fn deref_mut(&mut self) -> &mut AnyDriver {
+        match self {
+            CatalogDriver::Pinned(driver) => driver,
+            CatalogDriver::Connected(driver) => driver,
+        }
+    }
+}
+
+/// Get a driver on a datasource of a connection for a catalog request.
+///
+/// If a transaction is in progress on the default datasource of the connection, its pinned driver is used so the
+/// objects created within the transaction are visible. Otherwise the datasource is connected for the request.
+async fn connect_datasource(
+    state: &ServerState,
+    username: &validators::Username,
+    id: &str,
+    datasource: &str
+) -> ServerResult<CatalogDriver> {
+    let connection = connections::get_connection(username, id)?;
+    if connection.datasource == datasource {
+        if let Some(driver) = state.get_transaction(&ServerState::get_transaction_key(username, &connection.id)) {
+            return Ok(CatalogDriver::Pinned(driver.lock_owned().await));
+        }
+    }
+    let driver = tables::connect_datasource(connection, datasource).await.map_err(to_driver_error)?;
+    Ok(CatalogDriver::Connected(driver))
+}
+
+/// Run a catalog request on a datasource of a connection.
+///
+/// The driver is released once the request is done (see `CatalogDriver::close`) and the usage of the connection is
+/// recorded if the request succeeded.
+async fn fetch_catalog<T>(
+    state: &ServerState,
+    context: ServerResult<RequestContext>,
+    id: &str,
+    datasource: &str,
+    fetch: impl for<'a> FnOnce(&'a mut AnyDriver) -> BoxFuture<'a, anyhow::Result<T>>
+) -> ServerResult<Json<T>> {
+    let context = context?;
+    let username = validators::sanitize_username(context.get_username())?;
+    let mut driver = connect_datasource(state, &username, id, datasource).await?;
+    let result = fetch(&mut *driver).await;
+    driver.close().await;
+    let items = result.map_err(to_driver_error)?;
+    record_usage(&username, id);
+    Ok(Json(items))
+}
+
+/// GET /connections/:id/datasources/:ds/schemas
+///
+/// Get the schemas of a datasource.
+async fn get_schemas(
+    State(state): State<ServerState>,
+    context: ServerResult<RequestContext>,
+    Path((id, datasource)): Path<(String, String)>
+) -> ServerResult<Json<Vec<SchemaInfo>>> {
+    fetch_catalog(&state, context, &id, &datasource, |driver| Box::pin(tables::list_schemas(driver))).await
+}
+
+/// Query parameters for the tables of a datasource.
+#[derive(serde::Deserialize)]
+struct TablesQueryParameters {
+    schema: Option<String>,
+}
+
+/// GET /connections/:id/datasources/:ds/tables?schema=...
+///
+/// Get the tables and views of a datasource (only the ones of the given schema if any).
+async fn get_tables(
+    State(state): State<ServerState>,
+    context: ServerResult<RequestContext>,
+    Path((id, datasource)): Path<(String, String)>,
+    Query(params): Query<TablesQueryParameters>
+) -> ServerResult<Json<Vec<TableInfo>>> {
+    fetch_catalog(&state, context, &id, &datasource, move |driver| {
+        Box::pin(async move { tables::list_tables(driver, params.schema.as_deref()).await })
+    }).await
+}
+
+/// GET /connections/:id/datasources/:ds/columns?schema=...&table=...
+///
+/// Get the columns of a table (or a view) of a datasource.
+async fn get_columns(
+    State(state): State<ServerState>,
+    context: ServerResult<RequestContext>,
+    Path((id, datasource)): Path<(String, String)>,
+    Query(table): Query<TableReference>
+) -> ServerResult<Json<Vec<ColumnInfo>>> {
+    fetch_catalog(&state, context, &id, &datasource, move |driver| {
+        Box::pin(async move { tables::list_columns(driver, &table).await })
+    }).await
+}
+
+/// POST /connections/:id/transactions/begin
+///
+/// Start a transaction on a connection.
+///
+/// A connection to the datasource is pinned to the transaction and used by the following requests of the user on the
+/// same connection until the transaction is committed or rolled back. Only one transaction can be in progress per user
+/// and connection.
+async fn begin_transaction(
+    State(state): State<ServerState>,
+    context: ServerResult<RequestContext>,
+    Path(id): Path<String>
+) -> ServerResult<()> {
+    let context = context?;
+    let username = validators::sanitize_username(context.get_username())?;
+    let connection = connections::get_connection(&username, &id)?;
+    let key = ServerState::get_transaction_key(&username, &connection.id);
+    if state.get_transaction(&key).is_some() {
+        return Err(err_conflict!("A transaction is already in progress on the connection '{}'.", connection.id));
+    }
+
+    let mut driver = connection.create_driver()?;
+    driver.connect().await.map_err(to_driver_error)?;
+    if let Err(e) = execute_query(&mut driver, "BEGIN").await {
+        let _ = driver.close().await;
+        return Err(to_driver_error(e));
+    }
+    if !state.add_transaction(&key, driver) {
+        // Another transaction has been started concurrently, dropping the connection rolls back this one.
+        return Err(err_conflict!("A transaction is already in progress on the connection '{}'.", connection.id));
+    }
+    record_usage(&username, &connection.id);
+    Ok(())
+}
+
+/// POST /connections/:id/transactions/execute
+///
+/// Execute a statement within the transaction in progress on a connection.
+/// The number of rows returned is limited to `PEEK_MAX_ROWS`.
+///
+/// The statements writing data on a connection labeled as production must be confirmed (see
+/// `transactions::check_statement_confirmation`).
+async fn execute_statement(
+    State(state): State<ServerState>,
+    context: ServerResult<RequestContext>,
+    Path(id): Path<String>,
+    Json(request): Json<ExecuteStatementRequest>
+) -> ServerResult<Json<ExecuteStatementResult>> {
+    let context = context?;
+    let username = validators::sanitize_username(context.get_username())?;
+    let connection = connections::get_connection(&username, &id)?;
+    transactions::check_statement_confirmation(&connection, &request.statement, request.confirmed)?;
+    let Some(driver) = state.get_transaction(&ServerState::get_transaction_key(&username, &id)) else {
+        return Err(err_not_found!("No transaction in progress on the connection '{}'.", id));
+    };
+    let limit = request.limit.unwrap_or(PEEK_DEFAULT_ROWS).min(PEEK_MAX_ROWS);
+    match transactions::execute_statement(&mut *driver.lock().await, &request.statement, limit).await {
+        Ok(result) => {
+            record_usage(&username, &id);
+            Ok(Json(result))
+        }
+        Err(e) => Err(to_driver_error(e)),
+    }
+}
+
+/// End the transaction in progress on a connection with the given statement (COMMIT or ROLLBACK).
+///
+/// The connection pinned to the transaction is closed whether the statement succeeds or not.
+async fn end_transaction(
+    state: ServerState,
+    context: ServerResult<RequestContext>,
+    id: &str,
+    statement: &str
+) -> ServerResult<()> {
+    let context = context?;
+    let username = validators::sanitize_username(context.get_username())?;
+    let Some(driver) = state.remove_transaction(&ServerState::get_transaction_key(&username, id)) else {
+        return Err(err_not_found!("No transaction in progress on the connection '{}'.", id));
+    };
+    let result = transactions::end_transaction(&mut *driver.lock().await, statement).await;
+    result.map_err(to_driver_error)
+}
+
+/// POST /connections/:id/transactions/commit
+///
+/// Commit the transaction in progress on a connection.
+async fn commit_transaction(
+    State(state): State<ServerState>,
+    context: ServerResult<RequestContext>,
+    Path(id): Path<String>
+) -> ServerResult<()> {
+    end_transaction(state, context, &id, "COMMIT").await
+}
+
+/// POST /connections/:id/transactions/rollback
+///
+/// Roll back the transaction in progress on a connection.
+async fn rollback_transaction(
+    State(state): State<ServerState>,
+    context: ServerResult<RequestContext>,
+    Path(id): Path<String>
+) -> ServerResult<()> {
+    end_transaction(state, context, &id, "ROLLBACK").await
+}
+
+/// Roll back the transactions that have not been used for `transaction_idle_timeout`.
+///
+/// This task runs as long as the server, so the transactions left open by a client that went away do not keep their
+/// connection to the datasource until the agent is restarted.
+pub async fn expire_idle_transactions(state: ServerState) {
+    let mut interval = tokio::time::interval(Duration::from_secs(TRANSACTION_EXPIRY_CHECK_INTERVAL));
+    loop {
+        interval.tick().await;
+        for (key, driver) in state.remove_idle_transactions(settings::get_transaction_idle_timeout()) {
+            warn!("The transaction '{}' has been idle for too long, rolling back.", key);
+            if let Err(e) = transactions::end_transaction(&mut *driver.lock().await, "ROLLBACK").await {
+                warn!("Unable to roll back the transaction '{}': {}", key, redact(&e.to_string()));
+            }
+        }
+    }
+}
+
+/// Write the usage of the connections recorded in memory into the usage files every `CONNECTIONS_USAGE_FLUSH_INTERVAL`.
+///
+/// This task runs as long as the server, the files are written on a blocking thread so the requests being processed
+/// are not delayed.
+pub async fn flush_connections_usage() {
+    let mut interval = tokio::time::interval(Duration::from_secs(CONNECTIONS_USAGE_FLUSH_INTERVAL));
+    loop {
+        interval.tick().await;
+        match tokio::task::spawn_blocking(connections::flush_connections_usage).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => warn!("Unable to write the usage of the connections: {}", e),
+            Err(e) => warn!("Unable to write the usage of the connections: {}", e),
+        }
     }
 }
 
 pub fn authenticated_routes(state: ServerState) -> Router {
     Router::new()
         .route("/connections/defaults", get(get_connection_defaults))
+        .route("/connections/from-uri", post(create_connection_from_uri))
         .route("/connections/test", post(test_connection))
+        .route("/connections/preview-uri", post(preview_connection_uri))
+        .route("/connections/:id/tables/peek", post(peek_table))
+        .route("/connections/:id/tables/:table/rows", get(get_table_rows))
+        .route("/connections/:id/plan", post(get_query_plan))
+        .route("/connections/:id/datasources/:ds/schemas", get(get_schemas))
+        .route("/connections/:id/datasources/:ds/tables", get(get_tables))
+        .route("/connections/:id/datasources/:ds/columns", get(get_columns))
+        .route("/connections/:id/transactions/begin", post(begin_transaction))
+        .route("/connections/:id/transactions/execute", post(execute_statement))
+        .route("/connections/:id/transactions/commit", post(commit_transaction))
+        .route("/connections/:id/transactions/rollback", post(rollback_transaction))
         .with_state(state)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_order_by() {
+        assert_eq!(
+            parse_order_by("name, -id").unwrap(),
+            vec![
+                OrderBy { column: "name".to_string(), descending: false },
+                OrderBy { column: "id".to_string(), descending: true }
+            ]
+        );
+        assert!(parse_order_by("name,").is_err());
+        assert!(parse_order_by("-").is_err());
+    }
+}
@@ -4,6 +4,7 @@ use axum::response::{ IntoResponse, Response };
 use axum::http::StatusCode;
 use axum::body::Body;
 use anyhow::Error as AnyhowError;
+use drivers::redact::redact;
 use tracing::error;
 
 use crate::utils::user_error::UserError;
@@ -17,6 +18,10 @@ pub enum Error {
     BadRequest(String),
     InternalServerError,
     UnprocessableEntity(String),
+    RequestTimeout,
+    GatewayTimeout,
+    /// Too many authentication failures, the client must wait for the given number of seconds.
+    TooManyRequests(u64),
     UserError(UserError),
 }
 
@@ -28,6 +33,14 @@ impl IntoResponse for Error {
             Error::InternalServerError => (StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error").into_response(),
             Error::UnprocessableEntity(reason) =>
                 (StatusCode::UNPROCESSABLE_ENTITY, format!("Unprocessable Entity: {}", reason)).into_response(),
+            Error::RequestTimeout => (StatusCode::REQUEST_TIMEOUT, "Request Timeout").into_response(),
+            Error::GatewayTimeout => (StatusCode::GATEWAY_TIMEOUT, "Gateway Timeout").into_response(),
+            Error::TooManyRequests(retry_after) =>
+                (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    [(axum::http::header::RETRY_AFTER, retry_after.to_string())],
+                    "Too Many Requests",
+                ).into_response(),
             Error::UserError(user_error) => user_error.into_response(),
         }
     }
@@ -39,7 +52,7 @@ impl IntoResponse for Error {
 /// The first `UserError` is extracted from the `anyhow::Error` chain of causes and returned as the `Error::UserError`
 /// variant if present, otherwise the `Error::InternalServerError` is returned.
 ///
-/// In the meantime, all the causes are logged using the `tracing` crate.
+/// In the meantime, all the causes are logged using the `tracing` crate (with credentials redacted).
 impl From<anyhow::Error> for Error {
     fn from(err: AnyhowError) -> Self {
         let mut user_error: Option<UserError> = None;
@@ -47,7 +60,8 @@ impl From<anyhow::Error> for Error {
             if user_error.is_none() && cause.downcast_ref::<UserError>().is_some() {
                 user_error = cause.downcast_ref::<UserError>().cloned();
             }
-            error!("{}", cause);
+            // The causes may come from a driver and contain a connection string.
+            error!("{}", redact(&cause.to_string()));
         }
         match user_error {
             None => Error::InternalServerError,
@@ -83,6 +97,15 @@ impl IntoResponse for UserError {
                     },
                     StatusCode::CONFLICT,
                 ),
+            UserError::ConfirmationRequired(message) =>
+                (
+                    ResponseError {
+                        status: StatusCode::PRECONDITION_REQUIRED.as_u16(),
+                        code: "confirmation_required".to_string(),
+                        message,
+                    },
+                    StatusCode::PRECONDITION_REQUIRED,
+                ),
             UserError::NotFound(message) =>
                 (
                     ResponseError {
@@ -1,20 +1,35 @@
-use crate::models::connections::Connection;
+use crate::models::auth::{ AccessToken, AccessTokenRequest, NewAccessToken };
+use crate::models::connections::{ Connection, ConnectionUsageReport };
+use crate::models::environments::{ EnvironmentPreview, EnvironmentPreviewRequest };
+use crate::models::macros::{ KeyboardMacro, KeyboardMacroChange, KeyboardMacroRequest };
+use crate::models::queries::SavedQuery;
 use crate::models::users::UserSettings;
+use crate::resources::access_tokens;
 use crate::resources::catalog;
-use crate::resources::catalog::CatalogEntry;
+use crate::resources::connections;
+use crate::resources::environments;
+use crate::resources::macros;
+use crate::resources::queries;
+use crate::models::collections::CatalogEntry;
 use crate::resources::catalog::CatalogSection;
+use crate::resources::catalog::NameConflict;
+use crate::resources::samples;
 use crate::resources::users;
 use crate::utils::validators;
 use crate::server::context::RequestContext;
 use crate::api::error::ServerResult;
 use crate::api::error::Error;
-use crate::models::users::User;
+use crate::models::users::{ OfflineSnapshot, User };
 use crate::server::state::ServerState;
 use anyhow::Context;
+use axum::routing::delete;
 use axum::routing::post;
 use axum::routing::put;
 use axum::{ Json, Router, routing::get };
 use axum::extract::{ Path, Query };
+use axum::response::sse::{ Event, KeepAlive, Sse };
+use futures::{ Stream, StreamExt };
+use tokio::sync::broadcast::error::RecvError;
 use serde_json::Value;
 
 /// GET /users/:username/user
@@ -41,6 +56,28 @@ async fn get_user(context: ServerResult<RequestContext>, Path(username): Path<St
     }
 }
 
+/// GET /users/:username/offline-snapshot
+///
+/// Get a compact snapshot of the user data (user, settings & whole catalog) that the client can keep to render a
+/// read-only state while the agent is unreachable.
+async fn get_offline_snapshot(
+    context: ServerResult<RequestContext>,
+    Path(username): Path<String>
+) -> ServerResult<Json<OfflineSnapshot>> {
+    let username = validators::sanitize_username(username.as_str())?;
+
+    // As for now, the user can only get its own data.
+    if username.ne(context?.get_username()) {
+        return Err(Error::Forbidden);
+    }
+
+    let snapshot = users
+        ::get_offline_snapshot(&username)
+        .with_context(|| { format!("Unable to take the offline snapshot for the user '{}'.", username) })?;
+
+    Ok(Json(snapshot))
+}
+
 /// Query parameters for the catalog.
 #[derive(serde::Deserialize)]
 struct CatalogQueryParameters {
@@ -50,8 +87,8 @@ struct CatalogQueryParameters {
 /// GET /users/:username/catalog?path=...
 ///
 /// List all catalog entries for the specified user and path.
-/// The path is relative to the user's catalog directory and must stat with either `environments`, `workspaces` or
-/// `favorites`.
+/// The path is relative to the user's catalog directory and must stat with either `connections`, `environments`,
+/// `workspaces`, `queries` or `favorites`.
 ///
 /// ```text
 /// └── users
@@ -92,6 +129,71 @@ async fn read_user_catalog(
     Ok(Json(entries))
 }
 
+/// GET /users/:username/catalog/conflicts
+///
+/// List the connection names used more than once in the user's catalog (whatever the folder they are stored in).
+/// Those conflicts are not allowed when the setting `catalog_strict_names` is enabled, but may exist if the catalog was
+/// populated before enabling it.
+async fn read_user_catalog_conflicts(
+    context: ServerResult<RequestContext>,
+    Path(username): Path<String>
+) -> ServerResult<Json<Vec<NameConflict>>> {
+    let username = validators::sanitize_username(username.as_str())?;
+
+    // Make sure we are not trying to access the catalog of another user.
+    if username.ne(context?.get_username()) {
+        return Err(Error::Forbidden);
+    }
+
+    let conflicts = catalog
+        ::find_name_conflicts(&username, &CatalogSection::Connections)
+        .with_context(|| { format!("Unable to read the catalog conflicts for the user '{}'.", username) })?;
+
+    Ok(Json(conflicts))
+}
+
+/// GET /users/:username/connections/usage
+///
+/// Get the usage statistics of all the connections of the user (last time used & number of times used).
+/// The least recently used connections come first to help identifying the stale connections that can be cleaned up.
+async fn read_user_connections_usage(
+    context: ServerResult<RequestContext>,
+    Path(username): Path<String>
+) -> ServerResult<Json<Vec<ConnectionUsageReport>>> {
+    let username = validators::sanitize_username(username.as_str())?;
+
+    // Make sure we are not trying to access the connections of another user.
+    if username.ne(context?.get_username()) {
+        return Err(Error::Forbidden);
+    }
+
+    let report = connections
+        ::get_connections_usage(&username)
+        .with_context(|| { format!("Unable to read the connections usage for the user '{}'.", username) })?;
+
+    Ok(Json(report))
+}
+
+/// POST /users/:username/environments/:id/preview
+///
+/// Render a template (e.g. a connection URI) with the variables of an environment, so variable substitution problems
+/// can be investigated without attempting to connect. The secrets are masked in the result.
+async fn preview_user_environment(
+    context: ServerResult<RequestContext>,
+    Path((username, id)): Path<(String, String)>,
+    Json(request): Json<EnvironmentPreviewRequest>
+) -> ServerResult<Json<EnvironmentPreview>> {
+    let username = validators::sanitize_username(username.as_str())?;
+
+    // Make sure we are not trying to access the environments of another user.
+    if username.ne(context?.get_username()) {
+        return Err(Error::Forbidden);
+    }
+
+    let environment = environments::get_environment(&username, &id)?;
+    Ok(Json(environments::preview(&request.template, &environment.variables)))
+}
+
 #[derive(serde::Deserialize)]
 struct RenameUserCatalogEntry {
     new_name: String,
@@ -183,6 +285,15 @@ async fn create_user_resource(
                 Err(reason) => Err(Error::UnprocessableEntity(reason.to_string())),
             }
         }
+        CatalogSection::Queries => {
+            match serde_json::from_value::<SavedQuery>(resource.0) {
+                Ok(query) => {
+                    let catalog_entry = users::create_user_resource(&username, &catalog_path, &query)?;
+                    Ok(Json(catalog_entry))
+                }
+                Err(reason) => Err(Error::UnprocessableEntity(reason.to_string())),
+            }
+        }
         CatalogSection::Environments => {
             todo!();
         }
@@ -195,19 +306,269 @@ async fn create_user_resource(
     }
 }
 
+/// GET /users/:username/queries/:id
+///
+/// Get a query saved in the user's catalog.
+async fn get_user_saved_query(
+    context: ServerResult<RequestContext>,
+    Path((username, id)): Path<(String, String)>
+) -> ServerResult<Json<SavedQuery>> {
+    let username = validators::sanitize_username(username.as_str())?;
+
+    // Make sure we are not trying to access the queries of another user.
+    if username.ne(context?.get_username()) {
+        return Err(Error::Forbidden);
+    }
+
+    let query = queries
+        ::get_saved_query(&username, &id)
+        .with_context(|| { format!("Unable to read the query '{}' for the user '{}'.", id, username) })?;
+
+    Ok(Json(query))
+}
+
+/// POST /users/:username/samples/reset
+///
+/// Reset the sample database of the user to its initial state.
+/// If the sample connection has been deleted from the catalog, it is created again.
+async fn reset_user_samples(context: ServerResult<RequestContext>, Path(username): Path<String>) -> ServerResult<()> {
+    let username = validators::sanitize_username(username.as_str())?;
+
+    // Make sure we are not trying to reset the samples of another user.
+    if username.ne(context?.get_username()) {
+        return Err(Error::Forbidden);
+    }
+
+    samples
+        ::reset_sample_connection(&username).await
+        .with_context(|| { format!("Unable to reset the samples for the user '{}'.", username) })?;
+
+    Ok(())
+}
+
+/// GET /users/:username/tokens
+///
+/// Get the personal access tokens of the user (without the tokens themselves).
+async fn list_user_access_tokens(
+    context: ServerResult<RequestContext>,
+    Path(username): Path<String>
+) -> ServerResult<Json<Vec<AccessToken>>> {
+    let username = validators::sanitize_username(username.as_str())?;
+
+    // Make sure we are not trying to access the tokens of another user.
+    // The access tokens can only be managed from an interactive session, not with another access token.
+    let context = context?;
+    if username.ne(context.get_username()) || context.is_access_token() {
+        return Err(Error::Forbidden);
+    }
+
+    let access_tokens = access_tokens
+        ::list_access_tokens(&username)
+        .with_context(|| { format!("Unable to read the access tokens for the user '{}'.", username) })?;
+
+    Ok(Json(access_tokens))
+}
+
+/// POST /users/:username/tokens
+///
+/// Create a personal access token for the user.
+/// The token is only returned by this endpoint and cannot be retrieved later on.
+async fn create_user_access_token(
+    context: ServerResult<RequestContext>,
+    Path(username): Path<String>,
+    Json(request): Json<AccessTokenRequest>
+) -> ServerResult<Json<NewAccessToken>> {
+    let username = validators::sanitize_username(username.as_str())?;
+
+    // Make sure we are not trying to create a token for another user.
+    // The access tokens can only be managed from an interactive session, not with another access token.
+    let context = context?;
+    if username.ne(context.get_username()) || context.is_access_token() {
+        return Err(Error::Forbidden);
+    }
+
+    let new_access_token = access_tokens
+        ::create_access_token(&username, request)
+        .with_context(|| { format!("Unable to create an access token for the user '{}'.", username) })?;
+
+    Ok(Json(new_access_token))
+}
+
+/// DELETE /users/:username/tokens/:id
+///
+/// Revoke a personal access token of the user.
+async fn revoke_user_access_token(
+    context: ServerResult<RequestContext>,
+    Path((username, id)): Path<(String, String)>
+) -> ServerResult<()> {
+    let username = validators::sanitize_username(username.as_str())?;
+
+    // Make sure we are not trying to revoke a token of another user.
+    // The access tokens can only be managed from an interactive session, not with another access token.
+    let context = context?;
+    if username.ne(context.get_username()) || context.is_access_token() {
+        return Err(Error::Forbidden);
+    }
+
+    access_tokens
+        ::revoke_access_token(&username, &id)
+        .with_context(|| { format!("Unable to revoke the access token '{}' of the user '{}'.", id, username) })?;
+
+    Ok(())
+}
+
+/// GET /users/:username/macros
+///
+/// Get the keyboard macros of the user.
+async fn list_user_macros(
+    context: ServerResult<RequestContext>,
+    Path(username): Path<String>
+) -> ServerResult<Json<Vec<KeyboardMacro>>> {
+    let username = validators::sanitize_username(username.as_str())?;
+
+    // Make sure we are not trying to access the macros of another user.
+    if username.ne(context?.get_username()) {
+        return Err(Error::Forbidden);
+    }
+
+    let macros = macros
+        ::list_macros(&username)
+        .with_context(|| { format!("Unable to read the macros for the user '{}'.", username) })?;
+
+    Ok(Json(macros))
+}
+
+/// GET /users/:username/macros/events
+///
+/// Watch the changes of the keyboard macros of the user (server-sent events), so the macros created, updated or
+/// deleted from another machine are applied without polling.
+async fn watch_user_macros(
+    context: ServerResult<RequestContext>,
+    Path(username): Path<String>
+) -> ServerResult<Sse<impl Stream<Item = Result<Event, axum::Error>>>> {
+    let username = validators::sanitize_username(username.as_str())?;
+
+    // Make sure we are not trying to watch the macros of another user.
+    if username.ne(context?.get_username()) {
+        return Err(Error::Forbidden);
+    }
+
+    let changes = futures::stream::unfold(macros::subscribe_changes(), move |mut receiver| {
+        let username = username.to_string();
+        async move {
+            loop {
+                match receiver.recv().await {
+                    Ok((owner, change)) if owner == username => {
+                        return Some((change, receiver));
+                    }
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(_)) => {
+                        return Some((KeyboardMacroChange::Reset, receiver));
+                    }
+                    Err(RecvError::Closed) => {
+                        return None;
+                    }
+                }
+            }
+        }
+    });
+
+    Ok(Sse::new(changes.map(|change| Event::default().json_data(change))).keep_alive(KeepAlive::default()))
+}
+
+/// POST /users/:username/macros
+///
+/// Create a keyboard macro for the user.
+async fn create_user_macro(
+    context: ServerResult<RequestContext>,
+    Path(username): Path<String>,
+    Json(request): Json<KeyboardMacroRequest>
+) -> ServerResult<Json<KeyboardMacro>> {
+    let username = validators::sanitize_username(username.as_str())?;
+
+    // Make sure we are not trying to create a macro for another user.
+    if username.ne(context?.get_username()) {
+        return Err(Error::Forbidden);
+    }
+
+    let keyboard_macro = macros
+        ::create_macro(&username, request)
+        .with_context(|| { format!("Unable to create a macro for the user '{}'.", username) })?;
+
+    Ok(Json(keyboard_macro))
+}
+
+/// PUT /users/:username/macros/:id
+///
+/// Update a keyboard macro of the user.
+async fn update_user_macro(
+    context: ServerResult<RequestContext>,
+    Path((username, id)): Path<(String, String)>,
+    Json(request): Json<KeyboardMacroRequest>
+) -> ServerResult<Json<KeyboardMacro>> {
+    let username = validators::sanitize_username(username.as_str())?;
+
+    // Make sure we are not trying to update a macro of another user.
+    if username.ne(context?.get_username()) {
+        return Err(Error::Forbidden);
+    }
+
+    let keyboard_macro = macros
+        ::update_macro(&username, &id, request)
+        .with_context(|| { format!("Unable to update the macro '{}' of the user '{}'.", id, username) })?;
+
+    Ok(Json(keyboard_macro))
+}
+
+/// DELETE /users/:username/macros/:id
+///
+/// Delete a keyboard macro of the user.
+async fn delete_user_macro(
+    context: ServerResult<RequestContext>,
+    Path((username, id)): Path<(String, String)>
+) -> ServerResult<()> {
+    let username = validators::sanitize_username(username.as_str())?;
+
+    // Make sure we are not trying to delete a macro of another user.
+    if username.ne(context?.get_username()) {
+        return Err(Error::Forbidden);
+    }
+
+    macros
+        ::delete_macro(&username, &id)
+        .with_context(|| { format!("Unable to delete the macro '{}' of the user '{}'.", id, username) })?;
+
+    Ok(())
+}
+
 pub fn authenticated_routes(state: ServerState) -> Router {
     Router::new()
         .route("/users/:username/catalog", get(read_user_catalog))
         .route("/users/:username/catalog", post(create_user_resource))
+        .route("/users/:username/catalog/conflicts", get(read_user_catalog_conflicts))
         .route("/users/:username/catalog/rename", post(rename_user_catalog_entry))
+        .route("/users/:username/connections/usage", get(read_user_connections_usage))
+        .route("/users/:username/environments/:id/preview", post(preview_user_environment))
+        .route("/users/:username/macros", get(list_user_macros))
+        .route("/users/:username/macros", post(create_user_macro))
+        .route("/users/:username/macros/events", get(watch_user_macros))
+        .route("/users/:username/macros/:id", put(update_user_macro))
+        .route("/users/:username/macros/:id", delete(delete_user_macro))
+        .route("/users/:username/offline-snapshot", get(get_offline_snapshot))
+        .route("/users/:username/queries/:id", get(get_user_saved_query))
+        .route("/users/:username/samples/reset", post(reset_user_samples))
         .route("/users/:username/settings", put(save_user_settings))
+        .route("/users/:username/tokens", get(list_user_access_tokens))
+        .route("/users/:username/tokens", post(create_user_access_token))
+        .route("/users/:username/tokens/:id", delete(revoke_user_access_token))
         .route("/users/:username/user", get(get_user))
         .with_state(state)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::api::users::tests::catalog::{ CatalogEntryType, CatalogSection };
+    use crate::api::users::tests::catalog::CatalogSection;
+    use crate::models::collections::CatalogEntryType;
     use crate::resources::users::{ create_user, delete_user };
     use crate::server::state::ServerState;
     use crate::utils::constants::DEFAULT_WORKSPACE_NAME;
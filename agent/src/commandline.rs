@@ -1,4 +1,5 @@
 use std::net::Ipv4Addr;
+use std::path::PathBuf;
 use clap::{ Parser, Subcommand };
 use lazy_static::lazy_static;
 
@@ -45,17 +46,50 @@ pub enum Commands {
     /// Create a new user account.
     UserAdd {
         /// The username of the user to add.
-        username: String,
+        #[arg(required_unless_present = "from_csv")]
+        username: Option<String>,
+
+        /// Create a user account for each line of a CSV file having a `username` column.
+        #[arg(long, conflicts_with = "username")]
+        from_csv: Option<PathBuf>,
     },
 
     /// Delete a user account and all associated data.
     UserDel {
         /// The username of the user to delete.
         username: String,
+
+        /// Save the user data (settings, catalog, collections & data) into an archive (.tar.gz) before deleting it.
+        #[arg(long)]
+        archive: Option<PathBuf>,
+    },
+
+    /// Create a user account from the archive of a deleted user (see `user-del --archive`).
+    UserRestore {
+        /// The username of the user to create (may be different from the username of the archived user).
+        username: String,
+
+        /// The archive (.tar.gz) to restore.
+        archive: PathBuf,
     },
 
     /// Print the final configuration.
     ShowConfig,
+
+    /// Manage the configuration file.
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommands {
+    /// Check the configuration file, reporting invalid, unknown and deprecated settings.
+    Validate {
+        /// The configuration file to check (default: agent.conf in the app directory).
+        file: Option<PathBuf>,
+    },
 }
 
 lazy_static! {
@@ -2,6 +2,7 @@ mod api;
 mod commandline;
 mod models;
 mod resources;
+mod secrets;
 mod settings;
 mod server;
 mod utils;
@@ -10,10 +11,10 @@ use std::path::PathBuf;
 use common::pid_file::AgentStatus;
 use tracing::level_filters::LevelFilter;
 use tracing_appender::rolling;
-use tracing::{ error, Subscriber };
+use tracing::{ error, warn, Subscriber };
 use tracing_subscriber::{ self, filter::EnvFilter };
 use tracing_subscriber::{ prelude::*, Registry };
-use anyhow::{ Result, Context };
+use anyhow::{ anyhow, Result, Context };
 use utils::constants::ENV_VAR_LOG_LEVEL;
 use utils::validators::sanitize_username;
 use crate::server::web::Server;
@@ -83,8 +84,43 @@ fn get_tracing_subscriber(args: Option<&commandline::Args>) -> Result<Box<dyn Su
     Ok(Box::new(Registry::default().with(file_log).with(stdout_log)))
 }
 
+/// Create a user account for each username of a CSV file (see `user-add --from-csv`).
+///
+/// A user that cannot be created (e.g. already existing) does not prevent the others from being created.
+async fn add_users_from_csv(file: &std::path::Path) -> Result<()> {
+    let content = std::fs
+        ::read_to_string(file)
+        .with_context(|| format!("Unable to read the file: {}", file.display()))?;
+    let usernames = resources::users
+        ::read_usernames_from_csv(&content)
+        .with_context(|| format!("{}: invalid file", file.display()))?;
+    let mut failures = 0;
+    for username in usernames {
+        let result = match resources::users::create_user(&username) {
+            Ok(_) => resources::samples::create_sample_connection(&username).await.map(|_| ()),
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(_) => println!("The user '{}' has been created.", username),
+            Err(e) => {
+                println!("Unable to create the user '{}': {}", username, e);
+                failures += 1;
+            }
+        }
+    }
+    if failures > 0 {
+        return Err(anyhow!("{} user(s) could not be created.", failures));
+    }
+    Ok(())
+}
+
 /// Initialize and start the web server
 async fn run(args: &commandline::Args) -> Result<()> {
+    // the configuration must be validated before loading the settings that would fail on an invalid configuration.
+    if let commandline::Commands::Config { command: commandline::ConfigCommands::Validate { file } } = &args.command {
+        return settings::validate_config_file(file.as_deref());
+    }
+
     // we must be able to create files in the app directory (such as agent.pid, logs...).
     let app_dir = settings::get_app_dir();
     if !app_dir.exists() {
@@ -94,6 +130,9 @@ async fn run(args: &commandline::Args) -> Result<()> {
     }
     // now that the command line has been parsed, the app_directory exists we can initialize the tracing system.
     tracing::subscriber::set_global_default(get_tracing_subscriber(Some(args))?)?;
+    for warning in settings::get_warnings() {
+        warn!("{}", warning);
+    }
 
     match &args.command {
         commandline::Commands::Start { .. } => {
@@ -115,15 +154,32 @@ async fn run(args: &commandline::Args) -> Result<()> {
                 }
             }
         }
-        commandline::Commands::UserAdd { username } => {
-            resources::users::create_user(&sanitize_username(username)?)?;
+        commandline::Commands::UserAdd { from_csv: Some(file), .. } => {
+            add_users_from_csv(file).await?;
         }
-        commandline::Commands::UserDel { username } => {
-            resources::users::delete_user(&sanitize_username(username)?)?;
+        commandline::Commands::UserAdd { username, .. } => {
+            // clap requires either a username or a CSV file.
+            let username = sanitize_username(username.as_deref().unwrap_or_default())?;
+            resources::users::create_user(&username)?;
+            resources::samples::create_sample_connection(&username).await?;
+        }
+        commandline::Commands::UserDel { username, archive } => {
+            let username = sanitize_username(username)?;
+            if let Some(archive) = archive {
+                resources::users::archive_user(&username, archive)?;
+                println!("The user data has been archived into {}.", archive.display());
+            }
+            resources::users::delete_user(&username)?;
+        }
+        commandline::Commands::UserRestore { username, archive } => {
+            resources::users::restore_user(&sanitize_username(username)?, archive)?;
         }
         commandline::Commands::ShowConfig => {
             settings::show_config();
         }
+        commandline::Commands::Config { .. } => {
+            // already handled before loading the settings.
+        }
     }
     Ok(())
 }
@@ -61,4 +61,61 @@ pub struct AgentSettings {
     ///
     /// #default: 86400
     pub cors_max_age: std::time::Duration,
+
+    /// The latency above which a request is logged as a slow request.
+    ///
+    /// #default: 1000 (milliseconds)
+    pub slow_request_threshold: std::time::Duration,
+
+    /// The maximum duration to establish a connection to a database.
+    ///
+    /// #default: 30 (seconds)
+    pub connect_timeout: std::time::Duration,
+
+    /// The duration after which a transaction that is not used anymore is rolled back and its connection closed.
+    ///
+    /// #default: 600 (seconds)
+    pub transaction_idle_timeout: std::time::Duration,
+
+    /// Enforce unique connection names across the whole catalog of a user instead of only within their folder.
+    ///
+    /// #default: false
+    pub catalog_strict_names: bool,
+
+    /// Require the statements writing data to be confirmed by the user when executed on a connection labeled as
+    /// production (see `ExecuteStatementRequest::confirmed`).
+    ///
+    /// #default: true
+    pub production_write_confirmation: bool,
+
+    /// The number of consecutive authentication failures (invalid API key or logon) after which the client address is
+    /// locked out. A value of 0 disables the lockout.
+    ///
+    /// The clients connected through a loopback address are never locked out. The lockouts are only kept in memory and
+    /// reported as warnings in the logs: restarting the agent clears them.
+    ///
+    /// #default: 5
+    pub auth_max_failures: u32,
+
+    /// The duration of the first lockout, doubled for each new failure while locked out.
+    /// The failures are also forgotten after this duration without any new failure.
+    ///
+    /// #default: 60 (seconds)
+    pub auth_lockout_duration: std::time::Duration,
+
+    /// Serve the API over HTTPS.
+    ///
+    /// #default: false
+    pub tls_enabled: bool,
+
+    /// The certificate file (PEM) used to serve the API over HTTPS.
+    /// If neither `tls_cert_file` nor `tls_key_file` are set, a self-signed certificate is generated in the app directory.
+    pub tls_cert_file: String,
+
+    /// The private key file (PEM) of the certificate used to serve the API over HTTPS.
+    pub tls_key_file: String,
+
+    /// The warnings raised while loading the configuration file (unknown or deprecated settings...).
+    #[serde(skip)]
+    pub warnings: Vec<String>,
 }
@@ -47,3 +47,54 @@ pub struct SecurityToken {
 pub struct RefreshToken {
     pub refresh_token: String,
 }
+
+json_enum!(AccessTokenScope, Read, Write);
+
+/// A personal access token.
+///
+/// Access tokens are long-lived tokens created by a user so scripts can call the API without an interactive logon.
+/// Only a hash of the token is stored by the agent, the token itself is returned once when it is created.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AccessToken {
+    /// The unique identifier of the token (used to revoke it).
+    pub id: String,
+
+    /// A name given by the user to remember what the token is used for.
+    pub name: String,
+
+    /// The requests allowed with the token (`read` allows only GET requests).
+    pub scope: AccessTokenScope,
+
+    /// The time the token has been created (seconds since the UNIX epoch).
+    pub created_at: u64,
+
+    /// The time after which the token is rejected (seconds since the UNIX epoch), the token never expires if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+
+    /// The last time the token has been used (seconds since the UNIX epoch).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<u64>,
+}
+
+/// The request body of the POST /users/:username/tokens endpoint.
+#[derive(Deserialize, Debug)]
+pub struct AccessTokenRequest {
+    pub name: String,
+    pub scope: AccessTokenScope,
+
+    /// The number of days the token is valid, the token never expires if not set.
+    #[serde(default)]
+    pub expires_in_days: Option<u32>,
+}
+
+/// Response of the POST /users/:username/tokens endpoint.
+#[derive(Serialize)]
+pub struct NewAccessToken {
+    /// The token to be used in the Authorization header (`Bearer <token>`).
+    /// It cannot be retrieved later on.
+    pub token: String,
+
+    #[serde(flatten)]
+    pub access_token: AccessToken,
+}
@@ -32,3 +32,38 @@ impl Default for CollectionItem<WorkspaceCollectionItemType> {
     }
 }
 */
+
+pub type CatalogEntry = CollectionItem<CatalogEntryType>;
+
+/// Types of items that can be owned by a user.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CatalogEntryType {
+    Connection,
+    Environment,
+    Favorite,
+    Workspace,
+    Query,
+    Folder,
+    Unknown,
+}
+
+impl Default for CollectionItem<CatalogEntryType> {
+    fn default() -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: String::new(),
+            item_type: CatalogEntryType::Unknown,
+        }
+    }
+}
+
+/// A catalog entry along with the entries it contains (for folders).
+#[derive(Serialize, Debug)]
+pub struct CatalogTreeEntry {
+    #[serde(flatten)]
+    pub entry: CatalogEntry,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<CatalogTreeEntry>,
+}
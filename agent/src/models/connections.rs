@@ -1,4 +1,5 @@
 use serde::{ Deserialize, Serialize };
+use crate::json_enum;
 
 use super::datasources::Datasource;
 
@@ -57,6 +58,118 @@ pub struct Connection {
     /// Datasources available through this connection.
     #[serde(default)]
     pub datasources: Vec<Datasource>,
+
+    /// The name of the application reported to the server (e.g. `application_name` for PostgreSQL), so it can be
+    /// identified in the monitoring of the database.
+    ///
+    /// The following variables are replaced when connecting: `{username}` and `{connection}` (the name of the
+    /// connection).
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub application_name: String,
+
+    /// Safety labels displayed by the clients when the connection is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub labels: Option<ConnectionLabels>,
+
+    /// The SOCKS5 proxy used to reach the server (if any).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<ConnectionProxy>,
+
+    /// Information about the server collected when the connection has been validated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub info: Option<ConnectionInfo>,
+
+    /// The id of the environment providing the values of the variables used by the connection.
+    ///
+    /// Variables are referenced using the Jinja syntax (e.g. `{{ host }}`) in the host, socket, file, connection
+    /// string, username, password & datasource, they are rendered when connecting.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<String>,
+}
+
+json_enum!(ConnectionStage, Production, Staging, Development);
+
+/// Safety labels of a connection, helping users to notice they are working on a sensitive server.
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct ConnectionLabels {
+    /// The stage of the server (production, staging or development).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stage: Option<ConnectionStage>,
+
+    /// A color used to highlight the connection (e.g. "#d32f2f").
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub color: String,
+
+    /// The text of a warning banner displayed when using the connection.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub banner: String,
+}
+
+/// A SOCKS5 proxy used to reach the server of a connection (e.g. a bastion host).
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct ConnectionProxy {
+    pub host: String,
+    pub port: u16,
+
+    /// The username used to authenticate to the proxy (no authentication if empty).
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub username: String,
+
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub password: String,
+}
+
+/// Information about the server of a connection.
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct ConnectionInfo {
+    /// The version of the server (e.g. "16.2").
+    pub server_version: String,
+
+    /// The edition of the server (e.g. "PostgreSQL", "Redshift").
+    pub edition: String,
+
+    /// The features supported by the server (e.g. "limit_offset", "fetch_first", "returning").
+    pub features: Vec<String>,
+}
+
+/// The body of `POST /connections/preview-uri`.
+#[derive(Deserialize)]
+pub struct ConnectionPreviewRequest {
+    /// The connection to preview (may not be saved yet).
+    pub connection: Connection,
+
+    /// The id of the environment providing the values of the variables used by the connection (overriding the
+    /// environment of the connection if any).
+    #[serde(default)]
+    pub environment: Option<String>,
+}
+
+/// The body of `POST /connections/from-uri`.
+#[derive(Deserialize)]
+pub struct ConnectionFromUriRequest {
+    /// A connection URI (e.g. "postgresql://localhost/db") or the path of a SQLite database file.
+    pub uri: String,
+}
+
+/// Usage statistics of a connection.
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[cfg_attr(test, derive(PartialEq, Debug))]
+pub struct ConnectionUsage {
+    /// The last time the connection has been used (seconds since the epoch, 0 if never used).
+    pub last_used: u64,
+
+    /// The number of times the connection has been used.
+    pub count: u64,
+}
+
+/// An entry of the report on the usage of the connections of a user.
+#[derive(Serialize)]
+pub struct ConnectionUsageReport {
+    pub id: String,
+    pub name: String,
+
+    #[serde(flatten)]
+    pub usage: ConnectionUsage,
 }
 
 #[cfg(test)]
@@ -90,6 +203,23 @@ mod tests {
                 name: "template1".to_string(),
                 alias: None,
             }],
+
+            application_name: "squill ({username})".to_string(),
+
+            labels: Some(ConnectionLabels {
+                stage: Some(ConnectionStage::Production),
+                color: "#d32f2f".to_string(),
+                banner: "This is the production database!".to_string(),
+            }),
+
+            proxy: Some(ConnectionProxy {
+                host: "bastion.example.com".to_string(),
+                port: 1080,
+                ..Default::default()
+            }),
+
+            info: None,
+            environment: None,
         };
         println!("{}", serde_json::to_string_pretty(&connection).unwrap());
     }
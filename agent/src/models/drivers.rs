@@ -8,7 +8,7 @@ pub const DRIVER_SOCKET: &str = "SOCKET";
 pub const DRIVER_CONNECTION_STRING: &str = "CONNECTION_STRING";
 pub const DRIVER_CONNECTION_MODE: &str = "CONNECTION_MODE";
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum Capability {
     /// This capability indicates the data source is supporting SQL.
@@ -56,10 +56,27 @@ pub struct Driver {
     /// The capabilities of the driver
     pub capabilities: Vec<Capability>,
 
+    /// The URI schemes handled by the driver, without the trailing colon (e.g. "postgresql", "postgres")
+    #[serde(default)]
+    pub uri_schemes: Vec<String>,
+
     /// The default connection settings of the driver
     pub defaults: HashMap<String, String>,
 }
 
+impl Driver {
+    pub fn has_capability(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+
+    /// Check if a connection to the data source requires a host (the driver cannot connect to a file or a socket).
+    pub fn requires_host(&self) -> bool {
+        self.has_capability(Capability::ConnectHost) &&
+            !self.has_capability(Capability::ConnectFile) &&
+            !self.has_capability(Capability::ConnectSocket)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -78,11 +95,14 @@ mod tests {
                 Capability::ConnectHost,
                 Capability::ConnectSocket
             ],
+            uri_schemes: vec!["postgresql".to_string(), "postgres".to_string()],
             defaults: HashMap::from([
                 (DRIVER_PORT.to_string(), "5432".to_string()),
                 (DRIVER_USER.to_string(), "postgres".to_string()),
             ]),
         };
         println!("{}", serde_json::to_string_pretty(&driver).unwrap());
+        assert!(driver.has_capability(Capability::ConnectHost));
+        assert!(!driver.requires_host());
     }
 }
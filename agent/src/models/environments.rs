@@ -25,3 +25,40 @@ impl Default for Environment {
         }
     }
 }
+
+/// The body of `POST /users/:username/environments/:id/preview`.
+#[derive(Deserialize)]
+pub struct EnvironmentPreviewRequest {
+    /// The template to be rendered with the variables of the environment (e.g. a connection URI).
+    pub template: String,
+}
+
+/// The result of rendering a template with the variables of an environment.
+#[derive(Serialize)]
+pub struct EnvironmentPreview {
+    /// The rendered template, with the secrets masked (empty if the template cannot be rendered).
+    pub rendered: String,
+
+    /// The names of the variables referenced by the template but not resolved.
+    pub unresolved: Vec<String>,
+
+    /// The error preventing the template to be rendered (if any).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<TemplateError>,
+}
+
+/// An error found in a template (e.g. a syntax error), with its position in the template.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TemplateError {
+    /// The field of the connection containing the template (empty if not rendering a connection).
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub field: String,
+
+    pub message: String,
+
+    /// The line of the error in the template (starting at 1).
+    pub line: Option<usize>,
+
+    /// The column of the error in the template (starting at 1).
+    pub column: Option<usize>,
+}
@@ -0,0 +1,50 @@
+use serde::{ Deserialize, Serialize };
+use serde_json::Value;
+
+/// A keyboard macro defined by a user.
+///
+/// Macros are stored by the agent so the shortcuts configured by a user on one machine are available on the others.
+/// The agent does not interpret the action, it is up to the clients to define its format.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct KeyboardMacro {
+    pub id: String,
+    pub name: String,
+
+    /// The key binding triggering the macro (e.g. "Ctrl+Shift+K").
+    pub key_binding: String,
+
+    /// The action performed by the macro.
+    pub action: Value,
+
+    /// The last time the macro has been created or updated (seconds since the UNIX epoch).
+    pub updated_at: u64,
+}
+
+/// The request body of the POST /users/:username/macros and PUT /users/:username/macros/:id endpoints.
+#[derive(Deserialize, Debug)]
+pub struct KeyboardMacroRequest {
+    pub name: String,
+    pub key_binding: String,
+
+    #[serde(default)]
+    pub action: Value,
+}
+
+/// A change of the keyboard macros of a user, sent to the clients watching the macros (see
+/// GET /users/:username/macros/events).
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum KeyboardMacroChange {
+    Created {
+        keyboard_macro: KeyboardMacro,
+    },
+    Updated {
+        keyboard_macro: KeyboardMacro,
+    },
+    Deleted {
+        id: String,
+    },
+
+    /// Some changes have been missed by the client, the macros must be read again.
+    Reset,
+}
@@ -9,3 +9,8 @@ pub mod collections;
 pub mod errors;
 pub mod drivers;
 pub mod datasources;
+pub mod tables;
+pub mod plans;
+pub mod queries;
+pub mod macros;
+pub mod transactions;
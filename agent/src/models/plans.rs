@@ -0,0 +1,32 @@
+use serde::{ Deserialize, Serialize };
+
+/// The body of `POST /connections/:id/plan`.
+#[derive(Deserialize)]
+pub struct QueryPlanRequest {
+    /// The query to explain (it is not executed).
+    pub query: String,
+}
+
+/// A node of the plan of a query.
+///
+/// The plans of all the drivers are normalized into the same tree of nodes.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct QueryPlanNode {
+    /// The operation performed by the node (e.g. "Seq Scan", "SCAN users").
+    pub operation: String,
+
+    /// The relation (table or index) read by the node if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub relation: Option<String>,
+
+    /// The estimated total cost of the node (if reported by the datasource).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost: Option<f64>,
+
+    /// The estimated number of rows returned by the node (if reported by the datasource).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rows: Option<f64>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<QueryPlanNode>,
+}
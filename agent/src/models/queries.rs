@@ -0,0 +1,30 @@
+use serde::{ Deserialize, Serialize };
+
+/// A query saved in the catalog of a user.
+#[derive(Serialize, Deserialize)]
+pub struct SavedQuery {
+    pub id: String,
+    pub name: String,
+
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+
+    /// The text of the query.
+    pub query: String,
+
+    /// The connection the query has been written for (if any).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connection_id: Option<String>,
+}
+
+impl SavedQuery {
+    pub fn new(name: String, query: String) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            name,
+            description: String::new(),
+            query,
+            connection_id: None,
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use crate::json_enum;
+use serde::{ Deserialize, Serialize };
+
+/// A reference to a table of a datasource.
+#[derive(Serialize, Deserialize, Default)]
+pub struct TableReference {
+    /// The schema of the table (if not provided, the default schema of the connection is used).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+
+    /// The name of the table.
+    pub table: String,
+}
+
+/// The body of `POST /connections/:id/tables/peek`.
+#[derive(Deserialize)]
+pub struct PeekTableRequest {
+    #[serde(flatten)]
+    pub table: TableReference,
+
+    /// The maximum number of rows to be returned.
+    #[serde(default)]
+    pub limit: Option<u64>,
+}
+
+/// The first rows of a table.
+#[derive(Serialize)]
+pub struct PeekTableResult {
+    /// The rows, each row being an array of values.
+    pub rows: Vec<Vec<serde_json::Value>>,
+}
+
+/// A schema of a datasource.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct SchemaInfo {
+    pub name: String,
+}
+
+json_enum!(TableType, Table, View);
+
+/// A table (or a view) of a datasource.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct TableInfo {
+    pub schema: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub table_type: TableType,
+}
+
+/// A column of a table.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ColumnInfo {
+    pub name: String,
+
+    /// The data type as reported by the datasource (e.g. `integer`, `character varying`...).
+    pub data_type: String,
+
+    pub nullable: bool,
+}
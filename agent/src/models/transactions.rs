@@ -0,0 +1,30 @@
+use serde::{ Deserialize, Serialize };
+
+/// The body of `POST /connections/:id/transactions/execute`.
+#[derive(Deserialize)]
+pub struct ExecuteStatementRequest {
+    /// The statement to execute within the transaction.
+    pub statement: String,
+
+    /// The maximum number of rows to be returned.
+    #[serde(default)]
+    pub limit: Option<u64>,
+
+    /// The user has confirmed the execution of the statement.
+    ///
+    /// Required for the statements writing data on a connection labeled as production (unless the setting
+    /// `production_write_confirmation` is disabled).
+    #[serde(default)]
+    pub confirmed: bool,
+}
+
+/// The result of a statement executed within a transaction.
+#[derive(Serialize)]
+pub struct ExecuteStatementResult {
+    /// The rows returned by the statement (if any), each row being an array of values.
+    pub rows: Vec<Vec<serde_json::Value>>,
+
+    /// The number of rows affected by the statement (if reported by the datasource).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub affected_rows: Option<u64>,
+}
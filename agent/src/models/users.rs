@@ -1,5 +1,7 @@
+use std::collections::BTreeMap;
 use crate::json_enum;
 use crate::models::variables::Variable;
+use crate::models::collections::CatalogTreeEntry;
 use serde::{ Serialize, Deserialize };
 use uuid::Uuid;
 
@@ -87,3 +89,16 @@ impl Default for User {
         }
     }
 }
+
+/// A compact snapshot of the user data, used by the client to render a read-only state while the agent is unreachable.
+#[derive(Serialize)]
+pub struct OfflineSnapshot {
+    /// The time the snapshot was taken (seconds since the UNIX epoch).
+    pub created_at: u64,
+
+    /// The user (including the settings).
+    pub user: User,
+
+    /// The whole catalog of the user, by section (e.g. "connections", "workspaces").
+    pub catalog: BTreeMap<String, Vec<CatalogTreeEntry>>,
+}
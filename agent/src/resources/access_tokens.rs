@@ -0,0 +1,313 @@
+use std::collections::{ BTreeMap, HashMap };
+use std::path::{ Path, PathBuf };
+use std::sync::Mutex;
+use anyhow::{ Context, Result };
+use lazy_static::lazy_static;
+use rand::Rng;
+use crate::models::auth::{ AccessToken, AccessTokenRequest, NewAccessToken };
+use crate::utils::constants::{
+    ACCESS_TOKENS_FILENAME,
+    ACCESS_TOKEN_LAST_USED_PRECISION,
+    ACCESS_TOKEN_PREFIX,
+    USERS_DIRNAME,
+};
+use crate::utils::json_store::{ read_json_file, write_json_file };
+use crate::utils::time::now;
+use crate::utils::validators::{ sanitize_username, Username };
+use crate::{ err_not_found, err_param, settings };
+
+/// An access token along with its owner.
+struct OwnedAccessToken {
+    username: Username,
+    access_token: AccessToken,
+}
+
+/// The access tokens of all the users, the key being the hash of the token (see `hash_token`).
+type AccessTokensIndex = HashMap<String, OwnedAccessToken>;
+
+lazy_static! {
+    // The access tokens indexes, one for each users directory (so the index follows the base directory).
+    //
+    // An index is loaded from the access tokens files the first time it is used, then the files are only written when
+    // the tokens are updated. The requests are authenticated from the index without reading any file.
+    static ref ACCESS_TOKENS_INDEXES: Mutex<HashMap<PathBuf, AccessTokensIndex>> = Mutex::new(HashMap::new());
+}
+
+/// Get the directory of the users, each of them storing its access tokens in its own directory.
+///
+/// The directory depends on the settings, it must be resolved before moving to another thread (e.g. `spawn_blocking`).
+pub fn get_users_dir() -> PathBuf {
+    PathBuf::from(settings::get_base_dir()).join(USERS_DIRNAME)
+}
+
+/// Get the file used to store the access tokens of a user.
+fn get_access_tokens_file(users_dir: &Path, username: &Username) -> PathBuf {
+    users_dir.join(username.as_str()).join(ACCESS_TOKENS_FILENAME)
+}
+
+/// Read the access tokens of a user.
+///
+/// The key of the map is the hash of the token (see `hash_token`).
+fn read_access_tokens(users_dir: &Path, username: &Username) -> Result<BTreeMap<String, AccessToken>> {
+    read_json_file(&get_access_tokens_file(users_dir, username))
+}
+
+/// Write the access tokens of a user from the index.
+fn write_access_tokens(users_dir: &Path, username: &Username, index: &AccessTokensIndex) -> Result<()> {
+    let access_tokens: BTreeMap<&String, &AccessToken> = index
+        .iter()
+        .filter(|(_, owned)| owned.username.as_str() == username.as_str())
+        .map(|(hash, owned)| (hash, &owned.access_token))
+        .collect();
+    write_json_file(&get_access_tokens_file(users_dir, username), &access_tokens)
+}
+
+/// Load the access tokens of all the users.
+fn load_index(users_dir: &Path) -> Result<AccessTokensIndex> {
+    let mut index = AccessTokensIndex::new();
+    if !users_dir.exists() {
+        return Ok(index);
+    }
+    let dir_entries = std::fs
+        ::read_dir(users_dir)
+        .with_context(|| format!("Unable to read the users directory: {}", users_dir.display()))?;
+    for dir_entry in dir_entries {
+        let Ok(username) = sanitize_username(&dir_entry?.file_name().to_string_lossy()) else {
+            continue;
+        };
+        for (hash, access_token) in read_access_tokens(users_dir, &username)? {
+            index.insert(hash, OwnedAccessToken { username: username.clone(), access_token });
+        }
+    }
+    Ok(index)
+}
+
+/// Run a function with the access tokens index of the current users directory, loading it if needed.
+fn with_index<T>(f: impl FnOnce(&Path, &mut AccessTokensIndex) -> Result<T>) -> Result<T> {
+    with_index_in(&get_users_dir(), f)
+}
+
+/// Run a function with the access tokens index of a users directory, loading it if needed.
+fn with_index_in<T>(users_dir: &Path, f: impl FnOnce(&Path, &mut AccessTokensIndex) -> Result<T>) -> Result<T> {
+    let mut indexes = ACCESS_TOKENS_INDEXES.lock().unwrap();
+    if !indexes.contains_key(users_dir) {
+        let index = load_index(users_dir)?;
+        indexes.insert(users_dir.to_path_buf(), index);
+    }
+    f(users_dir, indexes.get_mut(users_dir).unwrap())
+}
+
+/// Hash a token (SHA-256 encoded in hexadecimal), so the tokens themselves are never stored.
+fn hash_token(token: &str) -> String {
+    hex::encode(openssl::sha::sha256(token.as_bytes()))
+}
+
+/// Create a new access token for a user.
+///
+/// The token is a 256-bit random number encoded in hexadecimal and prefixed by `ACCESS_TOKEN_PREFIX`.
+pub fn create_access_token(username: &Username, request: AccessTokenRequest) -> Result<NewAccessToken> {
+    let name = request.name.trim();
+    if name.is_empty() {
+        return Err(err_param!("The name of the access token cannot be empty."));
+    }
+    if request.expires_in_days == Some(0) {
+        return Err(err_param!("The access token must be valid for at least one day."));
+    }
+    let created_at = now()?;
+    let token = format!("{}{}", ACCESS_TOKEN_PREFIX, hex::encode(rand::thread_rng().gen::<[u8; 32]>()));
+    let access_token = AccessToken {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: name.to_string(),
+        scope: request.scope,
+        created_at,
+        expires_at: request.expires_in_days.map(|days| created_at + (days as u64) * 24 * 3600),
+        last_used_at: None,
+    };
+    with_index(|users_dir, index| {
+        index.insert(hash_token(&token), OwnedAccessToken {
+            username: username.clone(),
+            access_token: access_token.clone(),
+        });
+        write_access_tokens(users_dir, username, index)
+    })?;
+    Ok(NewAccessToken { token, access_token })
+}
+
+/// Get the access tokens of a user (the oldest first).
+pub fn list_access_tokens(username: &Username) -> Result<Vec<AccessToken>> {
+    let mut access_tokens: Vec<AccessToken> = with_index(|_, index| {
+        Ok(
+            index
+                .values()
+                .filter(|owned| owned.username.as_str() == username.as_str())
+                .map(|owned| owned.access_token.clone())
+                .collect()
+        )
+    })?;
+    access_tokens.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.name.cmp(&b.name)));
+    Ok(access_tokens)
+}
+
+/// Revoke an access token of a user.
+pub fn revoke_access_token(username: &Username, id: &str) -> Result<()> {
+    with_index(|users_dir, index| {
+        let count = index.len();
+        index.retain(|_, owned| owned.username.as_str() != username.as_str() || owned.access_token.id != id);
+        if index.len() == count {
+            return Err(err_not_found!("The access token '{}' does not exist.", id));
+        }
+        write_access_tokens(users_dir, username, index)
+    })
+}
+
+/// Forget the access tokens of a user (e.g. after the user has been deleted).
+///
+/// The access tokens file is not modified.
+pub fn forget_access_tokens(username: &Username) -> Result<()> {
+    with_index(|_, index| {
+        index.retain(|_, owned| owned.username.as_str() != username.as_str());
+        Ok(())
+    })
+}
+
+/// Save the access tokens of a user (e.g. after `authenticate` updated the last time a token has been used).
+///
+/// The users directory is given (see `get_users_dir`) so the tokens can be saved from another thread.
+pub fn save_access_tokens(users_dir: &Path, username: &Username) -> Result<()> {
+    with_index_in(users_dir, |users_dir, index| write_access_tokens(users_dir, username, index))
+}
+
+/// Check if a token is a valid access token (existing and not expired).
+pub fn is_valid_access_token(token: &str) -> Result<bool> {
+    if !token.starts_with(ACCESS_TOKEN_PREFIX) {
+        return Ok(false);
+    }
+    let hash = hash_token(token);
+    let now = now()?;
+    with_index(|_, index| {
+        Ok(
+            index
+                .get(&hash)
+                .is_some_and(|owned| !owned.access_token.expires_at.is_some_and(|expires_at| expires_at <= now))
+        )
+    })
+}
+
+/// Authenticate a request with an access token.
+///
+/// The token is searched in the access tokens index. If found and not expired, the last time the token has been used
+/// is updated in the index and the owner of the token is returned, along with a flag telling if the access tokens of
+/// the owner should be saved (see `save_access_tokens`). They only need to be saved every
+/// `ACCESS_TOKEN_LAST_USED_PRECISION` seconds.
+pub fn authenticate(token: &str) -> Result<Option<(Username, AccessToken, bool)>> {
+    if !token.starts_with(ACCESS_TOKEN_PREFIX) {
+        return Ok(None);
+    }
+    let hash = hash_token(token);
+    let now = now()?;
+    with_index(|_, index| {
+        let Some(owned) = index.get_mut(&hash) else {
+            return Ok(None);
+        };
+        if owned.access_token.expires_at.is_some_and(|expires_at| expires_at <= now) {
+            return Ok(None);
+        }
+        let last_used_at = owned.access_token.last_used_at.unwrap_or_default();
+        let save = now >= last_used_at + ACCESS_TOKEN_LAST_USED_PRECISION;
+        if save {
+            owned.access_token.last_used_at = Some(now);
+        }
+        Ok(Some((owned.username.clone(), owned.access_token.clone(), save)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::auth::AccessTokenScope;
+    use crate::resources::users::create_user;
+    use crate::utils::tests::settings;
+    use crate::utils::user_error::UserError;
+
+    #[test]
+    fn test_access_tokens() {
+        // setup
+        let temp_dir = tempfile::tempdir().unwrap();
+        settings::set_base_dir(temp_dir.path().to_str().unwrap().to_string());
+        let username: Username = "local".into();
+        create_user(&username).unwrap();
+
+        // 1) create a token
+        assert!(
+            create_access_token(&username, AccessTokenRequest {
+                name: " ".to_string(),
+                scope: AccessTokenScope::Read,
+                expires_in_days: None,
+            }).is_err()
+        );
+        let new_token = create_access_token(&username, AccessTokenRequest {
+            name: "ci".to_string(),
+            scope: AccessTokenScope::Read,
+            expires_in_days: Some(30),
+        }).unwrap();
+        assert!(new_token.token.starts_with(ACCESS_TOKEN_PREFIX));
+        assert!(new_token.access_token.expires_at.is_some());
+        let content = std::fs::read_to_string(get_access_tokens_file(&get_users_dir(), &username)).unwrap();
+        assert!(!content.contains(&new_token.token));
+
+        assert!(
+            create_access_token(&username, AccessTokenRequest {
+                name: "already expired".to_string(),
+                scope: AccessTokenScope::Read,
+                expires_in_days: Some(0),
+            }).is_err()
+        );
+
+        // 2) authenticate
+        assert!(is_valid_access_token(&new_token.token).unwrap());
+        let (owner, access_token, save) = authenticate(&new_token.token).unwrap().unwrap();
+        assert_eq!(owner.as_str(), "local");
+        assert_eq!(access_token.scope, AccessTokenScope::Read);
+        assert!(save);
+        assert!(!authenticate(&new_token.token).unwrap().unwrap().2);
+        assert!(list_access_tokens(&username).unwrap()[0].last_used_at.is_some());
+        assert!(authenticate(&format!("{}invalid", ACCESS_TOKEN_PREFIX)).unwrap().is_none());
+        assert!(!is_valid_access_token("invalid").unwrap());
+
+        // 3) the index is saved (from another thread, where the test settings do not apply) and can be reloaded
+        let users_dir = get_users_dir();
+        let owner = username.clone();
+        std::thread::spawn(move || save_access_tokens(&users_dir, &owner)).join().unwrap().unwrap();
+        let users_dir = temp_dir.path().join(USERS_DIRNAME);
+        let index = load_index(&users_dir).unwrap();
+        assert!(index.get(&hash_token(&new_token.token)).unwrap().access_token.last_used_at.is_some());
+
+        // 4) expired token
+        let expired_token = create_access_token(&username, AccessTokenRequest {
+            name: "expired".to_string(),
+            scope: AccessTokenScope::Write,
+            expires_in_days: Some(1),
+        }).unwrap();
+        with_index(|_, index| {
+            index.get_mut(&hash_token(&expired_token.token)).unwrap().access_token.expires_at = Some(now()? - 1);
+            Ok(())
+        }).unwrap();
+        assert!(authenticate(&expired_token.token).unwrap().is_none());
+        assert!(!is_valid_access_token(&expired_token.token).unwrap());
+        assert_eq!(list_access_tokens(&username).unwrap().len(), 2);
+
+        // 5) revoke
+        assert!(revoke_access_token(&username, &new_token.access_token.id).is_ok());
+        assert!(authenticate(&new_token.token).unwrap().is_none());
+        assert!(
+            matches!(
+                revoke_access_token(&username, &new_token.access_token.id).unwrap_err().downcast_ref::<UserError>(),
+                Some(UserError::NotFound(_))
+            )
+        );
+
+        // 6) forget the tokens of a user
+        forget_access_tokens(&username).unwrap();
+        assert!(list_access_tokens(&username).unwrap().is_empty());
+    }
+}
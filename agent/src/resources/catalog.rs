@@ -1,7 +1,8 @@
 use crate::{
+    err_conflict,
     err_not_found,
     err_param,
-    models::collections::CollectionItem,
+    models::collections::{ CatalogEntry, CatalogEntryType, CatalogTreeEntry },
     settings,
     utils::{
         constants::{ CATALOG_ENTRY_FILE_EXTENSION, USER_CATALOG_DIRNAME },
@@ -9,36 +10,12 @@ use crate::{
     },
 };
 use anyhow::{ Context, Result };
-use serde::{ Deserialize, Serialize };
+use serde::Serialize;
 use core::panic;
+use std::collections::BTreeMap;
 use std::path::{ Path, PathBuf };
 use tracing::{ error, warn };
 
-pub type CatalogEntry = CollectionItem<CatalogEntryType>;
-
-/// Types of items that can be owned by a user.
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(rename_all = "snake_case")]
-#[cfg_attr(test, derive(PartialEq))]
-pub enum CatalogEntryType {
-    Connection,
-    Environment,
-    Favorite,
-    Workspace,
-    Folder,
-    Unknown,
-}
-
-impl Default for CollectionItem<CatalogEntryType> {
-    fn default() -> Self {
-        Self {
-            id: uuid::Uuid::new_v4().to_string(),
-            name: String::new(),
-            item_type: CatalogEntryType::Unknown,
-        }
-    }
-}
-
 /// A Section of the catalog.
 ///
 /// The catalog is organized in different sections:
@@ -51,6 +28,7 @@ impl Default for CollectionItem<CatalogEntryType> {
 ///             ├── connections              |
 ///             ├── environments          <- | Sections of the catalog
 ///             ├── workspaces               |
+///             ├── queries                  |
 ///             └── favorites                |
 /// ```
 #[derive(PartialEq, Debug)]
@@ -59,6 +37,7 @@ pub enum CatalogSection {
     Environments,
     Favorites,
     Workspaces,
+    Queries,
 }
 
 impl CatalogSection {
@@ -68,6 +47,7 @@ impl CatalogSection {
             CatalogSection::Workspaces => "workspaces",
             CatalogSection::Environments => "environments",
             CatalogSection::Favorites => "favorites",
+            CatalogSection::Queries => "queries",
         }
     }
 
@@ -76,7 +56,8 @@ impl CatalogSection {
             &CatalogSection::Connections,
             &CatalogSection::Workspaces,
             &CatalogSection::Environments,
-            &CatalogSection::Favorites
+            &CatalogSection::Favorites,
+            &CatalogSection::Queries
         ]
     }
 
@@ -90,6 +71,7 @@ impl CatalogSection {
             Some("workspaces") => CatalogSection::Workspaces,
             Some("environments") => CatalogSection::Environments,
             Some("favorites") => CatalogSection::Favorites,
+            Some("queries") => CatalogSection::Queries,
             _ => panic!("Invalid path: {}", path.as_str()),
         }
     }
@@ -111,6 +93,7 @@ pub fn create_file(username: &Username, path: &CatalogPath, id: &str) -> Result<
         CatalogSection::Workspaces => CatalogEntryType::Workspace,
         CatalogSection::Environments => CatalogEntryType::Environment,
         CatalogSection::Connections => CatalogEntryType::Connection,
+        CatalogSection::Queries => CatalogEntryType::Query,
         CatalogSection::Favorites => panic!("Files cannot be created in section: {}", section.as_str()),
     };
     let fs_path = to_fs_path(username, path).with_extension(CATALOG_ENTRY_FILE_EXTENSION);
@@ -136,6 +119,18 @@ pub fn create_dir(username: &Username, path: &CatalogPath) -> Result<()> {
         .with_context(|| format!("Unable to create the directory '{}' in the user's catalog.", path))
 }
 
+/// Create the sections missing in the catalog of a user.
+///
+/// The sections added by a new version of the agent do not exist in the catalog of the users created before.
+pub fn create_missing_sections(username: &Username) -> Result<()> {
+    for section in CatalogSection::variants() {
+        if !exists(username, &section.as_path()) {
+            create_dir(username, &section.as_path())?;
+        }
+    }
+    Ok(())
+}
+
 /// Check if a path exists in the catalog.
 ///
 /// # Arguments
@@ -182,9 +177,14 @@ pub fn rename(username: &Username, path: &CatalogPath, new_name: &CatalogPathCom
         let new_path = sanitize_catalog_path(
             PathBuf::from(path.as_str()).parent().unwrap().join(new_name.as_str()).as_os_str().to_str().unwrap()
         )?;
-        if exists(username, &new_path) {
+        // On a case-insensitive filesystem, the new path of a case-only rename ("Foo" -> "foo") is the path itself.
+        if !is_case_only_rename(path, new_name) && exists(username, &new_path) {
             return Err(err_param!("'{}' already exists.", new_path));
         }
+        let section = CatalogSection::from_path(path);
+        if settings::get_catalog_strict_names() && section == CatalogSection::Connections && !is_dir(username, path) {
+            check_unique_name(username, &section, new_name.as_str(), Some(path))?;
+        }
     }
     let fs_path = to_fs_path(username, path);
     if fs_path.exists() {
@@ -202,6 +202,14 @@ pub fn rename(username: &Username, path: &CatalogPath, new_name: &CatalogPathCom
         let fs_new_path = fs_path.with_file_name(new_name.as_str()).with_extension(CATALOG_ENTRY_FILE_EXTENSION);
         let mut entry = read_fs_entry(&fs_path.with_extension(CATALOG_ENTRY_FILE_EXTENSION))?;
         entry.name = new_name.as_str().to_owned();
+        if is_case_only_rename(path, new_name) {
+            // The old and new files may be the same file (case-insensitive filesystem), it must be renamed in place.
+            let fs_old_path = fs_path.with_extension(CATALOG_ENTRY_FILE_EXTENSION);
+            write_fs_entry(&fs_old_path, &entry)?;
+            return std::fs
+                ::rename(&fs_old_path, &fs_new_path)
+                .with_context(|| format!("Unable to rename the file '{}' in the user's catalog.", path));
+        }
         write_fs_entry(&fs_new_path, &entry)?;
         match std::fs::remove_file(fs_path.with_extension(CATALOG_ENTRY_FILE_EXTENSION)) {
             Ok(_) => (),
@@ -215,6 +223,112 @@ pub fn rename(username: &Username, path: &CatalogPath, new_name: &CatalogPathCom
     Ok(())
 }
 
+/// Read the whole content of a directory of the catalog, including the content of its sub-directories.
+pub fn read_tree(username: &Username, path: &CatalogPath) -> Result<Vec<CatalogTreeEntry>> {
+    let mut tree = Vec::new();
+    for entry in read_dir(username, path)? {
+        let children = if entry.item_type == CatalogEntryType::Folder {
+            let child_path = sanitize_catalog_path(&format!("{}/{}", path, entry.name))?;
+            read_tree(username, &child_path)?
+        } else {
+            Vec::new()
+        };
+        tree.push(CatalogTreeEntry { entry, children });
+    }
+    Ok(tree)
+}
+
+/// A name used by more than one file of the same section of the catalog.
+#[derive(Serialize, Debug)]
+pub struct NameConflict {
+    /// The name in conflict.
+    pub name: String,
+
+    /// The paths of all the files using this name (e.g. "connections/Production/Sales").
+    pub paths: Vec<String>,
+}
+
+/// List the paths of all the files of a section of the catalog, whatever the folder they are stored in.
+pub fn list_files(username: &Username, section: &CatalogSection) -> Result<Vec<String>> {
+    let mut paths = Vec::new();
+    inner_list(&to_fs_path(username, &section.as_path()), section.as_str(), &mut paths)?;
+    return Ok(paths);
+
+    fn inner_list(fs_path: &Path, path: &str, paths: &mut Vec<String>) -> Result<()> {
+        let fs_entries = std::fs
+            ::read_dir(fs_path)
+            .with_context(|| { format!("Unable to read the content of the directory '{}'.", path) })?;
+        for fs_entry in fs_entries.flatten() {
+            let fs_entry_path = fs_entry.path();
+            if fs_entry_path.is_dir() {
+                // The name of a folder may contain a dot (e.g. "Prod.EU"), it has no extension to strip.
+                let Some(name) = fs_entry_path.file_name().and_then(|name| name.to_str()) else {
+                    continue;
+                };
+                inner_list(&fs_entry_path, &format!("{}/{}", path, name), paths)?;
+            } else if fs_entry_path.extension().is_some_and(|ext| ext.eq(CATALOG_ENTRY_FILE_EXTENSION)) {
+                let Some(name) = fs_entry_path.file_stem().and_then(|name| name.to_str()) else {
+                    continue;
+                };
+                paths.push(format!("{}/{}", path, name));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Find the names used by more than one file of a section of the catalog.
+///
+/// Names are compared case-insensitively because they are used to reference the entries from outside of the catalog
+/// (e.g. `--connection <name>`).
+pub fn find_name_conflicts(username: &Username, section: &CatalogSection) -> Result<Vec<NameConflict>> {
+    let mut names: BTreeMap<String, NameConflict> = BTreeMap::new();
+    for path in list_files(username, section)? {
+        let name = path.rsplit('/').next().unwrap_or_default().to_string();
+        names
+            .entry(name.to_lowercase())
+            .or_insert_with(|| NameConflict { name, paths: Vec::new() })
+            .paths.push(path);
+    }
+    Ok(
+        names
+            .into_values()
+            .filter(|conflict| conflict.paths.len() > 1)
+            .collect()
+    )
+}
+
+/// Check if renaming an entry only changes the case of its name (e.g. "Foo" -> "foo").
+fn is_case_only_rename(path: &CatalogPath, new_name: &CatalogPathComponent) -> bool {
+    let name = path.as_str().rsplit('/').next().unwrap_or_default();
+    name != new_name.as_str() && name.to_lowercase() == new_name.as_str().to_lowercase()
+}
+
+/// Check that a name is not already used by a file of a section of the catalog, whatever the folder it is stored in.
+///
+/// The entry being renamed (if any) is excluded, so its name can still change case.
+pub fn check_unique_name(
+    username: &Username,
+    section: &CatalogSection,
+    name: &str,
+    renamed: Option<&CatalogPath>
+) -> Result<()> {
+    let suffix = format!("/{}", name.to_lowercase());
+    let used_by = list_files(username, section)?
+        .into_iter()
+        .filter(|path| !renamed.is_some_and(|renamed| renamed.as_str() == path))
+        .find(|path| path.to_lowercase().ends_with(&suffix));
+    if let Some(path) = used_by {
+        return Err(err_conflict!("'{}' is already used by '{}'.", name, path));
+    }
+    Ok(())
+}
+
+/// Check if a path of the catalog is a directory.
+fn is_dir(username: &Username, path: &CatalogPath) -> bool {
+    to_fs_path(username, path).is_dir()
+}
+
 /// Read the content of a directory and return a list of catalog entries.
 pub fn read_dir(username: &Username, path: &CatalogPath) -> Result<Vec<CatalogEntry>> {
     let fs_path = to_fs_path(username, path);
@@ -314,11 +428,12 @@ mod tests {
         assert_eq!(CatalogSection::Workspaces.as_str(), "workspaces");
         assert_eq!(CatalogSection::Environments.as_str(), "environments");
         assert_eq!(CatalogSection::Favorites.as_str(), "favorites");
+        assert_eq!(CatalogSection::Queries.as_str(), "queries");
     }
 
     #[test]
     fn test_catalog_section_variants() {
-        assert_eq!(CatalogSection::variants().len(), 4);
+        assert_eq!(CatalogSection::variants().len(), 5);
     }
 
     #[test]
@@ -328,6 +443,7 @@ mod tests {
         assert_eq!(CatalogSection::from_path(&CatalogPath::from("workspaces/A/B")), CatalogSection::Workspaces);
         assert_eq!(CatalogSection::from_path(&CatalogPath::from("environments")), CatalogSection::Environments);
         assert_eq!(CatalogSection::from_path(&CatalogPath::from("favorites")), CatalogSection::Favorites);
+        assert_eq!(CatalogSection::from_path(&CatalogPath::from("queries/reports")), CatalogSection::Queries);
         assert!(std::panic::catch_unwind(|| CatalogSection::from_path(&CatalogPath::from("invalid/path"))).is_err());
     }
 
@@ -407,6 +523,46 @@ mod tests {
         assert!(to_fs_path(&username, &path).with_extension(CATALOG_ENTRY_FILE_EXTENSION).exists());
         std::fs::set_permissions(to_fs_path(&username, &path).parent().unwrap(), restore_permissions).unwrap();
 
+        // 7) Change the case of the name of a connection in strict mode
+        settings::set_catalog_strict_names(true);
+        let path = CatalogPath::from("connections/Foo");
+        catalog::create_file(&username, &path, "id").unwrap();
+        assert!(catalog::rename(&username, &path, &CatalogPathComponent::from("foo")).is_ok());
+        let files = list_files(&username, &CatalogSection::Connections).unwrap();
+        assert!(files.contains(&"connections/foo".to_string()) && !files.contains(&"connections/Foo".to_string()));
+        let fs_path = to_fs_path(&username, &CatalogPath::from("connections/foo"));
+        assert_eq!(read_fs_entry(&fs_path.with_extension(CATALOG_ENTRY_FILE_EXTENSION)).unwrap().name, "foo");
+        settings::set_catalog_strict_names(false);
+
+        // cleanup
+        std::fs::remove_dir_all(temp_dir.path()).unwrap();
+    }
+
+    #[test]
+    fn test_list_files() {
+        // setup
+        let temp_dir = tempfile::tempdir().unwrap();
+        settings::set_base_dir(temp_dir.path().to_str().unwrap().to_string());
+        let username: Username = "marty.mcfly".into();
+        create_user(&username).unwrap();
+        let folder = CatalogPath::from("connections/Prod.EU");
+        let path = CatalogPath::from("connections/Prod.EU/Sales");
+        catalog::create_dir(&username, &folder).unwrap();
+        catalog::create_file(&username, &path, "id").unwrap();
+
+        // 1) The name of a folder containing a dot is kept as is
+        assert_eq!(list_files(&username, &CatalogSection::Connections).unwrap(), vec!["connections/Prod.EU/Sales"]);
+
+        // 2) The name is used by a file of the folder
+        assert!(check_unique_name(&username, &CatalogSection::Connections, "sales", None).is_err());
+
+        // 3) The file of the folder is renamed by only changing the case of its name
+        assert!(check_unique_name(&username, &CatalogSection::Connections, "sales", Some(&path)).is_ok());
+        settings::set_catalog_strict_names(true);
+        assert!(catalog::rename(&username, &path, &CatalogPathComponent::from("sales")).is_ok());
+        assert_eq!(list_files(&username, &CatalogSection::Connections).unwrap(), vec!["connections/Prod.EU/sales"]);
+        settings::set_catalog_strict_names(false);
+
         // cleanup
         std::fs::remove_dir_all(temp_dir.path()).unwrap();
     }
@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use crate::models::drivers::{
+    Capability,
+    Driver,
+    DRIVER_CONNECTION_STRING,
+    DRIVER_CONNECTION_MODE,
+    DRIVER_HOST,
+    DRIVER_PORT,
+    DRIVER_SOCKET,
+    DRIVER_USER,
+};
+
+/// Get the descriptors of the drivers supported by the agent.
+pub fn get_drivers() -> Vec<Driver> {
+    vec![
+        Driver {
+            name: "sqlite".to_string(),
+            label: "SQLite".to_string(),
+            icon: "sqlite.svg".to_string(),
+            description: "SQLite is a C-language library that implements a small, fast, self-contained, high-reliability, full-featured, SQL database engine.".to_string(),
+            capabilities: vec![
+                Capability::Sql,
+                Capability::ConnectFile,
+                Capability::ConnectString,
+                Capability::ReadOnly
+            ],
+            uri_schemes: vec!["sqlite".to_string()],
+            defaults: HashMap::from([
+                (DRIVER_CONNECTION_MODE.to_string(), "file".to_string()),
+                (DRIVER_CONNECTION_STRING.to_string(), "memory://".to_string()),
+            ]),
+        },
+        Driver {
+            name: "postgresql".to_string(),
+            label: "PostgreSQL".to_string(),
+            icon: "postgresql.svg".to_string(),
+            description: "PostgreSQL is a powerful, open source object-relational database system with over 30 years of active development that has earned it a strong reputation for reliability, feature robustness, and performance.".to_string(),
+            capabilities: vec![
+                Capability::Sql,
+                Capability::AuthUserPassword,
+                Capability::ConnectString,
+                Capability::ConnectHost,
+                Capability::ConnectSocket
+            ],
+            uri_schemes: vec!["postgresql".to_string(), "postgres".to_string()],
+            defaults: HashMap::from([
+                (DRIVER_CONNECTION_MODE.to_string(), "host".to_string()),
+                (DRIVER_HOST.to_string(), "localhost".to_string()),
+                (DRIVER_PORT.to_string(), "5432".to_string()),
+                (DRIVER_USER.to_string(), "postgres".to_string()),
+                (DRIVER_SOCKET.to_string(), "/var/run/postgres/.s.PGSQL.5432".to_string()),
+            ]),
+        },
+        Driver {
+            name: "redshift".to_string(),
+            label: "Amazon Redshift".to_string(),
+            icon: "redshift.svg".to_string(),
+            description: "Amazon Redshift is a fully managed, petabyte-scale data warehouse service in the cloud.".to_string(),
+            capabilities: vec![
+                Capability::Sql,
+                Capability::AuthUserPassword,
+                Capability::ConnectString,
+                Capability::ConnectHost
+            ],
+            // Selected from the host of a `postgresql:` URI (see `Connection::from_uri`).
+            uri_schemes: Vec::new(),
+            defaults: HashMap::from([
+                (DRIVER_CONNECTION_MODE.to_string(), "host".to_string()),
+                (DRIVER_PORT.to_string(), "5439".to_string()),
+                (DRIVER_USER.to_string(), "awsuser".to_string()),
+            ]),
+        },
+        Driver {
+            name: "trino".to_string(),
+            label: "Trino".to_string(),
+            icon: "trino.svg".to_string(),
+            description: "Trino is a fast distributed SQL query engine for big data analytics.".to_string(),
+            capabilities: vec![
+                Capability::Sql,
+                Capability::AuthUserPassword,
+                Capability::ConnectString,
+                Capability::ConnectHost
+            ],
+            uri_schemes: vec!["trino".to_string()],
+            defaults: HashMap::from([
+                (DRIVER_CONNECTION_MODE.to_string(), "host".to_string()),
+                (DRIVER_HOST.to_string(), "localhost".to_string()),
+                (DRIVER_PORT.to_string(), "8080".to_string()),
+                (DRIVER_USER.to_string(), "trino".to_string()),
+            ]),
+        },
+        Driver {
+            name: "mysql".to_string(),
+            label: "MySQL".to_string(),
+            icon: "mysql.svg".to_string(),
+            description: "MySQL is an open-source relational database management system (RDBMS).".to_string(),
+            capabilities: vec![
+                Capability::Sql,
+                Capability::AuthUserPassword,
+                Capability::ConnectString,
+                Capability::ConnectHost,
+                Capability::ConnectSocket
+            ],
+            uri_schemes: Vec::new(),
+            defaults: HashMap::from([
+                (DRIVER_CONNECTION_MODE.to_string(), "host".to_string()),
+                (DRIVER_HOST.to_string(), "localhost".to_string()),
+                (DRIVER_PORT.to_string(), "3306".to_string()),
+                (DRIVER_USER.to_string(), "root".to_string()),
+            ]),
+        },
+        Driver {
+            name: "mock".to_string(),
+            label: "Mock".to_string(),
+            icon: "mock.svg".to_string(),
+            description: "A driver returning a deterministic dataset without any database, for demos and tests. Latency and failures can be simulated using the options of the connection string.".to_string(),
+            capabilities: vec![Capability::Sql, Capability::ConnectString],
+            uri_schemes: vec!["mock".to_string()],
+            defaults: HashMap::from([
+                (DRIVER_CONNECTION_MODE.to_string(), "connection_string".to_string()),
+                (DRIVER_CONNECTION_STRING.to_string(), "mock://?rows=100&latency=0".to_string()),
+            ]),
+        }
+    ]
+}
+
+/// Get the descriptor of the driver handling a URI scheme (case-insensitive, without the trailing colon).
+pub fn get_driver_by_uri_scheme(scheme: &str) -> Option<Driver> {
+    get_drivers()
+        .into_iter()
+        .find(|driver| driver.uri_schemes.iter().any(|uri_scheme| uri_scheme.eq_ignore_ascii_case(scheme)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_driver_by_uri_scheme() {
+        assert_eq!(get_driver_by_uri_scheme("postgres").unwrap().name, "postgresql");
+        assert_eq!(get_driver_by_uri_scheme("PostgreSQL").unwrap().name, "postgresql");
+        assert_eq!(get_driver_by_uri_scheme("sqlite").unwrap().name, "sqlite");
+        assert!(get_driver_by_uri_scheme("mysql").is_none());
+        assert!(get_driver_by_uri_scheme("redshift").is_none());
+    }
+}
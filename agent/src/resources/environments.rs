@@ -0,0 +1,317 @@
+use std::collections::BTreeMap;
+use anyhow::{ Context, Result };
+use drivers::redact::{ redact, REDACTED };
+use crate::models::connections::Connection;
+use crate::models::environments::{ Environment, EnvironmentPreview, TemplateError };
+use crate::models::variables::{ Variable, VariableValue };
+use crate::resources::Resource;
+use crate::resources::users::get_collections_dir;
+use crate::utils::validators::Username;
+use crate::{ err_not_found, err_param };
+
+impl Resource for Environment {
+    fn id(&self) -> &str {
+        self.id.as_str()
+    }
+
+    fn name(&self) -> &str {
+        self.name.as_str()
+    }
+}
+
+/// Load an environment of the user from the filesystem.
+pub fn get_environment(username: &Username, id: &str) -> Result<Environment> {
+    // The id is used as a filename, it must be a valid uuid to prevent any path traversal.
+    let Ok(id) = uuid::Uuid::parse_str(id) else {
+        return Err(err_param!("'{}' is not a valid environment id.", id));
+    };
+    let file = get_collections_dir(username).join(id.to_string());
+    if !file.exists() {
+        return Err(err_not_found!("The environment '{}' does not exist.", id));
+    }
+    let content = std::fs
+        ::read_to_string(&file)
+        .with_context(|| format!("Unable to read the environment file: {}", file.display()))?;
+    serde_json
+        ::from_str::<Environment>(&content)
+        .with_context(|| format!("Unable to parse the environment file: {}", file.display()))
+}
+
+/// Render a template (e.g. a connection URI) with the variables of an environment.
+///
+/// Templates are using the Jinja syntax, variables being referenced as `{{ name }}`. The values of the secrets are
+/// masked, as well as anything looking like a password in the rendered text, so the preview can be displayed safely.
+/// The variables without a value or not defined by the environment are rendered as empty strings and reported as
+/// unresolved, the syntax errors are reported with their position in the template.
+pub fn preview(template: &str, variables: &[Variable]) -> EnvironmentPreview {
+    let mut unresolved: Vec<String> = Vec::new();
+    match render(template, variables, true, &mut unresolved) {
+        Ok(rendered) => EnvironmentPreview { rendered: redact(&rendered), unresolved, error: None },
+        Err(error) => EnvironmentPreview { rendered: String::new(), unresolved, error: Some(error) },
+    }
+}
+
+/// Render the connection string of a connection with the variables of an environment.
+///
+/// The connection is rendered the same way as when connecting (see `render_connection`), except for the secrets that
+/// are masked and the unresolved variables that are reported instead of failing.
+pub fn preview_connection(
+    mut connection: Connection,
+    variables: &[Variable],
+    username: &Username
+) -> Result<EnvironmentPreview> {
+    let mut unresolved: Vec<String> = Vec::new();
+    if let Err(error) = render_connection_fields(&mut connection, variables, true, &mut unresolved) {
+        return Ok(EnvironmentPreview { rendered: String::new(), unresolved, error: Some(error) });
+    }
+
+    // The variables are already rendered (with the secrets masked), only the session tags remain to be rendered.
+    connection.environment = None;
+    connection.render(username)?;
+    Ok(EnvironmentPreview { rendered: redact(&connection.to_connection_string()?), unresolved, error: None })
+}
+
+/// Render the fields of a connection with the variables of its environment before connecting to the server.
+///
+/// Nothing is done if the connection is not using an environment. All the variables referenced by the connection must
+/// be resolved by the environment.
+pub fn render_connection(connection: &mut Connection, username: &Username) -> Result<()> {
+    let Some(id) = connection.environment.clone() else {
+        return Ok(());
+    };
+    let variables = get_environment(username, &id)?.variables;
+    let mut unresolved: Vec<String> = Vec::new();
+    render_connection_fields(connection, &variables, false, &mut unresolved).map_err(|e| err_param!("{}", e))?;
+    if !unresolved.is_empty() {
+        return Err(
+            err_param!(
+                "The connection is using variables not resolved by the environment '{}': {}.",
+                id,
+                unresolved.join(", ")
+            )
+        );
+    }
+    Ok(())
+}
+
+/// Render the fields of a connection defining how to connect to the server (host, socket, file, connection string,
+/// username, password & datasource).
+fn render_connection_fields(
+    connection: &mut Connection,
+    variables: &[Variable],
+    mask_secrets: bool,
+    unresolved: &mut Vec<String>
+) -> Result<(), TemplateError> {
+    for (name, field) in [
+        ("host", &mut connection.host),
+        ("socket", &mut connection.socket),
+        ("file", &mut connection.file),
+        ("connection_string", &mut connection.connection_string),
+        ("username", &mut connection.username),
+        ("password", &mut connection.password),
+        ("datasource", &mut connection.datasource),
+    ] {
+        *field = render(field, variables, mask_secrets, unresolved).map_err(|error| TemplateError {
+            field: name.to_string(),
+            ..error
+        })?;
+    }
+    Ok(())
+}
+
+/// Render a template with the variables of an environment.
+///
+/// The names of the variables referenced by the template that cannot be resolved are added to `unresolved`, they are
+/// rendered as empty strings.
+fn render(
+    template: &str,
+    variables: &[Variable],
+    mask_secrets: bool,
+    unresolved: &mut Vec<String>
+) -> Result<String, TemplateError> {
+    let env = minijinja::Environment::new();
+    let compiled = env.template_from_str(template).map_err(|e| to_template_error(template, e))?;
+    let mut names: Vec<String> = compiled.undeclared_variables(false).into_iter().collect();
+    names.sort();
+    let mut context: BTreeMap<String, minijinja::Value> = BTreeMap::new();
+    for name in names {
+        match variables.iter().find(|variable| variable.name == name).and_then(|variable| variable.value.as_ref()) {
+            Some(value) => {
+                context.insert(name, to_template_value(value, mask_secrets));
+            }
+            None if !unresolved.contains(&name) => unresolved.push(name),
+            None => {}
+        }
+    }
+    compiled.render(context).map_err(|e| to_template_error(template, e))
+}
+
+/// Convert an error raised by the template engine into a `TemplateError`.
+fn to_template_error(template: &str, e: minijinja::Error) -> TemplateError {
+    let column = e
+        .range()
+        .and_then(|range| template.get(..range.start))
+        .map(|before| before.rsplit('\n').next().unwrap_or_default().chars().count() + 1);
+    TemplateError {
+        field: String::new(),
+        message: e.detail().map_or_else(|| e.kind().to_string(), str::to_string),
+        line: e.line(),
+        column,
+    }
+}
+
+impl std::fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.field.as_str() {
+            "" => write!(f, "Invalid template")?,
+            field => write!(f, "Invalid template in '{}'", field)?,
+        }
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(f, " (line {}, column {})", line, column)?,
+            (Some(line), None) => write!(f, " (line {})", line)?,
+            _ => {}
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// Convert the value of a variable into the value used to render a template.
+fn to_template_value(value: &VariableValue, mask_secrets: bool) -> minijinja::Value {
+    match value {
+        VariableValue::Text(value) | VariableValue::Date(value) | VariableValue::Timestamp(value) => {
+            minijinja::Value::from(value.as_str())
+        }
+        VariableValue::Boolean(value) => minijinja::Value::from(*value),
+        VariableValue::Float(value) => minijinja::Value::from(*value),
+        VariableValue::Integer(value) => minijinja::Value::from(*value),
+        VariableValue::Secret(_) if mask_secrets => minijinja::Value::from(REDACTED),
+        VariableValue::Secret(value) => minijinja::Value::from(value.as_str()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::connections::ConnectionMode;
+    use crate::resources::catalog::CatalogSection;
+    use crate::resources::users::{ create_user, create_user_resource };
+    use crate::utils::tests::settings;
+
+    fn variable(name: &str, value: Option<VariableValue>) -> Variable {
+        Variable { name: name.to_string(), value, ..Default::default() }
+    }
+
+    #[test]
+    fn test_preview() {
+        let variables = vec![
+            variable("host", Some(VariableValue::Text("db.local".to_string()))),
+            variable("port", Some(VariableValue::Integer(5432))),
+            variable("password", Some(VariableValue::Secret("tiger".to_string()))),
+            variable("database", None)
+        ];
+
+        // 1) all the variables are resolved, the secrets are masked
+        let preview = super::preview("postgresql://scott:{{ password }}@{{ host }}:{{ port }}/db", &variables);
+        assert_eq!(preview.rendered, format!("postgresql://scott:{}@db.local:5432/db", REDACTED));
+        assert!(preview.unresolved.is_empty());
+        assert!(preview.error.is_none());
+
+        // 2) the variables without a value or unknown are rendered as empty strings and reported
+        let preview = super::preview("{{ host }}/{{ database }}?{{ unknown }}&{{ database }}", &variables);
+        assert_eq!(preview.rendered, "db.local/?&");
+        assert_eq!(preview.unresolved, vec!["database".to_string(), "unknown".to_string()]);
+
+        // 3) the passwords are masked even if they are not coming from a secret
+        let preview = super::preview("postgresql://scott:{{ host }}@localhost", &variables);
+        assert_eq!(preview.rendered, format!("postgresql://scott:{}@localhost", REDACTED));
+
+        // 4) the filters of Jinja can be used
+        assert_eq!(super::preview("{{ host | upper }}", &variables).rendered, "DB.LOCAL");
+
+        // 5) syntax error, reported with its position
+        let preview = super::preview("postgresql://\n{{ host }@localhost", &variables);
+        assert_eq!(preview.rendered, "");
+        let error = preview.error.unwrap();
+        assert_eq!(error.line, Some(2));
+        assert!(error.column.is_some());
+        assert!(!error.message.is_empty());
+    }
+
+    #[test]
+    fn test_preview_connection() {
+        let variables = vec![
+            variable("host", Some(VariableValue::Text("db.local".to_string()))),
+            variable("password", Some(VariableValue::Secret("tiger".to_string())))
+        ];
+        let connection = || Connection {
+            driver: "postgresql".to_string(),
+            mode: ConnectionMode::Host,
+            host: "{{ host }}".to_string(),
+            port: Some(5432),
+            username: "scott{{ user }}".to_string(),
+            password: "{{ password }}".to_string(),
+            ..Default::default()
+        };
+
+        // 1) the variables are rendered, the secrets are masked
+        let preview = preview_connection(connection(), &variables, &"marty.mcfly".into()).unwrap();
+        assert_eq!(preview.rendered, "host=db.local password=**** port=5432 user=scott");
+        assert_eq!(preview.unresolved, vec!["user".to_string()]);
+
+        // 2) syntax error, reported with the field
+        let preview = preview_connection(
+            Connection { datasource: "{% if %}".to_string(), ..connection() },
+            &variables,
+            &"marty.mcfly".into()
+        ).unwrap();
+        let error = preview.error.unwrap();
+        assert_eq!(error.field, "datasource");
+        assert_eq!(error.line, Some(1));
+        assert!(error.to_string().starts_with("Invalid template in 'datasource' (line 1"));
+    }
+
+    #[test]
+    fn test_render_connection() {
+        // setup
+        let username: Username = "marty.mcfly".into();
+        let temp_dir = tempfile::tempdir().unwrap();
+        settings::set_base_dir(temp_dir.path().to_str().unwrap().to_string());
+        create_user(&username).unwrap();
+        let environment = Environment {
+            name: "Production".to_string(),
+            variables: vec![
+                variable("host", Some(VariableValue::Text("db.local".to_string()))),
+                variable("password", Some(VariableValue::Secret("tiger".to_string())))
+            ],
+            ..Default::default()
+        };
+        create_user_resource(&username, &CatalogSection::Environments.as_path(), &environment).unwrap();
+        let connection = || Connection {
+            host: "{{ host }}".to_string(),
+            password: "{{ password }}".to_string(),
+            environment: Some(environment.id.clone()),
+            ..Default::default()
+        };
+
+        // 1) the secrets are not masked when connecting
+        let mut rendered = connection();
+        render_connection(&mut rendered, &username).unwrap();
+        assert_eq!(rendered.host, "db.local");
+        assert_eq!(rendered.password, "tiger");
+
+        // 2) no environment, the templates are left as is
+        let mut rendered = Connection { environment: None, ..connection() };
+        render_connection(&mut rendered, &username).unwrap();
+        assert_eq!(rendered.host, "{{ host }}");
+
+        // 3) unresolved variables and syntax errors
+        let mut rendered = Connection { username: "{{ user }}".to_string(), ..connection() };
+        assert!(render_connection(&mut rendered, &username).unwrap_err().to_string().contains("user"));
+        let mut rendered = Connection { host: "{{ host ".to_string(), ..connection() };
+        assert!(render_connection(&mut rendered, &username).is_err());
+
+        // 4) unknown environment
+        let mut rendered = Connection { environment: Some(uuid::Uuid::new_v4().to_string()), ..connection() };
+        assert!(render_connection(&mut rendered, &username).is_err());
+    }
+}
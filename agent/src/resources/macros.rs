@@ -0,0 +1,202 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use anyhow::Result;
+use lazy_static::lazy_static;
+use tokio::sync::broadcast;
+use crate::models::macros::{ KeyboardMacro, KeyboardMacroChange, KeyboardMacroRequest };
+use crate::utils::constants::{ MACROS_CHANGES_CAPACITY, MACROS_FILENAME };
+use crate::utils::json_store::{ read_json_file, write_json_file };
+use crate::utils::time::now;
+use crate::utils::validators::Username;
+use crate::{ err_conflict, err_not_found, err_param, settings };
+
+lazy_static! {
+    // Serialize the updates of the macros files (read, update & write).
+    static ref MACROS_LOCK: Mutex<()> = Mutex::new(());
+
+    // The changes of the macros of all the users (username, change), see `subscribe_changes`.
+    static ref MACROS_CHANGES: broadcast::Sender<(String, KeyboardMacroChange)> = broadcast::channel(
+        MACROS_CHANGES_CAPACITY
+    ).0;
+}
+
+/// Get notified of the changes of the keyboard macros of all the users (the username comes with each change).
+///
+/// If the receiver falls behind by more than `MACROS_CHANGES_CAPACITY` changes, the oldest ones are lost (see
+/// `broadcast::error::RecvError::Lagged`).
+pub fn subscribe_changes() -> broadcast::Receiver<(String, KeyboardMacroChange)> {
+    MACROS_CHANGES.subscribe()
+}
+
+/// Notify the receivers of a change (there may be no receiver at all).
+fn notify_change(username: &Username, change: KeyboardMacroChange) {
+    let _ = MACROS_CHANGES.send((username.to_string(), change));
+}
+
+/// Get the file used to store the keyboard macros of a user.
+fn get_macros_file(username: &Username) -> PathBuf {
+    settings::get_user_dir(username.as_str()).join(MACROS_FILENAME)
+}
+
+/// Read the keyboard macros of a user (by id).
+fn read_macros(username: &Username) -> Result<BTreeMap<String, KeyboardMacro>> {
+    read_json_file(&get_macros_file(username))
+}
+
+/// Write the keyboard macros of a user.
+fn write_macros(username: &Username, macros: &BTreeMap<String, KeyboardMacro>) -> Result<()> {
+    write_json_file(&get_macros_file(username), macros)
+}
+
+/// Check the request and return it with its name and key binding trimmed.
+///
+/// A key binding can only be used by one macro (except the one being updated, if any).
+fn check_request(
+    macros: &BTreeMap<String, KeyboardMacro>,
+    request: KeyboardMacroRequest,
+    id: Option<&str>
+) -> Result<KeyboardMacroRequest> {
+    let name = request.name.trim();
+    if name.is_empty() {
+        return Err(err_param!("The name of the macro cannot be empty."));
+    }
+    let key_binding = request.key_binding.trim();
+    if key_binding.is_empty() {
+        return Err(err_param!("The key binding of the macro cannot be empty."));
+    }
+    if
+        let Some(other) = macros
+            .values()
+            .find(|other| Some(other.id.as_str()) != id && other.key_binding.eq_ignore_ascii_case(key_binding))
+    {
+        return Err(err_conflict!("The key binding '{}' is already used by the macro '{}'.", key_binding, other.name));
+    }
+    Ok(KeyboardMacroRequest {
+        name: name.to_string(),
+        key_binding: key_binding.to_string(),
+        action: request.action,
+    })
+}
+
+/// Get the keyboard macros of a user (sorted by name).
+pub fn list_macros(username: &Username) -> Result<Vec<KeyboardMacro>> {
+    let mut macros: Vec<KeyboardMacro> = read_macros(username)?.into_values().collect();
+    macros.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.id.cmp(&b.id)));
+    Ok(macros)
+}
+
+/// Create a keyboard macro for a user.
+pub fn create_macro(username: &Username, request: KeyboardMacroRequest) -> Result<KeyboardMacro> {
+    let _lock = MACROS_LOCK.lock().unwrap();
+    let mut macros = read_macros(username)?;
+    let request = check_request(&macros, request, None)?;
+    let keyboard_macro = KeyboardMacro {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: request.name,
+        key_binding: request.key_binding,
+        action: request.action,
+        updated_at: now()?,
+    };
+    macros.insert(keyboard_macro.id.clone(), keyboard_macro.clone());
+    write_macros(username, &macros)?;
+    notify_change(username, KeyboardMacroChange::Created { keyboard_macro: keyboard_macro.clone() });
+    Ok(keyboard_macro)
+}
+
+/// Update a keyboard macro of a user.
+pub fn update_macro(username: &Username, id: &str, request: KeyboardMacroRequest) -> Result<KeyboardMacro> {
+    let _lock = MACROS_LOCK.lock().unwrap();
+    let mut macros = read_macros(username)?;
+    if !macros.contains_key(id) {
+        return Err(err_not_found!("The macro '{}' does not exist.", id));
+    }
+    let request = check_request(&macros, request, Some(id))?;
+    let keyboard_macro = KeyboardMacro {
+        id: id.to_string(),
+        name: request.name,
+        key_binding: request.key_binding,
+        action: request.action,
+        updated_at: now()?,
+    };
+    macros.insert(keyboard_macro.id.clone(), keyboard_macro.clone());
+    write_macros(username, &macros)?;
+    notify_change(username, KeyboardMacroChange::Updated { keyboard_macro: keyboard_macro.clone() });
+    Ok(keyboard_macro)
+}
+
+/// Delete a keyboard macro of a user.
+pub fn delete_macro(username: &Username, id: &str) -> Result<()> {
+    let _lock = MACROS_LOCK.lock().unwrap();
+    let mut macros = read_macros(username)?;
+    if macros.remove(id).is_none() {
+        return Err(err_not_found!("The macro '{}' does not exist.", id));
+    }
+    write_macros(username, &macros)?;
+    notify_change(username, KeyboardMacroChange::Deleted { id: id.to_string() });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::users::create_user;
+    use crate::utils::tests::settings;
+    use crate::utils::user_error::UserError;
+    use serde_json::json;
+
+    #[test]
+    fn test_macros() {
+        // setup
+        let temp_dir = tempfile::tempdir().unwrap();
+        settings::set_base_dir(temp_dir.path().to_str().unwrap().to_string());
+        let username: Username = "local".into();
+        create_user(&username).unwrap();
+        let request = |name: &str, key_binding: &str| KeyboardMacroRequest {
+            name: name.to_string(),
+            key_binding: key_binding.to_string(),
+            action: json!({ "command": "format" }),
+        };
+        let error = |result: Result<KeyboardMacro>| result.unwrap_err().downcast::<UserError>().unwrap();
+        let mut changes = subscribe_changes();
+        let mut next_change = || {
+            // the tests of other users may run in parallel
+            loop {
+                match changes.try_recv().unwrap() {
+                    (owner, change) if owner == username.as_str() => {
+                        return serde_json::to_value(change).unwrap();
+                    }
+                    _ => {}
+                }
+            }
+        };
+
+        // 1) create
+        assert!(list_macros(&username).unwrap().is_empty());
+        let format = create_macro(&username, request(" Format ", "Ctrl+Shift+F")).unwrap();
+        assert_eq!(format.name, "Format");
+        assert!(matches!(error(create_macro(&username, request("", "Ctrl+K"))), UserError::InvalidParameter(_)));
+        assert!(matches!(error(create_macro(&username, request("Run", " "))), UserError::InvalidParameter(_)));
+        assert!(matches!(error(create_macro(&username, request("Run", "ctrl+shift+f"))), UserError::Conflict(_)));
+        let run = create_macro(&username, request("Run", "Ctrl+Enter")).unwrap();
+        assert_eq!(next_change(), json!({ "type": "created", "keyboard_macro": format }));
+        assert_eq!(next_change(), json!({ "type": "created", "keyboard_macro": run }));
+        let names: Vec<String> = list_macros(&username).unwrap().into_iter().map(|m| m.name).collect();
+        assert_eq!(names, vec!["Format", "Run"]);
+
+        // 2) update
+        let updated = update_macro(&username, &format.id, request("Format SQL", "Ctrl+Shift+F")).unwrap();
+        assert_eq!(updated.id, format.id);
+        assert_eq!(list_macros(&username).unwrap()[0].name, "Format SQL");
+        assert_eq!(next_change(), json!({ "type": "updated", "keyboard_macro": updated }));
+        let result = update_macro(&username, &run.id, request("Run", "Ctrl+Shift+F"));
+        assert!(matches!(error(result), UserError::Conflict(_)));
+        assert!(matches!(error(update_macro(&username, "unknown", request("Run", "F5"))), UserError::NotFound(_)));
+
+        // 3) delete
+        assert!(delete_macro(&username, &run.id).is_ok());
+        assert_eq!(next_change(), json!({ "type": "deleted", "id": run.id }));
+        assert!(delete_macro(&username, &run.id).is_err());
+        assert_eq!(list_macros(&username).unwrap().len(), 1);
+    }
+}
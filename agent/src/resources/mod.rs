@@ -1,5 +1,14 @@
+pub mod access_tokens;
 pub mod catalog;
 pub mod connections;
+pub mod drivers;
+pub mod environments;
+pub mod macros;
+pub mod plans;
+pub mod queries;
+pub mod samples;
+pub mod tables;
+pub mod transactions;
 pub mod users;
 pub mod workspaces;
 
@@ -0,0 +1,72 @@
+use anyhow::Result;
+use drivers::driver::{ DriverConnection, DriverExecutor };
+use drivers::factory::AnyDriver;
+use drivers::plan::{ get_explain_query, parse_plan, PlanNode };
+use drivers::value::DriverValue;
+use futures::TryStreamExt;
+use crate::models::connections::Connection;
+use crate::models::plans::QueryPlanNode;
+
+impl From<PlanNode> for QueryPlanNode {
+    fn from(node: PlanNode) -> Self {
+        Self {
+            operation: node.operation,
+            relation: node.relation,
+            cost: node.cost,
+            rows: node.rows,
+            children: node.children.into_iter().map(QueryPlanNode::from).collect(),
+        }
+    }
+}
+
+/// Get the plan of a query.
+///
+/// The connection must already be rendered (see `Connection::render`).
+pub async fn get_query_plan(connection: &Connection, query: &str) -> Result<QueryPlanNode> {
+    let mut driver = connection.create_driver()?;
+    driver.connect().await?;
+    let result = fetch_query_plan(&mut driver, query).await;
+    let _ = driver.close().await;
+    result
+}
+
+/// Get the plan of a query using a driver already connected (e.g. pinned to a transaction).
+pub async fn fetch_query_plan(driver: &mut AnyDriver, query: &str) -> Result<QueryPlanNode> {
+    let info = driver.get_info().await?;
+    let explain_query = get_explain_query(query, &info)?;
+    let rows: Vec<DriverValue> = driver.query(&explain_query).await?.try_collect().await?;
+    Ok(parse_plan(rows, &info)?.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use drivers::factory::DriverFactory;
+    use crate::models::connections::ConnectionMode;
+
+    #[tokio::test]
+    async fn test_get_query_plan() {
+        // setup
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file = temp_dir.path().join("test.db");
+        let connection = Connection {
+            driver: "sqlite".to_string(),
+            mode: ConnectionMode::ConnectionString,
+            connection_string: format!("sqlite://{}?mode=rwc", file.to_str().unwrap()),
+            ..Default::default()
+        };
+        let connection_string = connection.to_connection_string().unwrap();
+        let mut driver = AnyDriver::new(DriverFactory::create("sqlite", connection_string).unwrap());
+        driver.connect().await.unwrap();
+        drivers::driver::execute_query(&mut driver, "CREATE TABLE users (id INTEGER, name TEXT)").await.unwrap();
+        driver.close().await.unwrap();
+
+        // 1) the plan is normalized
+        let plan = get_query_plan(&connection, "SELECT * FROM users WHERE name = 'marty'").await.unwrap();
+        assert_eq!(plan.children.len(), 1);
+        assert_eq!(plan.children[0].relation.as_deref(), Some("users"));
+
+        // 2) invalid query
+        assert!(get_query_plan(&connection, "SELECT * FROM unknown").await.is_err());
+    }
+}
@@ -0,0 +1,76 @@
+use anyhow::{ Context, Result };
+use crate::models::queries::SavedQuery;
+use crate::resources::Resource;
+use crate::resources::users::get_collections_dir;
+use crate::utils::validators::Username;
+use crate::{ err_not_found, err_param };
+
+impl Resource for SavedQuery {
+    fn id(&self) -> &str {
+        self.id.as_str()
+    }
+
+    fn name(&self) -> &str {
+        self.name.as_str()
+    }
+}
+
+/// Load a saved query of the user from the filesystem.
+pub fn get_saved_query(username: &Username, id: &str) -> Result<SavedQuery> {
+    // The id is used as a filename, it must be a valid uuid to prevent any path traversal.
+    let Ok(id) = uuid::Uuid::parse_str(id) else {
+        return Err(err_param!("'{}' is not a valid query id.", id));
+    };
+    let file = get_collections_dir(username).join(id.to_string());
+    if !file.exists() {
+        return Err(err_not_found!("The query '{}' does not exist.", id));
+    }
+    let content = std::fs
+        ::read_to_string(&file)
+        .with_context(|| format!("Unable to read the query file: {}", file.display()))?;
+    serde_json
+        ::from_str::<SavedQuery>(&content)
+        .with_context(|| format!("Unable to parse the query file: {}", file.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::collections::CatalogEntryType;
+    use crate::resources::catalog::{ self, CatalogSection };
+    use crate::resources::users::{ create_user, create_user_resource };
+    use crate::utils::tests::settings;
+    use crate::utils::user_error::UserError;
+    use crate::utils::validators::CatalogPath;
+
+    #[test]
+    fn test_saved_query() {
+        // setup
+        let temp_dir = tempfile::tempdir().unwrap();
+        settings::set_base_dir(temp_dir.path().to_str().unwrap().to_string());
+        let username: Username = "local".into();
+        create_user(&username).unwrap();
+
+        // 1) save a query in a folder of the catalog
+        let folder = CatalogPath::from("queries/reports");
+        catalog::create_dir(&username, &folder).unwrap();
+        let query = SavedQuery::new("Active users".to_string(), "SELECT * FROM users WHERE active".to_string());
+        let entry = create_user_resource(&username, &folder, &query).unwrap();
+        assert_eq!(entry.item_type, CatalogEntryType::Query);
+        assert_eq!(catalog::read_tree(&username, &CatalogSection::Queries.as_path()).unwrap()[0].children.len(), 1);
+
+        // 2) open it
+        let saved_query = get_saved_query(&username, &query.id).unwrap();
+        assert_eq!(saved_query.name, "Active users");
+        assert_eq!(saved_query.query, "SELECT * FROM users WHERE active");
+
+        // 3) invalid or unknown id
+        assert!(get_saved_query(&username, "../user.json").is_err());
+        assert!(
+            matches!(
+                get_saved_query(&username, &uuid::Uuid::new_v4().to_string()).unwrap_err().downcast_ref::<UserError>(),
+                Some(UserError::NotFound(_))
+            )
+        );
+    }
+}
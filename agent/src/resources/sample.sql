@@ -0,0 +1,56 @@
+CREATE TABLE dim_customer (
+    customer_id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    city TEXT NOT NULL,
+    country TEXT NOT NULL
+);
+INSERT INTO dim_customer (customer_id, name, city, country) VALUES
+    (1, 'Marty McFly', 'Hill Valley', 'USA'),
+    (2, 'Emmett Brown', 'Hill Valley', 'USA'),
+    (3, 'Lorraine Baines', 'Hill Valley', 'USA'),
+    (4, 'Jules Verne', 'Nantes', 'France'),
+    (5, 'Ada Lovelace', 'London', 'United Kingdom');
+CREATE TABLE dim_product (
+    product_id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    category TEXT NOT NULL,
+    unit_price REAL NOT NULL
+);
+INSERT INTO dim_product (product_id, name, category, unit_price) VALUES
+    (1, 'Hoverboard', 'Sports', 299.99),
+    (2, 'Flux Capacitor', 'Electronics', 1210.00),
+    (3, 'Self-Lacing Sneakers', 'Apparel', 149.50),
+    (4, 'Sports Almanac', 'Books', 19.85),
+    (5, 'Plutonium Case', 'Electronics', 455.00);
+CREATE TABLE dim_date (
+    date_id INTEGER PRIMARY KEY,
+    date TEXT NOT NULL,
+    year INTEGER NOT NULL,
+    quarter INTEGER NOT NULL,
+    month INTEGER NOT NULL
+);
+INSERT INTO dim_date (date_id, date, year, quarter, month) VALUES
+    (20240115, '2024-01-15', 2024, 1, 1),
+    (20240212, '2024-02-12', 2024, 1, 2),
+    (20240408, '2024-04-08', 2024, 2, 4),
+    (20240701, '2024-07-01', 2024, 3, 7),
+    (20241021, '2024-10-21', 2024, 4, 10);
+CREATE TABLE fact_sales (
+    sale_id INTEGER PRIMARY KEY,
+    date_id INTEGER NOT NULL REFERENCES dim_date (date_id),
+    customer_id INTEGER NOT NULL REFERENCES dim_customer (customer_id),
+    product_id INTEGER NOT NULL REFERENCES dim_product (product_id),
+    quantity INTEGER NOT NULL,
+    amount REAL NOT NULL
+);
+INSERT INTO fact_sales (sale_id, date_id, customer_id, product_id, quantity, amount) VALUES
+    (1, 20240115, 1, 1, 1, 299.99),
+    (2, 20240115, 2, 2, 1, 1210.00),
+    (3, 20240212, 3, 3, 2, 299.00),
+    (4, 20240212, 1, 4, 1, 19.85),
+    (5, 20240408, 4, 4, 3, 59.55),
+    (6, 20240408, 2, 5, 2, 910.00),
+    (7, 20240701, 5, 2, 1, 1210.00),
+    (8, 20240701, 1, 3, 1, 149.50),
+    (9, 20241021, 3, 1, 2, 599.98),
+    (10, 20241021, 5, 4, 4, 79.40);
@@ -0,0 +1,167 @@
+use std::path::{ Path, PathBuf };
+use anyhow::{ Context, Result };
+use drivers::driver::{ execute_query, DriverConnection };
+use drivers::factory::{ AnyDriver, DriverFactory };
+use crate::models::connections::{ Connection, ConnectionMode };
+use crate::models::collections::{ CatalogEntry, CatalogEntryType, CatalogTreeEntry };
+use crate::resources::catalog::{ self, CatalogSection };
+use crate::resources::connections::to_sqlite_file_uri;
+use crate::resources::users::create_user_resource;
+use crate::settings;
+use crate::utils::constants::{
+    SAMPLE_CONNECTION_ID_FILENAME,
+    SAMPLE_CONNECTION_NAME,
+    SAMPLE_DATABASE_FILENAME,
+    USER_DATA_DIRNAME,
+};
+use crate::utils::validators::Username;
+
+/// The script used to populate the sample database (a small star schema of sales).
+const SAMPLE_DATABASE_SCRIPT: &str = include_str!("sample.sql");
+
+/// Create the sample connection for the user.
+///
+/// The sample connection is an SQLite database stored in the data directory of the user, populated with a small
+/// dataset so the user can try the application without having to configure a real database.
+///
+/// ```text
+/// users
+/// └── :username
+///     ├── catalog
+///     │   └── connections
+///     │       └── Sample Database.json    <--- connection entry in the catalog
+///     ├── collections
+///     │   └── 2a8f5bc1-...                <--- connection file in collections
+///     └── data
+///         ├── sample.db                   <--- the sample database
+///         └── sample.id                   <--- the identifier of the sample connection
+/// ```
+pub async fn create_sample_connection(username: &Username) -> Result<CatalogEntry> {
+    let file = get_sample_database_path(username);
+    create_sample_database(&file).await?;
+
+    let connection = Connection {
+        driver: "sqlite".to_string(),
+        mode: ConnectionMode::File,
+        file: file.to_string_lossy().to_string(),
+        ..Connection::new(SAMPLE_CONNECTION_NAME.to_string())
+    };
+    let entry = create_user_resource(username, &CatalogSection::Connections.as_path(), &connection)?;
+    let id_file = get_sample_connection_id_path(username);
+    std::fs
+        ::write(&id_file, &entry.id)
+        .with_context(|| format!("Unable to save the sample connection identifier: {}", id_file.display()))?;
+    Ok(entry)
+}
+
+/// Reset the sample database of the user to its initial state.
+///
+/// The sample connection is tracked by its identifier, so it is still found after being renamed or moved in the
+/// catalog. If it has been removed from the catalog, it is created again.
+pub async fn reset_sample_connection(username: &Username) -> Result<()> {
+    if has_sample_connection(username)? {
+        create_sample_database(&get_sample_database_path(username)).await
+    } else {
+        create_sample_connection(username).await.map(|_| ())
+    }
+}
+
+/// Check if the sample connection created for the user is still in the catalog.
+fn has_sample_connection(username: &Username) -> Result<bool> {
+    let Ok(id) = std::fs::read_to_string(get_sample_connection_id_path(username)) else {
+        return Ok(false);
+    };
+    let tree = catalog::read_tree(username, &CatalogSection::Connections.as_path())?;
+    return Ok(contains_connection(&tree, id.trim()));
+
+    fn contains_connection(tree: &[CatalogTreeEntry], id: &str) -> bool {
+        tree.iter().any(|tree_entry| {
+            (tree_entry.entry.item_type == CatalogEntryType::Connection && tree_entry.entry.id == id) ||
+                contains_connection(&tree_entry.children, id)
+        })
+    }
+}
+
+fn get_sample_database_path(username: &Username) -> PathBuf {
+    settings::get_user_dir(username.as_str()).join(USER_DATA_DIRNAME).join(SAMPLE_DATABASE_FILENAME)
+}
+
+fn get_sample_connection_id_path(username: &Username) -> PathBuf {
+    settings::get_user_dir(username.as_str()).join(USER_DATA_DIRNAME).join(SAMPLE_CONNECTION_ID_FILENAME)
+}
+
+/// Create (or re-create) the sample database.
+async fn create_sample_database(file: &Path) -> Result<()> {
+    if file.exists() {
+        std::fs
+            ::remove_file(file)
+            .with_context(|| format!("Unable to delete the sample database: {}", file.display()))?;
+    }
+
+    let connection_string = format!("{}?mode=rwc", to_sqlite_file_uri(&file.to_string_lossy()));
+    let mut driver = AnyDriver::new(DriverFactory::create("sqlite", connection_string)?);
+    driver.connect().await.context("Unable to create the sample database.")?;
+    for statement in SAMPLE_DATABASE_SCRIPT.split(';').map(str::trim).filter(|s| !s.is_empty()) {
+        execute_query(&mut driver, statement).await?;
+    }
+    driver.close().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::users::create_user;
+    use crate::utils::tests::settings;
+    use crate::utils::validators::{ join_catalog_path, sanitize_catalog_path_component };
+
+    #[tokio::test]
+    async fn test_sample_connection() {
+        // setup
+        let username: Username = "marty.mcfly".into();
+        let temp_dir = tempfile::tempdir().unwrap();
+        settings::set_base_dir(temp_dir.path().to_str().unwrap().to_string());
+        create_user(&username).unwrap();
+
+        // 1) create the sample connection
+        let entry = create_sample_connection(&username).await.unwrap();
+        assert_eq!(entry.name, SAMPLE_CONNECTION_NAME);
+        let file = get_sample_database_path(&username);
+        assert!(file.exists());
+
+        // 2) the sample database is populated
+        let connection_string = to_sqlite_file_uri(&file.to_string_lossy());
+        let mut driver = AnyDriver::new(DriverFactory::create("sqlite", connection_string.clone()).unwrap());
+        driver.connect().await.unwrap();
+        assert_eq!(execute_query(&mut driver, "DELETE FROM fact_sales").await.unwrap(), 10);
+        driver.close().await.unwrap();
+
+        // 3) reset the sample database
+        reset_sample_connection(&username).await.unwrap();
+        let mut driver = AnyDriver::new(DriverFactory::create("sqlite", connection_string).unwrap());
+        driver.connect().await.unwrap();
+        assert_eq!(execute_query(&mut driver, "DELETE FROM fact_sales").await.unwrap(), 10);
+        driver.close().await.unwrap();
+
+        // 4) reset the sample database after renaming the sample connection (no new connection is created)
+        let catalog_path = CatalogSection::Connections.as_path();
+        let sample_path = join_catalog_path(
+            &catalog_path,
+            &sanitize_catalog_path_component(SAMPLE_CONNECTION_NAME).unwrap()
+        );
+        let new_name = sanitize_catalog_path_component("My Sample").unwrap();
+        catalog::rename(&username, &sample_path, &new_name).unwrap();
+        reset_sample_connection(&username).await.unwrap();
+        let tree = catalog::read_tree(&username, &catalog_path).unwrap();
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0].entry.id, entry.id);
+        assert_eq!(tree[0].entry.name, "My Sample");
+
+        // 5) reset the sample database after deleting the sample connection (the connection is created again)
+        catalog::delete(&username, &join_catalog_path(&catalog_path, &new_name)).unwrap();
+        reset_sample_connection(&username).await.unwrap();
+        let tree = catalog::read_tree(&username, &catalog_path).unwrap();
+        assert_eq!(tree.len(), 1);
+        assert_ne!(tree[0].entry.id, entry.id);
+        assert_eq!(tree[0].entry.name, SAMPLE_CONNECTION_NAME);
+    }
+}
@@ -0,0 +1,278 @@
+use anyhow::Result;
+use drivers::driver::{ DriverConnection, DriverExecutor, QueryParams };
+use drivers::factory::AnyDriver;
+use drivers::sql::{ get_columns_query, get_page_query, get_schemas_query, get_tables_query, supports_paging, OrderBy };
+use drivers::value::DriverValue;
+use futures::TryStreamExt;
+use serde_json::Value;
+use crate::models::connections::Connection;
+use crate::models::tables::{ ColumnInfo, SchemaInfo, TableInfo, TableReference, TableType };
+use crate::err_not_found;
+
+/// Get the first rows of a table.
+///
+/// The connection must already be rendered (see `Connection::render`).
+pub async fn peek_table(connection: &Connection, table: &TableReference, limit: u64) -> Result<Vec<Vec<Value>>> {
+    get_table_rows(connection, table, &[], 0, limit).await
+}
+
+/// Get a page of rows from a table.
+///
+/// The paging is done by the server when supported, otherwise the rows are skipped while fetching.
+/// The connection must already be rendered (see `Connection::render`).
+pub async fn get_table_rows(
+    connection: &Connection,
+    table: &TableReference,
+    order_by: &[OrderBy],
+    offset: u64,
+    limit: u64
+) -> Result<Vec<Vec<Value>>> {
+    let mut driver = connection.create_driver()?;
+    driver.connect().await?;
+    let result = fetch_table_rows(&mut driver, table, order_by, offset, limit).await;
+    let _ = driver.close().await;
+    result
+}
+
+/// Get a page of rows from a table using a driver already connected (e.g. pinned to a transaction).
+pub async fn fetch_table_rows(
+    driver: &mut AnyDriver,
+    table: &TableReference,
+    order_by: &[OrderBy],
+    offset: u64,
+    limit: u64
+) -> Result<Vec<Vec<Value>>> {
+    let info = driver.get_info().await?;
+    let query = get_page_query(table.schema.as_deref(), &table.table, order_by, offset, limit, &info);
+    let mut skip = if supports_paging(&info) { 0 } else { offset };
+    let mut stream = driver.query(&query).await?;
+    let mut rows = Vec::new();
+    while (rows.len() as u64) < limit {
+        let Some(value) = stream.try_next().await? else {
+            break;
+        };
+        if skip > 0 {
+            skip -= 1;
+            continue;
+        }
+        match value {
+            DriverValue::Array(values) => rows.push(values.into_iter().map(to_json_value).collect()),
+            value => rows.push(vec![to_json_value(value)]),
+        }
+    }
+    Ok(rows)
+}
+
+/// Connect to a datasource of a connection.
+///
+/// The datasource must be either the default datasource of the connection or one of the datasources it lists.
+/// The connection must already be rendered (see `Connection::render`).
+pub async fn connect_datasource(mut connection: Connection, datasource: &str) -> Result<AnyDriver> {
+    if connection.datasource != datasource && !connection.datasources.iter().any(|ds| ds.name == datasource) {
+        return Err(err_not_found!("The datasource '{}' does not exist.", datasource));
+    }
+    connection.datasource = datasource.to_string();
+    let mut driver = connection.create_driver()?;
+    driver.connect().await?;
+    Ok(driver)
+}
+
+/// Run a catalog query and get all the rows, each row being an array of values.
+///
+/// The names of the schemas and tables are bound to the parameters of the query, never inlined into the query.
+async fn fetch_catalog_rows(driver: &mut AnyDriver, query: &str, params: QueryParams) -> Result<Vec<Vec<DriverValue>>> {
+    let mut stream = if params == QueryParams::Named(Vec::new()) {
+        driver.query(query).await?
+    } else {
+        driver.query_with_params(query, params).await?
+    };
+    let mut rows = Vec::new();
+    while let Some(value) = stream.try_next().await? {
+        match value {
+            DriverValue::Array(values) => rows.push(values),
+            value => rows.push(vec![value]),
+        }
+    }
+    Ok(rows)
+}
+
+/// Convert a value returned by a catalog query into a text.
+fn to_text(value: DriverValue) -> String {
+    match value {
+        DriverValue::Text(value) => value,
+        DriverValue::Null => String::new(),
+        value => to_json_value(value).to_string(),
+    }
+}
+
+/// Get the schemas of the datasource a driver is connected to.
+pub async fn list_schemas(driver: &mut AnyDriver) -> Result<Vec<SchemaInfo>> {
+    let info = driver.get_info().await?;
+    let rows = fetch_catalog_rows(driver, &get_schemas_query(&info), QueryParams::Named(Vec::new())).await?;
+    Ok(
+        rows
+            .into_iter()
+            .filter_map(|row| row.into_iter().next())
+            .map(|name| SchemaInfo { name: to_text(name) })
+            .collect()
+    )
+}
+
+/// Get the tables and views of a schema (or of all the schemas if `schema` is `None`).
+pub async fn list_tables(driver: &mut AnyDriver, schema: Option<&str>) -> Result<Vec<TableInfo>> {
+    let info = driver.get_info().await?;
+    let (query, params) = get_tables_query(schema, &info);
+    let rows = fetch_catalog_rows(driver, &query, params).await?;
+    Ok(
+        rows
+            .into_iter()
+            .map(|row| {
+                let mut values = row.into_iter().map(to_text);
+                let schema = values.next().unwrap_or_default();
+                let name = values.next().unwrap_or_default();
+                let table_type = match values.next() {
+                    Some(table_type) if table_type.to_uppercase().contains("VIEW") => TableType::View,
+                    _ => TableType::Table,
+                };
+                TableInfo { schema, name, table_type }
+            })
+            .collect()
+    )
+}
+
+/// Get the columns of a table.
+pub async fn list_columns(driver: &mut AnyDriver, table: &TableReference) -> Result<Vec<ColumnInfo>> {
+    let info = driver.get_info().await?;
+    let (query, params) = get_columns_query(table.schema.as_deref(), &table.table, &info);
+    let rows = fetch_catalog_rows(driver, &query, params).await?;
+    if rows.is_empty() {
+        return Err(err_not_found!("The table '{}' does not exist.", table.table));
+    }
+    Ok(
+        rows
+            .into_iter()
+            .map(|row| {
+                let mut values = row.into_iter();
+                let name = values.next().map(to_text).unwrap_or_default();
+                let data_type = values.next().map(to_text).unwrap_or_default();
+                let nullable = match values.next() {
+                    Some(DriverValue::Bool(nullable)) => nullable,
+                    Some(DriverValue::Int16(nullable)) => nullable != 0,
+                    Some(DriverValue::Int32(nullable)) => nullable != 0,
+                    Some(DriverValue::Int64(nullable)) => nullable != 0,
+                    Some(DriverValue::Text(nullable)) => nullable.eq_ignore_ascii_case("YES"),
+                    _ => true,
+                };
+                ColumnInfo { name, data_type, nullable }
+            })
+            .collect()
+    )
+}
+
+/// Convert a value returned by a driver into a JSON value.
+pub fn to_json_value(value: DriverValue) -> Value {
+    match value {
+        DriverValue::Null => Value::Null,
+        DriverValue::Bool(value) => Value::from(value),
+        DriverValue::Int16(value) => Value::from(value),
+        DriverValue::Int32(value) => Value::from(value),
+        DriverValue::Int64(value) => Value::from(value),
+        DriverValue::Float32(value) => Value::from(value),
+        DriverValue::Float64(value) => Value::from(value),
+        DriverValue::Text(value) => Value::from(value),
+        DriverValue::ByteArray(value) => Value::from(hex::encode(value)),
+        DriverValue::Array(values) => Value::Array(values.into_iter().map(to_json_value).collect()),
+        DriverValue::UnsupportedType(_) => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use drivers::factory::DriverFactory;
+    use crate::models::connections::ConnectionMode;
+
+    #[test]
+    fn test_to_json_value() {
+        assert_eq!(to_json_value(DriverValue::Null), Value::Null);
+        assert_eq!(to_json_value(DriverValue::Int32(42)), Value::from(42));
+        assert_eq!(to_json_value(DriverValue::Text("Hello".to_string())), Value::from("Hello"));
+        assert_eq!(to_json_value(DriverValue::ByteArray(vec![0xca, 0xfe])), Value::from("cafe"));
+        assert_eq!(
+            to_json_value(DriverValue::Array(vec![DriverValue::Bool(true), DriverValue::Null])),
+            serde_json::json!([true, null])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_table_rows() {
+        // setup
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file = temp_dir.path().join("test.db");
+        let connection = Connection {
+            driver: "sqlite".to_string(),
+            mode: ConnectionMode::ConnectionString,
+            connection_string: format!("sqlite://{}?mode=rwc", file.to_str().unwrap()),
+            ..Default::default()
+        };
+        let connection_string = connection.to_connection_string().unwrap();
+        let mut driver = AnyDriver::new(DriverFactory::create("sqlite", connection_string).unwrap());
+        driver.connect().await.unwrap();
+        drivers::driver::execute_query(&mut driver, "CREATE TABLE users (id INTEGER, name TEXT)").await.unwrap();
+        drivers::driver::execute_query(&mut driver, "INSERT INTO users VALUES (1, 'marty'), (2, 'doc')").await.unwrap();
+        driver.close().await.unwrap();
+        let table = TableReference { schema: None, table: "users".to_string() };
+
+        // 1) peek: the number of rows is limited
+        let rows = peek_table(&connection, &table, 1).await.unwrap();
+        assert_eq!(rows, vec![vec![Value::from(1), Value::from("marty")]]);
+
+        // 2) second page sorted by name
+        let order_by = vec![OrderBy { column: "name".to_string(), descending: false }];
+        let rows = get_table_rows(&connection, &table, &order_by, 1, 10).await.unwrap();
+        assert_eq!(rows, vec![vec![Value::from(1), Value::from("marty")]]);
+
+        // 3) the table does not exist
+        let table = TableReference { schema: None, table: "unknown".to_string() };
+        assert!(peek_table(&connection, &table, 1).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_catalog() {
+        // setup
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file = temp_dir.path().join("test.db");
+        let connection = || Connection {
+            driver: "sqlite".to_string(),
+            mode: ConnectionMode::ConnectionString,
+            connection_string: format!("sqlite://{}?mode=rwc", file.to_str().unwrap()),
+            datasource: "main".to_string(),
+            ..Default::default()
+        };
+        assert!(connect_datasource(connection(), "unknown").await.is_err());
+        let mut driver = connect_datasource(connection(), "main").await.unwrap();
+        let query = "CREATE TABLE users (id INTEGER, name TEXT NOT NULL)";
+        drivers::driver::execute_query(&mut driver, query).await.unwrap();
+        drivers::driver::execute_query(&mut driver, "CREATE VIEW names AS SELECT name FROM users").await.unwrap();
+
+        // 1) schemas
+        let schemas = list_schemas(&mut driver).await.unwrap();
+        assert!(schemas.contains(&SchemaInfo { name: "main".to_string() }));
+
+        // 2) tables & views
+        assert_eq!(list_tables(&mut driver, None).await.unwrap(), vec![
+            TableInfo { schema: "main".to_string(), name: "names".to_string(), table_type: TableType::View },
+            TableInfo { schema: "main".to_string(), name: "users".to_string(), table_type: TableType::Table }
+        ]);
+
+        // 3) columns
+        let table = TableReference { schema: None, table: "users".to_string() };
+        assert_eq!(list_columns(&mut driver, &table).await.unwrap(), vec![
+            ColumnInfo { name: "id".to_string(), data_type: "INTEGER".to_string(), nullable: true },
+            ColumnInfo { name: "name".to_string(), data_type: "TEXT".to_string(), nullable: false }
+        ]);
+        let table = TableReference { schema: None, table: "unknown".to_string() };
+        assert!(list_columns(&mut driver, &table).await.is_err());
+        driver.close().await.unwrap();
+    }
+}
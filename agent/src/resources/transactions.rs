@@ -0,0 +1,118 @@
+use anyhow::Result;
+use drivers::driver::{ execute_query, DriverConnection, DriverExecutor };
+use drivers::factory::AnyDriver;
+use drivers::sql::is_read_only_statement;
+use drivers::value::DriverValue;
+use futures::TryStreamExt;
+use crate::models::connections::{ Connection, ConnectionStage };
+use crate::models::transactions::ExecuteStatementResult;
+use crate::resources::tables::to_json_value;
+use crate::settings;
+use crate::utils::user_error::UserError;
+
+/// Check if a statement can be executed on a connection.
+///
+/// When the setting `production_write_confirmation` is enabled, the statements that are not read-only (see
+/// `is_read_only_statement`) must be confirmed by the user to be executed on a connection labeled as production.
+pub fn check_statement_confirmation(connection: &Connection, statement: &str, confirmed: bool) -> Result<()> {
+    let is_production = connection.labels
+        .as_ref()
+        .is_some_and(|labels| labels.stage == Some(ConnectionStage::Production));
+    let requires_confirmation = is_production && settings::get_production_write_confirmation();
+    if requires_confirmation && !confirmed && !is_read_only_statement(statement) {
+        return Err(
+            UserError::ConfirmationRequired(
+                format!("The statement must be confirmed to run on the production connection '{}'.", connection.id)
+            ).into()
+        );
+    }
+    Ok(())
+}
+
+/// Execute a statement using the driver pinned to a transaction.
+///
+/// At most `limit` rows are returned, the remaining rows are not read. The stream is only read to its end when the
+/// statement returns no rows, so the number of affected rows can be reported.
+pub async fn execute_statement(driver: &mut AnyDriver, statement: &str, limit: u64) -> Result<ExecuteStatementResult> {
+    let mut stream = driver.query(statement).await?;
+    let mut rows = Vec::new();
+    while let Some(value) = stream.try_next().await? {
+        if (rows.len() as u64) >= limit {
+            // The remaining rows are not read, the number of affected rows is only needed when there are no rows.
+            break;
+        }
+        match value {
+            DriverValue::Array(values) => rows.push(values.into_iter().map(to_json_value).collect()),
+            value => rows.push(vec![to_json_value(value)]),
+        }
+    }
+    Ok(ExecuteStatementResult { rows, affected_rows: stream.affected_rows() })
+}
+
+/// End a transaction with the given statement (COMMIT or ROLLBACK) and close the driver pinned to it.
+///
+/// The driver is closed whether the statement succeeds or not.
+pub async fn end_transaction(driver: &mut AnyDriver, statement: &str) -> Result<()> {
+    let result = execute_query(driver, statement).await;
+    let _ = driver.close().await;
+    result.map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use drivers::factory::DriverFactory;
+    use crate::models::connections::ConnectionLabels;
+    use crate::utils::tests::settings;
+
+    #[tokio::test]
+    async fn test_execute_statement() {
+        // setup
+        let mut driver = AnyDriver::new(DriverFactory::create("sqlite", "sqlite::memory:".to_string()).unwrap());
+        driver.connect().await.unwrap();
+        execute_query(&mut driver, "BEGIN").await.unwrap();
+
+        // 1) statements without rows
+        let result = execute_statement(&mut driver, "CREATE TABLE t (id INTEGER)", 10).await.unwrap();
+        assert!(result.rows.is_empty());
+        let result = execute_statement(&mut driver, "INSERT INTO t VALUES (1), (2), (3)", 10).await.unwrap();
+        assert_eq!(result.affected_rows, Some(3));
+
+        // 2) the rows are limited
+        let result = execute_statement(&mut driver, "SELECT id FROM t ORDER BY id", 2).await.unwrap();
+        assert_eq!(result.rows, vec![vec![serde_json::json!(1)], vec![serde_json::json!(2)]]);
+
+        // 3) end the transaction
+        assert!(end_transaction(&mut driver, "ROLLBACK").await.is_ok());
+    }
+
+    #[test]
+    fn test_check_statement_confirmation() {
+        // setup
+        let mut connection = Connection {
+            labels: Some(ConnectionLabels { stage: Some(ConnectionStage::Production), ..Default::default() }),
+            ..Connection::new("Production".to_string())
+        };
+        let is_confirmation_required = |result: Result<()>| {
+            matches!(result.unwrap_err().downcast_ref::<UserError>(), Some(UserError::ConfirmationRequired(_)))
+        };
+
+        // 1) write statements on a production connection are rejected unless confirmed
+        assert!(is_confirmation_required(check_statement_confirmation(&connection, "DELETE FROM t", false)));
+        assert!(check_statement_confirmation(&connection, "DELETE FROM t", true).is_ok());
+
+        // 2) read-only statements do not require a confirmation
+        assert!(check_statement_confirmation(&connection, "SELECT * FROM t", false).is_ok());
+
+        // 3) the confirmation can be disabled
+        settings::set_production_write_confirmation(false);
+        assert!(check_statement_confirmation(&connection, "DELETE FROM t", false).is_ok());
+        settings::set_production_write_confirmation(true);
+
+        // 4) other stages do not require a confirmation
+        connection.labels = Some(ConnectionLabels { stage: Some(ConnectionStage::Development), ..Default::default() });
+        assert!(check_statement_confirmation(&connection, "DELETE FROM t", false).is_ok());
+        connection.labels = None;
+        assert!(check_statement_confirmation(&connection, "DELETE FROM t", false).is_ok());
+    }
+}
@@ -1,21 +1,31 @@
-use crate::models::users::{ User, UserSettings };
+use crate::models::users::{ OfflineSnapshot, User, UserSettings };
+use crate::resources::access_tokens;
 use crate::resources::workspaces::create_workspace;
 use crate::{ err_conflict, err_not_found, settings };
 use crate::utils::constants::{
+    ACCESS_TOKENS_FILENAME,
     DEFAULT_WORKSPACE_NAME,
     USER_CATALOG_DIRNAME,
     USER_COLLECTIONS_DIRNAME,
     USER_DATA_DIRNAME,
     USER_FILENAME,
 };
-use crate::utils::validators::{ join_catalog_path, sanitize_catalog_path_component, CatalogPath, Username };
+use crate::utils::json_store::write_json_file;
+use crate::utils::time::now;
+use crate::utils::validators::{
+    join_catalog_path,
+    sanitize_catalog_path_component,
+    sanitize_username,
+    CatalogPath,
+    Username,
+};
 use anyhow::{ anyhow, Context, Result };
-use std::path::PathBuf;
+use std::path::{ Path, PathBuf };
 use crate::resources::catalog::{ self };
 use crate::resources::catalog::CatalogSection;
 use crate::resources::Resource;
 
-use super::catalog::CatalogEntry;
+use crate::models::collections::CatalogEntry;
 
 /// Create a new user.
 ///
@@ -27,6 +37,7 @@ use super::catalog::CatalogEntry;
 ///     ├── catalog
 ///     │   ├── workspaces
 ///     │   ├── favorites
+///     │   ├── queries
 ///     │   └── environments
 ///     ├── collections
 ///     └── data
@@ -57,7 +68,7 @@ pub fn create_user(username: &Username) -> Result<User> {
 
     // Save the user.json to the filesystem.
     let user_file = user_dir.join(USER_FILENAME);
-    std::fs::write(user_file.as_path(), serde_json::to_string_pretty(&user)?)?;
+    write_json_file(&user_file, &user)?;
 
     // Create all additional directories for the user.
     //
@@ -67,6 +78,7 @@ pub fn create_user(username: &Username) -> Result<User> {
     //     │   ├── connections
     //     │   ├── environments
     //     │   ├── favorites
+    //     │   ├── queries
     //     │   └── workspaces
     //     ├── collections
     //     └── data
@@ -96,6 +108,171 @@ pub fn delete_user(username: &Username) -> Result<()> {
     std::fs
         ::remove_dir_all(user_dir.as_path())
         .with_context(|| { format!("Unable to delete the user directory: {}", user_dir.to_str().unwrap()) })?;
+
+    // The access tokens of the user must not be accepted anymore.
+    access_tokens::forget_access_tokens(username)?;
+    Ok(())
+}
+
+/// Read the usernames from a CSV file (see `user-add --from-csv`).
+///
+/// The first line is the header and must have a `username` column, the other columns are ignored. Empty lines are
+/// skipped. All the usernames are validated before returning so no user is created if the file is not valid.
+pub fn read_usernames_from_csv(content: &str) -> Result<Vec<Username>> {
+    let mut lines = content.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+    let Some((_, header)) = lines.next() else {
+        return Err(anyhow!("The file is empty."));
+    };
+    let Some(column) = split_csv_line(header).iter().position(|name| name.eq_ignore_ascii_case("username")) else {
+        return Err(anyhow!("The header must have a 'username' column."));
+    };
+    let mut usernames: Vec<Username> = Vec::new();
+    for (index, line) in lines {
+        let value = split_csv_line(line).into_iter().nth(column).unwrap_or_default();
+        let username = sanitize_username(&value).with_context(|| format!("line {}: invalid username", index + 1))?;
+        if usernames.iter().any(|other| other.as_str() == username.as_str()) {
+            return Err(anyhow!("line {}: duplicate username '{}'.", index + 1, username));
+        }
+        usernames.push(username);
+    }
+    Ok(usernames)
+}
+
+/// Split a line of a CSV file into its fields (trimmed).
+///
+/// A field may be quoted (e.g. `"username"` or `"Doe, John"`), in which case a quote is escaped by doubling it.
+/// Quoted fields spanning several lines are not supported.
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if quoted => {
+                quoted = false;
+            }
+            '"' if field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+            }
+            ',' if !quoted => {
+                fields.push(field.trim().to_string());
+                field.clear();
+            }
+            c => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+/// Save all the data of a user (settings, catalog, collections & data) into an archive (.tar.gz).
+///
+/// The archive can be restored later using `restore_user`, possibly under another username.
+pub fn archive_user(username: &Username, archive: &Path) -> Result<()> {
+    let user_dir = settings::get_user_dir(username.as_str());
+    if !user_dir.exists() {
+        return Err(anyhow!("The user {} does not exist.", &username));
+    }
+    let file = std::fs::File
+        ::create(archive)
+        .with_context(|| { format!("Unable to create the archive: {}", archive.display()) })?;
+    let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(file, flate2::Compression::default()));
+    builder
+        .append_dir_all(".", &user_dir)
+        .with_context(|| { format!("Unable to archive the user directory: {}", user_dir.display()) })?;
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+/// Create a user from an archive created by `archive_user`.
+///
+/// The restored user is a new user: the username of the archived user is replaced by the given one and a new user id
+/// is generated. The access tokens of the archived user are not restored, and the SQLite databases stored in the data
+/// directory of the archived user (e.g. the sample database) are relocated to the data directory of the new user.
+pub fn restore_user(username: &Username, archive: &Path) -> Result<User> {
+    let user_dir = settings::get_user_dir(username.as_str());
+    if user_dir.exists() {
+        return Err(anyhow!("The user already exists."));
+    }
+    let file = std::fs::File
+        ::open(archive)
+        .with_context(|| { format!("Unable to open the archive: {}", archive.display()) })?;
+    std::fs
+        ::create_dir_all(user_dir.as_path())
+        .with_context(|| { format!("Unable to create the user directory: {}", user_dir.display()) })?;
+    let result = inner_restore(username, &user_dir, file);
+    if result.is_err() {
+        // Do not leave a partially restored user behind.
+        let _ = std::fs::remove_dir_all(user_dir.as_path());
+    }
+    return result;
+
+    fn inner_restore(username: &Username, user_dir: &Path, file: std::fs::File) -> Result<User> {
+        // `unpack` ignores the entries that would be extracted outside of the user directory.
+        tar::Archive
+            ::new(flate2::read::GzDecoder::new(file))
+            .unpack(user_dir)
+            .context("Unable to extract the archive.")?;
+        let user_file = user_dir.join(USER_FILENAME);
+        let mut user: User = serde_json
+            ::from_str(&std::fs::read_to_string(user_file.as_path()).context("The archive is not a user archive.")?)
+            .context("Unable to parse the user file.")?;
+        let archived_username = std::mem::replace(&mut user.username, username.to_string());
+        user.user_id = uuid::Uuid::new_v4().to_string();
+        write_json_file(&user_file, &user)?;
+
+        // The access tokens are bound to the archived user, they must not become valid for the new one.
+        let access_tokens_file = user_dir.join(ACCESS_TOKENS_FILENAME);
+        if access_tokens_file.exists() {
+            std::fs::remove_file(&access_tokens_file).context("Unable to remove the access tokens.")?;
+        }
+        relocate_data_files(user_dir, &archived_username)?;
+        Ok(user)
+    }
+}
+
+/// Update the connections of a restored user using a file of the data directory of the archived user, so they are
+/// using the file of the data directory of the restored user instead.
+fn relocate_data_files(user_dir: &Path, archived_username: &str) -> Result<()> {
+    let collections_dir = user_dir.join(USER_COLLECTIONS_DIRNAME);
+    if !collections_dir.exists() {
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(&collections_dir)? {
+        let path = entry?.path();
+        let Ok(mut value) = serde_json::from_str::<serde_json::Value>(&std::fs::read_to_string(&path)?) else {
+            continue;
+        };
+        let Some(serde_json::Value::String(file)) = value.get_mut("file") else {
+            continue;
+        };
+
+        // e.g. "/home/marty/.squill/users/marty.mcfly/data/sample.db"
+        let archived_data_dir = Path::new(file.as_str())
+            .ancestors()
+            .find(|dir| {
+                dir.file_name().is_some_and(|name| name == USER_DATA_DIRNAME) &&
+                    dir
+                        .parent()
+                        .and_then(Path::file_name)
+                        .is_some_and(|name| name == archived_username)
+            })
+            .map(Path::to_path_buf);
+        let Some(archived_data_dir) = archived_data_dir else {
+            continue;
+        };
+        let relative_path = Path::new(file.as_str()).strip_prefix(&archived_data_dir)?.to_path_buf();
+        *file = user_dir.join(USER_DATA_DIRNAME).join(relative_path).to_string_lossy().to_string();
+        std::fs
+            ::write(&path, serde_json::to_string_pretty(&value)?)
+            .with_context(|| format!("Unable to update the connection file: {}", path.display()))?;
+    }
     Ok(())
 }
 
@@ -122,7 +299,7 @@ pub fn save_user_settings(username: &Username, user_settings: UserSettings) -> R
 
     // Save the user.json to the filesystem.
     let user_file = settings::get_user_dir(username.as_str()).join(USER_FILENAME);
-    std::fs::write(user_file.as_path(), serde_json::to_string_pretty(&user)?)?;
+    write_json_file(&user_file, &user)?;
     Ok(user.settings)
 }
 
@@ -146,6 +323,12 @@ pub fn create_user_resource<T>(username: &Username, parent_path: &CatalogPath, r
         return Err(err_conflict!("'{}' already exists.", catalog_path));
     }
 
+    // In strict mode, connection names must be unique across the whole catalog and not only within their folder.
+    let section = CatalogSection::from_path(parent_path);
+    if settings::get_catalog_strict_names() && section == CatalogSection::Connections {
+        catalog::check_unique_name(username, &section, name.as_str(), None)?;
+    }
+
     // Create the resource in the catalog.
     let catalog_entry = catalog::create_file(username, &catalog_path, resource.id())?;
 
@@ -159,6 +342,19 @@ pub fn create_user_resource<T>(username: &Username, parent_path: &CatalogPath, r
     Ok(catalog_entry)
 }
 
+/// Take a snapshot of the user data (user & catalog).
+pub fn get_offline_snapshot(username: &Username) -> Result<OfflineSnapshot> {
+    let mut snapshot = OfflineSnapshot {
+        created_at: now()?,
+        user: get_user(username)?,
+        catalog: Default::default(),
+    };
+    for section in CatalogSection::variants() {
+        snapshot.catalog.insert(section.as_str().to_string(), catalog::read_tree(username, &section.as_path())?);
+    }
+    Ok(snapshot)
+}
+
 pub fn get_collections_dir(username: &Username) -> PathBuf {
     settings::get_user_dir(username.as_str()).join(USER_COLLECTIONS_DIRNAME)
 }
@@ -168,10 +364,31 @@ mod tests {
     use super::*;
     use crate::{
         api::error::Error,
-        models::connections::Connection,
+        models::auth::{ AccessTokenRequest, AccessTokenScope },
+        models::connections::{ Connection, ConnectionMode },
+        resources::access_tokens::list_access_tokens,
         utils::{ constants::USERS_DIRNAME, tests::{ set_readonly, settings }, user_error::UserError },
     };
 
+    #[test]
+    fn test_read_usernames_from_csv() {
+        // 1) the username column can be anywhere
+        let usernames = read_usernames_from_csv("email, username\nmarty@hv.com, marty\n\ndoc@hv.com,doc\n").unwrap();
+        assert_eq!(usernames.iter().map(Username::as_str).collect::<Vec<_>>(), vec!["marty", "doc"]);
+
+        // 2) quoted fields
+        let usernames = read_usernames_from_csv("\"name\",\"username\"\n\"McFly, Marty\",\"marty\"\n").unwrap();
+        assert_eq!(usernames.iter().map(Username::as_str).collect::<Vec<_>>(), vec!["marty"]);
+        assert_eq!(split_csv_line(r#" "Brown, ""Doc"" " , doc"#), vec!["Brown, \"Doc\"", "doc"]);
+
+        // 3) invalid files
+        assert!(read_usernames_from_csv("").is_err());
+        assert!(read_usernames_from_csv("email\nmarty@hv.com").is_err());
+        assert!(read_usernames_from_csv("username\nmarty\nmarty").is_err());
+        let err = read_usernames_from_csv("username\nmarty\n\n../doc").unwrap_err();
+        assert!(err.to_string().starts_with("line 4:"));
+    }
+
     #[test]
     fn test_create_user_resource() {
         // setup
@@ -202,6 +419,82 @@ mod tests {
         let result = create_user_resource(&username, &parent_path, &connection);
         assert!(result.is_err());
         assert!(matches!(Error::from(result.unwrap_err()), Error::UserError(UserError::InvalidParameter(_))));
+
+        // 5. In strict mode, the same name cannot be used in another folder (expect to fail)
+        catalog::create_dir(&username, &CatalogPath::from("connections/Folder")).unwrap();
+        let folder_path = CatalogPath::from("connections/Folder");
+        let connection = Connection::new("test connection".to_string());
+        assert!(create_user_resource(&username, &folder_path, &connection).is_ok());
+        settings::set_catalog_strict_names(true);
+        let connection = Connection::new("Another Connection".to_string());
+        assert!(create_user_resource(&username, &folder_path, &connection).is_ok());
+        let connection = Connection::new("Another Connection".to_string());
+        let result = create_user_resource(&username, &parent_path, &connection);
+        assert!(matches!(Error::from(result.unwrap_err()), Error::UserError(UserError::Conflict(_))));
+        assert_eq!(catalog::find_name_conflicts(&username, &CatalogSection::Connections).unwrap().len(), 1);
+        settings::set_catalog_strict_names(false);
+    }
+
+    #[test]
+    fn test_archive_and_restore_user() {
+        // setup
+        let username: Username = "marty.mcfly".into();
+        let temp_dir = tempfile::tempdir().unwrap();
+        settings::set_base_dir(temp_dir.path().join("base").to_str().unwrap().to_string());
+        let user = create_user(&username).unwrap();
+        let data_file = settings::get_user_dir(username.as_str()).join(USER_DATA_DIRNAME).join("sales.db");
+        let connection = Connection {
+            driver: "sqlite".to_string(),
+            mode: ConnectionMode::File,
+            file: data_file.to_string_lossy().to_string(),
+            ..Connection::new("Test Connection".to_string())
+        };
+        create_user_resource(&username, &CatalogSection::Connections.as_path(), &connection).unwrap();
+        let new_token = access_tokens
+            ::create_access_token(&username, AccessTokenRequest {
+                name: "script".to_string(),
+                scope: AccessTokenScope::Read,
+                expires_in_days: None,
+            })
+            .unwrap();
+        let archive = temp_dir.path().join("marty.mcfly.tar.gz");
+
+        // 1) archive then delete the user
+        archive_user(&username, &archive).unwrap();
+        delete_user(&username).unwrap();
+        assert!(archive.exists());
+
+        // 2) restore the user under another username, as a new user without the access tokens
+        let new_username: Username = "george.mcfly".into();
+        let restored_user = restore_user(&new_username, &archive).unwrap();
+        assert_eq!(restored_user.username, "george.mcfly");
+        assert_ne!(restored_user.user_id, user.user_id);
+        assert_eq!(get_user(&new_username).unwrap().username, "george.mcfly");
+        assert!(catalog::exists(&new_username, &CatalogPath::from("connections/Test Connection")));
+        assert!(list_access_tokens(&new_username).unwrap().is_empty());
+        assert!(!access_tokens::is_valid_access_token(&new_token.token).unwrap());
+
+        // 3) the connections using the data directory are relocated
+        let content = std::fs::read_to_string(get_collections_dir(&new_username).join(&connection.id)).unwrap();
+        let restored_connection: Connection = serde_json::from_str(&content).unwrap();
+        assert_eq!(
+            restored_connection.file,
+            settings::get_user_dir(new_username.as_str()).join(USER_DATA_DIRNAME).join("sales.db").to_string_lossy()
+        );
+
+        // 4) restoring twice gives two different users
+        let other_username: Username = "dave.mcfly".into();
+        assert_ne!(restore_user(&other_username, &archive).unwrap().user_id, restored_user.user_id);
+
+        // 5) the user already exists (expect to fail)
+        assert!(restore_user(&new_username, &archive).is_err());
+
+        // 6) not an archive (expect to fail and leave nothing behind)
+        let username: Username = "biff.tannen".into();
+        let not_an_archive = settings::get_user_dir(new_username.as_str()).join(USER_FILENAME);
+        assert!(restore_user(&username, &temp_dir.path().join("missing.tar.gz")).is_err());
+        assert!(restore_user(&username, &not_an_archive).is_err());
+        assert!(!settings::get_user_dir(username.as_str()).exists());
     }
 
     #[test]
@@ -260,6 +553,31 @@ mod tests {
         std::fs::remove_dir_all(temp_dir).unwrap();
     }
 
+    #[test]
+    fn test_get_offline_snapshot() {
+        // setup
+        let username: Username = "test_user".into();
+        let temp_dir = tempfile::tempdir().unwrap();
+        settings::set_base_dir(temp_dir.path().to_str().unwrap().to_string());
+        create_user(&username).unwrap();
+        catalog::create_dir(&username, &CatalogPath::from("connections/Folder")).unwrap();
+        let connection = Connection::new("Test Connection".to_string());
+        create_user_resource(&username, &CatalogPath::from("connections/Folder"), &connection).unwrap();
+
+        // 1) the snapshot includes the whole catalog
+        let snapshot = get_offline_snapshot(&username).unwrap();
+        assert_eq!(snapshot.user.username, "test_user");
+        assert_eq!(snapshot.catalog.len(), CatalogSection::variants().len());
+        let connections = snapshot.catalog.get("connections").unwrap();
+        assert_eq!(connections.len(), 1);
+        assert_eq!(connections[0].entry.name, "Folder");
+        assert_eq!(connections[0].children[0].entry.name, "Test Connection");
+        assert_eq!(snapshot.catalog.get("workspaces").unwrap()[0].entry.name, DEFAULT_WORKSPACE_NAME);
+
+        // 2) unknown user
+        assert!(get_offline_snapshot(&"unknown_user".into()).is_err());
+    }
+
     #[test]
     fn test_get_user() {
         // setup
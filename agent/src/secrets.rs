@@ -0,0 +1,136 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use anyhow::{ anyhow, Context, Result };
+use lazy_static::lazy_static;
+use openssl::symm::{ decrypt_aead, encrypt_aead, Cipher };
+use rand::Rng;
+use crate::settings;
+use crate::utils::constants::{ ENCRYPTED_SECRET_PREFIX, MASTER_KEY_FILENAME };
+
+/// The size of the master key (AES-256).
+const KEY_SIZE: usize = 32;
+
+/// The size of the nonce used for each encryption (recommended size for GCM).
+const NONCE_SIZE: usize = 12;
+
+/// The size of the authentication tag appended to the encrypted secrets.
+const TAG_SIZE: usize = 16;
+
+lazy_static! {
+    // Serialize the creation of the master key (read or create).
+    static ref MASTER_KEY_LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// Get the file used to store the master key of the agent.
+fn get_master_key_file() -> PathBuf {
+    PathBuf::from(settings::get_base_dir()).join(MASTER_KEY_FILENAME)
+}
+
+/// Get the master key used to encrypt the secrets, creating it if it does not exist yet.
+///
+/// The key is stored in the base directory, only readable by the owner, so the secrets cannot be read by copying the
+/// collections of a user to another agent.
+fn get_master_key() -> Result<[u8; KEY_SIZE]> {
+    let _lock = MASTER_KEY_LOCK.lock().unwrap();
+    let file = get_master_key_file();
+    if !file.exists() {
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            // The master key must only be readable by the owner, from the creation of the file.
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options
+            .open(&file)
+            .and_then(|mut f| f.write_all(hex::encode(rand::thread_rng().gen::<[u8; KEY_SIZE]>()).as_bytes()))
+            .with_context(|| format!("Unable to write the master key file: {}", file.display()))?;
+    }
+    let content = std::fs
+        ::read_to_string(&file)
+        .with_context(|| format!("Unable to read the master key file: {}", file.display()))?;
+    let key = hex::decode(content.trim()).ok().and_then(|key| <[u8; KEY_SIZE]>::try_from(key).ok());
+    key.ok_or_else(|| anyhow!("Invalid master key file: {}", file.display()))
+}
+
+/// Check if a value has been encrypted by `encrypt`.
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_SECRET_PREFIX)
+}
+
+/// Encrypt a secret with the master key (AES-256-GCM).
+///
+/// The result is `ENCRYPTED_SECRET_PREFIX` followed by the nonce, the encrypted secret and the authentication tag
+/// encoded in hexadecimal. Empty and already encrypted values are returned as is.
+pub fn encrypt(value: &str) -> Result<String> {
+    if value.is_empty() || is_encrypted(value) {
+        return Ok(value.to_string());
+    }
+    let key = get_master_key()?;
+    let nonce = rand::thread_rng().gen::<[u8; NONCE_SIZE]>();
+    let mut tag = [0u8; TAG_SIZE];
+    let encrypted = encrypt_aead(Cipher::aes_256_gcm(), &key, Some(&nonce), &[], value.as_bytes(), &mut tag)?;
+    Ok(format!("{}{}", ENCRYPTED_SECRET_PREFIX, hex::encode([&nonce[..], &encrypted[..], &tag[..]].concat())))
+}
+
+/// Decrypt a secret encrypted by `encrypt`.
+///
+/// Values that are not encrypted (e.g. stored before the encryption of the secrets) are returned as is.
+pub fn decrypt(value: &str) -> Result<String> {
+    let Some(encoded) = value.strip_prefix(ENCRYPTED_SECRET_PREFIX) else {
+        return Ok(value.to_string());
+    };
+    let data = hex::decode(encoded).map_err(|_| anyhow!("Invalid encrypted secret."))?;
+    if data.len() < NONCE_SIZE + TAG_SIZE {
+        return Err(anyhow!("Invalid encrypted secret."));
+    }
+    let (nonce, data) = data.split_at(NONCE_SIZE);
+    let (encrypted, tag) = data.split_at(data.len() - TAG_SIZE);
+    let key = get_master_key()?;
+    let decrypted = decrypt_aead(Cipher::aes_256_gcm(), &key, Some(nonce), &[], encrypted, tag).map_err(|_|
+        anyhow!("Unable to decrypt a secret, the master key may have changed.")
+    )?;
+    Ok(String::from_utf8(decrypted)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::tests::settings;
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        // setup
+        let temp_dir = tempfile::tempdir().unwrap();
+        settings::set_base_dir(temp_dir.path().to_str().unwrap().to_string());
+
+        // 1) round trip
+        let encrypted = encrypt("p@ssw0rd").unwrap();
+        assert!(is_encrypted(&encrypted));
+        assert!(!encrypted.contains("p@ssw0rd"));
+        assert_ne!(encrypt("p@ssw0rd").unwrap(), encrypted);
+        assert_eq!(decrypt(&encrypted).unwrap(), "p@ssw0rd");
+        assert!(get_master_key_file().exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(get_master_key_file()).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        // 2) empty, plain and already encrypted values
+        assert_eq!(encrypt("").unwrap(), "");
+        assert_eq!(encrypt(&encrypted).unwrap(), encrypted);
+        assert_eq!(decrypt("plain").unwrap(), "plain");
+
+        // 3) tampered secret or different master key
+        let mut tampered = encrypted.clone();
+        tampered.replace_range(tampered.len() - 2.., if encrypted.ends_with("00") { "01" } else { "00" });
+        assert!(decrypt(&tampered).is_err());
+        assert!(decrypt(&format!("{}zz", ENCRYPTED_SECRET_PREFIX)).is_err());
+        std::fs::remove_file(get_master_key_file()).unwrap();
+        assert!(decrypt(&encrypted).is_err());
+    }
+}
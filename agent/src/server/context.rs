@@ -34,6 +34,11 @@ impl RequestContext {
             None => USERNAME_ANONYMOUS,
         }
     }
+
+    /// Check if the request has been authenticated with a personal access token.
+    pub fn is_access_token(&self) -> bool {
+        self.user_session.as_ref().is_some_and(|user_session| user_session.is_access_token())
+    }
 }
 
 /// The request context extractor.
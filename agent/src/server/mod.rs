@@ -1,3 +1,4 @@
 pub mod web;
 pub mod state;
 pub mod context;
+pub mod tls;
@@ -1,10 +1,14 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
 use std::sync::{ Mutex, Arc };
 use std::num::NonZeroUsize;
-use std::time::SystemTime;
+use std::time::{ Duration, SystemTime };
+use drivers::factory::AnyDriver;
 use lru::LruCache;
 
-use crate::models::auth::SecurityToken;
+use crate::models::auth::{ SecurityToken, TokenType };
 use crate::settings;
+use crate::utils::constants::AUTH_FAILURES_CACHE_SIZE;
 use crate::utils::validators::Username;
 
 /// The user session cache.
@@ -17,6 +21,42 @@ type UserSessionCache = Arc<Mutex<LruCache<String, Arc<UserSession>>>>;
 
 type RefreshTokenCache = Arc<Mutex<LruCache<String, Arc<RefreshToken>>>>;
 
+/// The authentication failures cache.
+///
+/// The key identifies the client, either by its address (e.g. "ip:192.168.1.10") or by the username it tried to logon
+/// with along with its address (e.g. "user:local@192.168.1.10"), see `get_auth_failure_keys`.
+type AuthFailureCache = Arc<Mutex<LruCache<String, AuthFailures>>>;
+
+/// The open transactions.
+///
+/// The key identifies the user and the connection (see `get_transaction_key`). Each transaction is pinned to its own
+/// connection to the datasource, used by all the requests of the user on this connection until the transaction is
+/// committed or rolled back, or until it expires (see `remove_idle_transactions`).
+type TransactionCache = Arc<Mutex<HashMap<String, Transaction>>>;
+
+/// The connection to the datasource pinned to a transaction.
+pub type TransactionDriver = Arc<tokio::sync::Mutex<AnyDriver>>;
+
+/// A transaction in progress.
+struct Transaction {
+    driver: TransactionDriver,
+
+    /// The last time the transaction has been used by a request.
+    last_used: SystemTime,
+}
+
+/// The consecutive authentication failures of a client.
+struct AuthFailures {
+    /// The number of consecutive failures.
+    count: u32,
+
+    /// The time of the last failure (seconds since the UNIX epoch).
+    last_failure: u32,
+
+    /// The time until which the client is locked out (seconds since the UNIX epoch).
+    locked_until: u32,
+}
+
 /// A user session stored in the cache.
 ///
 /// The user session is the server side of the security token. It contains a reference to the whole security token
@@ -26,9 +66,35 @@ pub struct UserSession {
     username: Username,
     expires_at: u32,
     security_token: Arc<SecurityToken>,
+
+    /// The session has been created for a request authenticated with a personal access token.
+    access_token: bool,
 }
 
 impl UserSession {
+    /// Create a user session for a request authenticated with a personal access token.
+    ///
+    /// Such a session is not stored in the cache and has no refresh token, it only lasts for the request.
+    pub fn from_access_token(username: Username, user_id: &str) -> Self {
+        Self {
+            username,
+            expires_at: 0,
+            security_token: Arc::new(SecurityToken {
+                token: String::new(),
+                token_type: TokenType::Bearer,
+                refresh_token: String::new(),
+                expires_in: 0,
+                user_id: user_id.to_string(),
+            }),
+            access_token: true,
+        }
+    }
+
+    /// Check if the session has been created for a request authenticated with a personal access token.
+    pub fn is_access_token(&self) -> bool {
+        self.access_token
+    }
+
     /// Get the username.
     pub fn get_username(&self) -> &str {
         self.username.as_str()
@@ -62,6 +128,8 @@ pub struct RefreshToken {
 pub struct ServerState {
     user_sessions: UserSessionCache,
     refresh_tokens: RefreshTokenCache,
+    auth_failures: AuthFailureCache,
+    transactions: TransactionCache,
 }
 
 impl ServerState {
@@ -74,6 +142,10 @@ impl ServerState {
             user_sessions: Arc::new(
                 Mutex::new(LruCache::new(NonZeroUsize::new(settings::get_max_user_sessions()).unwrap()))
             ),
+            auth_failures: Arc::new(
+                Mutex::new(LruCache::new(NonZeroUsize::new(AUTH_FAILURES_CACHE_SIZE).unwrap()))
+            ),
+            transactions: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
@@ -92,6 +164,7 @@ impl ServerState {
             username: username.clone(),
             security_token: security_token.clone(),
             expires_at: Self::get_expiration_time(security_token.expires_in),
+            access_token: false,
         });
 
         // Create a refresh token for the cache based on the security token.
@@ -237,6 +310,150 @@ impl ServerState {
         }
     }
 
+    /// Get the keys used to track the authentication failures of a client (see `add_auth_failure`).
+    ///
+    /// The failures are tracked by client address, and by username along with the client address so a remote host
+    /// cannot lock a user out from everywhere. The clients connected through a loopback address (such as the desktop
+    /// application) are never tracked, so they cannot be locked out by another client.
+    pub fn get_auth_failure_keys(addr: Option<IpAddr>, username: Option<&Username>) -> Vec<String> {
+        match addr {
+            Some(addr) if !addr.is_loopback() => {
+                let mut keys = vec![format!("ip:{}", addr)];
+                if let Some(username) = username {
+                    keys.push(format!("user:{}@{}", username, addr));
+                }
+                keys
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Get the number of seconds a client has still to wait before being allowed to authenticate again.
+    ///
+    /// Returns `None` if the client is not locked out.
+    pub fn get_lockout(&self, key: &str) -> Option<u64> {
+        let Ok(mut auth_failures) = self.auth_failures.lock() else {
+            panic!("Unable to recover from a poisoned authentication failures mutex");
+        };
+        let now = Self::get_expiration_time(0);
+        match auth_failures.get(key) {
+            Some(failures) if failures.locked_until > now => Some((failures.locked_until - now) as u64),
+            _ => None,
+        }
+    }
+
+    /// Record an authentication failure for a client.
+    ///
+    /// Once the client has reached `auth_max_failures` consecutive failures, it is locked out for
+    /// `auth_lockout_duration`, and the duration is doubled for each new failure. The failures are forgotten if the
+    /// client did not fail for `auth_lockout_duration` after its last failure or lockout.
+    ///
+    /// Returns `true` if the client is now locked out.
+    pub fn add_auth_failure(&self, key: &str) -> bool {
+        let max_failures = settings::get_auth_max_failures();
+        if max_failures == 0 {
+            return false;
+        }
+        let Ok(mut auth_failures) = self.auth_failures.lock() else {
+            panic!("Unable to recover from a poisoned authentication failures mutex");
+        };
+        let now = Self::get_expiration_time(0);
+        let lockout_duration = settings::get_auth_lockout_duration().as_secs() as u32;
+        let failures = auth_failures.get_or_insert_mut(key.to_string(), || AuthFailures {
+            count: 0,
+            last_failure: now,
+            locked_until: 0,
+        });
+        if now.saturating_sub(failures.last_failure.max(failures.locked_until)) > lockout_duration {
+            failures.count = 0;
+        }
+        failures.count += 1;
+        failures.last_failure = now;
+        if failures.count >= max_failures {
+            let exponent = (failures.count - max_failures).min(16);
+            failures.locked_until = now.saturating_add(lockout_duration.saturating_mul(1 << exponent));
+            return true;
+        }
+        false
+    }
+
+    /// Forget the authentication failures of a client (after a successful authentication).
+    pub fn clear_auth_failures(&self, key: &str) {
+        let Ok(mut auth_failures) = self.auth_failures.lock() else {
+            panic!("Unable to recover from a poisoned authentication failures mutex");
+        };
+        auth_failures.pop(key);
+    }
+
+    /// Get the key of the transaction of a user on a connection.
+    pub fn get_transaction_key(username: &Username, connection_id: &str) -> String {
+        format!("{}/{}", username, connection_id)
+    }
+
+    /// Pin a connection to the datasource to a transaction.
+    ///
+    /// Returns `false` if there is already a transaction for the given key (the driver is dropped).
+    pub fn add_transaction(&self, key: &str, driver: AnyDriver) -> bool {
+        let Ok(mut transactions) = self.transactions.lock() else {
+            panic!("Unable to recover from a poisoned transactions mutex");
+        };
+        if transactions.contains_key(key) {
+            return false;
+        }
+        transactions.insert(key.to_string(), Transaction {
+            driver: Arc::new(tokio::sync::Mutex::new(driver)),
+            last_used: SystemTime::now(),
+        });
+        true
+    }
+
+    /// Get the connection to the datasource pinned to a transaction (if any).
+    ///
+    /// Getting the transaction keeps it from expiring.
+    pub fn get_transaction(&self, key: &str) -> Option<TransactionDriver> {
+        let Ok(mut transactions) = self.transactions.lock() else {
+            panic!("Unable to recover from a poisoned transactions mutex");
+        };
+        transactions.get_mut(key).map(|transaction| {
+            transaction.last_used = SystemTime::now();
+            transaction.driver.clone()
+        })
+    }
+
+    /// Remove a transaction, returning the connection to the datasource that was pinned to it.
+    pub fn remove_transaction(&self, key: &str) -> Option<TransactionDriver> {
+        let Ok(mut transactions) = self.transactions.lock() else {
+            panic!("Unable to recover from a poisoned transactions mutex");
+        };
+        transactions.remove(key).map(|transaction| transaction.driver)
+    }
+
+    /// Remove the transactions that have not been used for the given duration.
+    ///
+    /// A transaction whose connection to the datasource is locked is running a statement, it is considered as used
+    /// (so a statement running longer than the idle timeout does not expire its transaction).
+    ///
+    /// Returns the keys of the expired transactions along with the connections to the datasource that were pinned to
+    /// them, so they can be rolled back and closed.
+    pub fn remove_idle_transactions(&self, idle_timeout: Duration) -> Vec<(String, TransactionDriver)> {
+        let Ok(mut transactions) = self.transactions.lock() else {
+            panic!("Unable to recover from a poisoned transactions mutex");
+        };
+        let now = SystemTime::now();
+        let mut expired: Vec<String> = Vec::new();
+        for (key, transaction) in transactions.iter_mut() {
+            if transaction.driver.try_lock().is_err() {
+                transaction.last_used = now;
+            } else if now.duration_since(transaction.last_used).unwrap_or_default() >= idle_timeout {
+                expired.push(key.clone());
+            }
+        }
+        expired
+            .into_iter()
+            .filter_map(|key| transactions.remove(&key).map(|transaction| (key, transaction.driver)))
+            .collect()
+    }
+
     /// Calculate the expiration time based on the current time and a duration in seconds.
     ///
     /// # Arguments
@@ -253,6 +470,86 @@ impl ServerState {
 mod tests {
     use super::*;
     use crate::utils::tests::settings;
+    use drivers::factory::DriverFactory;
+
+    #[test]
+    fn test_auth_failures() {
+        let state = ServerState::new();
+        settings::set_auth_max_failures(3);
+
+        // 1) the client is locked out after 3 consecutive failures
+        assert!(!state.add_auth_failure("ip:127.0.0.1"));
+        assert!(!state.add_auth_failure("ip:127.0.0.1"));
+        assert!(state.get_lockout("ip:127.0.0.1").is_none());
+        assert!(state.add_auth_failure("ip:127.0.0.1"));
+        let lockout = state.get_lockout("ip:127.0.0.1").unwrap();
+        assert!(lockout > 0 && lockout <= settings::get_auth_lockout_duration().as_secs());
+
+        // 2) the lockout duration is doubled for each new failure
+        assert!(state.add_auth_failure("ip:127.0.0.1"));
+        assert!(state.get_lockout("ip:127.0.0.1").unwrap() > settings::get_auth_lockout_duration().as_secs());
+
+        // 3) other clients are not affected
+        assert!(state.get_lockout("user:local").is_none());
+
+        // 4) a successful authentication clears the failures
+        state.clear_auth_failures("ip:127.0.0.1");
+        assert!(state.get_lockout("ip:127.0.0.1").is_none());
+
+        // 5) the lockout can be disabled
+        settings::set_auth_max_failures(0);
+        for _ in 0..10 {
+            assert!(!state.add_auth_failure("user:local"));
+        }
+        assert!(state.get_lockout("user:local").is_none());
+        settings::set_auth_max_failures(5);
+    }
+
+    #[test]
+    fn test_get_auth_failure_keys() {
+        let username: Username = "local".into();
+        assert!(ServerState::get_auth_failure_keys(None, Some(&username)).is_empty());
+        assert!(ServerState::get_auth_failure_keys(Some("127.0.0.1".parse().unwrap()), Some(&username)).is_empty());
+        assert!(ServerState::get_auth_failure_keys(Some("::1".parse().unwrap()), None).is_empty());
+        assert_eq!(ServerState::get_auth_failure_keys(Some("192.168.1.10".parse().unwrap()), None), vec![
+            "ip:192.168.1.10"
+        ]);
+        assert_eq!(ServerState::get_auth_failure_keys(Some("192.168.1.10".parse().unwrap()), Some(&username)), vec![
+            "ip:192.168.1.10",
+            "user:local@192.168.1.10"
+        ]);
+    }
+
+    #[test]
+    fn test_transactions() {
+        let state = ServerState::new();
+        let key = ServerState::get_transaction_key(&"local".into(), "connection_id");
+        let driver = || AnyDriver::new(DriverFactory::create("sqlite", "sqlite::memory:".to_string()).unwrap());
+
+        // 1) a single transaction per key
+        assert!(state.get_transaction(&key).is_none());
+        assert!(state.add_transaction(&key, driver()));
+        assert!(!state.add_transaction(&key, driver()));
+        assert!(state.get_transaction(&key).is_some());
+        assert!(state.get_transaction(&ServerState::get_transaction_key(&"other".into(), "connection_id")).is_none());
+
+        // 2) remove
+        assert!(state.remove_transaction(&key).is_some());
+        assert!(state.get_transaction(&key).is_none());
+        assert!(state.remove_transaction(&key).is_none());
+
+        // 3) idle transactions
+        assert!(state.add_transaction(&key, driver()));
+        assert!(state.remove_idle_transactions(Duration::from_secs(60)).is_empty());
+        let running = state.get_transaction(&key).unwrap();
+        let guard = running.try_lock().unwrap();
+        assert!(state.remove_idle_transactions(Duration::ZERO).is_empty());
+        drop(guard);
+        let expired = state.remove_idle_transactions(Duration::ZERO);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].0, key);
+        assert!(state.get_transaction(&key).is_none());
+    }
 
     #[test]
     fn test_add_user_session() {
@@ -0,0 +1,151 @@
+use std::net::IpAddr;
+use std::path::{ Path, PathBuf };
+use anyhow::{ anyhow, Context, Result };
+use common::tls::get_certificate_fingerprint;
+use openssl::asn1::Asn1Time;
+use openssl::bn::{ BigNum, MsbOption };
+use openssl::ec::{ EcGroup, EcKey };
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::{ PKey, Private };
+use openssl::x509::extension::{ BasicConstraints, SubjectAlternativeName };
+use openssl::x509::{ X509, X509NameBuilder };
+use tracing::info;
+use crate::settings;
+use crate::utils::constants::{ SELF_SIGNED_CERT_FILENAME, SELF_SIGNED_CERT_VALIDITY_DAYS, SELF_SIGNED_KEY_FILENAME };
+
+/// The TLS configuration used to serve the API over HTTPS.
+pub struct TlsConfig {
+    /// The certificate file (PEM).
+    pub cert_file: PathBuf,
+
+    /// The private key file (PEM).
+    pub key_file: PathBuf,
+
+    /// The SHA-256 fingerprint of the certificate (e.g. "AB:CD:...").
+    pub fingerprint: String,
+}
+
+/// Get the TLS configuration.
+///
+/// If the settings `tls_cert_file` and `tls_key_file` are set, they are used as is. Otherwise a self-signed certificate
+/// stored in the app directory is used, and generated if it does not exist yet. Setting only one of them is an error.
+pub fn get_tls_config() -> Result<TlsConfig> {
+    if settings::get_tls_cert_file().is_empty() != settings::get_tls_key_file().is_empty() {
+        return Err(anyhow!("The settings `tls_cert_file` and `tls_key_file` must be set together."));
+    }
+    let (cert_file, key_file) = if settings::get_tls_cert_file().is_empty() {
+        let app_dir = settings::get_app_dir();
+        let cert_file = app_dir.join(SELF_SIGNED_CERT_FILENAME);
+        let key_file = app_dir.join(SELF_SIGNED_KEY_FILENAME);
+        if !cert_file.exists() || !key_file.exists() {
+            create_self_signed_certificate(&cert_file, &key_file)?;
+        }
+        (cert_file, key_file)
+    } else {
+        (PathBuf::from(settings::get_tls_cert_file()), PathBuf::from(settings::get_tls_key_file()))
+    };
+
+    let cert = X509::from_pem(
+        &std::fs
+            ::read(&cert_file)
+            .with_context(|| format!("Unable to read the certificate file: {}", cert_file.display()))?
+    ).with_context(|| format!("Invalid certificate file: {}", cert_file.display()))?;
+
+    Ok(TlsConfig {
+        fingerprint: get_certificate_fingerprint(&cert)?,
+        cert_file,
+        key_file,
+    })
+}
+
+/// Create a self-signed certificate and save it along with its private key.
+fn create_self_signed_certificate(cert_file: &Path, key_file: &Path) -> Result<()> {
+    let (cert, key) = generate_self_signed_certificate().context("Unable to generate a self-signed certificate.")?;
+    std::fs
+        ::write(key_file, key.private_key_to_pem_pkcs8()?)
+        .with_context(|| format!("Unable to write the private key file: {}", key_file.display()))?;
+    #[cfg(unix)]
+    {
+        // The private key must only be readable by the owner.
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(key_file, std::fs::Permissions::from_mode(0o600))?;
+    }
+    std::fs
+        ::write(cert_file, cert.to_pem()?)
+        .with_context(|| format!("Unable to write the certificate file: {}", cert_file.display()))?;
+    info!("A self-signed certificate has been created: {}", cert_file.display());
+    Ok(())
+}
+
+/// Generate a self-signed certificate for `localhost` (and the listen address if it is not a wildcard address).
+fn generate_self_signed_certificate() -> Result<(X509, PKey<Private>)> {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+    let key = PKey::from_ec_key(EcKey::generate(&group)?)?;
+
+    let mut name = X509NameBuilder::new()?;
+    name.append_entry_by_nid(Nid::COMMONNAME, "localhost")?;
+    let name = name.build();
+
+    let mut serial = BigNum::new()?;
+    serial.rand(128, MsbOption::MAYBE_ZERO, false)?;
+
+    let mut builder = X509::builder()?;
+    builder.set_version(2)?;
+    builder.set_serial_number(&serial.to_asn1_integer()?)?;
+    builder.set_subject_name(&name)?;
+    builder.set_issuer_name(&name)?;
+    builder.set_pubkey(&key)?;
+    builder.set_not_before(&Asn1Time::days_from_now(0)?)?;
+    builder.set_not_after(&Asn1Time::days_from_now(SELF_SIGNED_CERT_VALIDITY_DAYS)?)?;
+    builder.append_extension(BasicConstraints::new().critical().build()?)?;
+
+    let mut san = SubjectAlternativeName::new();
+    san.dns("localhost").ip("127.0.0.1").ip("::1");
+    let listen_address = settings::get_listen_address();
+    if let Ok(ip) = listen_address.parse::<IpAddr>() {
+        if !ip.is_unspecified() && !ip.is_loopback() {
+            san.ip(&listen_address);
+        }
+    }
+    let san = san.build(&builder.x509v3_context(None, None))?;
+    builder.append_extension(san)?;
+
+    builder.sign(&key, MessageDigest::sha256())?;
+    Ok((builder.build(), key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::tests::settings;
+
+    #[test]
+    fn test_get_tls_config() {
+        // setup
+        let app_dir = tempfile::tempdir().unwrap();
+        settings::set_app_dir(app_dir.path());
+
+        // 1) a self-signed certificate is created the first time
+        let tls_config = get_tls_config().unwrap();
+        assert!(app_dir.path().join(SELF_SIGNED_CERT_FILENAME).exists());
+        assert!(app_dir.path().join(SELF_SIGNED_KEY_FILENAME).exists());
+        assert_eq!(tls_config.fingerprint.split(':').count(), 32);
+
+        // 2) the same certificate is used the next time
+        assert_eq!(get_tls_config().unwrap().fingerprint, tls_config.fingerprint);
+
+        // 3) the certificate provided in the settings is used instead
+        settings::set_tls_cert_file(app_dir.path().join("missing.crt").to_str().unwrap().to_string());
+        settings::set_tls_key_file(app_dir.path().join("missing.key").to_str().unwrap().to_string());
+        assert!(get_tls_config().is_err());
+
+        // 4) only one of the certificate and the private key is provided
+        settings::set_tls_key_file(String::new());
+        assert_eq!(
+            get_tls_config().err().unwrap().to_string(),
+            "The settings `tls_cert_file` and `tls_key_file` must be set together."
+        );
+        settings::set_tls_cert_file(String::new());
+    }
+}
@@ -1,17 +1,23 @@
-use crate::models::auth::AuthenticationMethod;
+use crate::models::auth::{ AccessTokenScope, AuthenticationMethod };
+use crate::resources::{ self, access_tokens, users };
+use crate::utils::constants::{ ACCESS_TOKEN_PREFIX, CONDITIONAL_GET_MAX_BODY_SIZE };
 use crate::utils::validators::parse_authorization_header;
 use crate::{ settings, api };
 use crate::api::error::{ Error, ServerResult };
-use crate::server::state::ServerState;
+use crate::server::state::{ ServerState, UserSession };
 use crate::server::context::RequestContext;
-use common::constants::{ X_API_KEY_HEADER, X_REQUEST_ID_HEADER };
+use crate::server::tls::{ self, TlsConfig };
+use common::constants::{ AGENT_API_KEY_ENV, X_API_KEY_HEADER, X_REQUEST_ID_HEADER, X_REQUEST_TIMEOUT_HEADER };
+use drivers::redact::redact;
 use common::pid_file::{ delete_pid_file, get_agent_status, load_pid_file, save_pid_file, AgentStatus, PID_FILENAME };
+use std::net::SocketAddr;
 use std::str::FromStr;
-use std::time::{ SystemTime, UNIX_EPOCH };
-use axum::extract::State;
+use std::sync::Arc;
+use std::time::{ Duration, Instant, SystemTime, UNIX_EPOCH };
+use axum::extract::{ ConnectInfo, State };
 use axum::http::{ self, HeaderValue, Method };
 use axum::middleware::{ from_fn, from_fn_with_state, Next };
-use axum::{ Router, extract::Request, response::Response };
+use axum::{ Router, body::{ Body, HttpBody }, extract::Request, response::{ IntoResponse, Response } };
 use anyhow::{ Result, Context };
 use rand::Rng;
 use tokio::signal;
@@ -20,7 +26,7 @@ use tower_http::LatencyUnit;
 use tracing::{ debug, info, warn, Level };
 use tower_http::trace::{ self, TraceLayer };
 use tower_http::cors::{ CorsLayer, Any };
-use http::header::{ AUTHORIZATION, ACCEPT, CONTENT_TYPE };
+use http::header::{ AUTHORIZATION, ACCEPT, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED };
 
 pub struct Server {}
 
@@ -46,15 +52,21 @@ impl Server {
         // Server initialization
         let mut server = Server {};
         let listener = server.bind().await?;
+        let tls_config = if settings::get_tls_enabled() { Some(tls::get_tls_config()?) } else { None };
 
         // Save the file agent.pid
         let app_dir = settings::get_app_dir();
-        save_pid_file(&app_dir, &listener.local_addr()?, &settings::get_api_key()).or(
+        let tls_fingerprint = tls_config.as_ref().map(|tls_config| tls_config.fingerprint.as_str());
+        // The API key is not written if it is the one paired with the desktop application that started the agent.
+        let api_key = settings::get_api_key();
+        let paired = std::env::var(AGENT_API_KEY_ENV).is_ok_and(|paired_api_key| paired_api_key == api_key);
+        let pid_file_api_key = if paired { None } else { Some(api_key.as_str()) };
+        save_pid_file(&app_dir, &listener.local_addr()?, pid_file_api_key, tls_fingerprint).or(
             Err(anyhow::anyhow!("Unable to save the pid file: {:?}", app_dir.join(PID_FILENAME)))
         )?;
 
         // Run the server
-        let result = server.run(listener).await;
+        let result = server.run(listener, tls_config).await;
 
         // delete the file agent.pid
         delete_pid_file(&app_dir).or(
@@ -81,7 +93,7 @@ impl Server {
         let routes = Router::new()
             .merge(api::auth::routes(state.clone()))
             .merge(api::agent::routes(state.clone()))
-            .layer(from_fn(check_api_key));
+            .layer(from_fn_with_state(state.clone(), check_api_key));
         // routes that require authentication
         let auth_routes = Router::new().merge(
             api::users
@@ -89,19 +101,26 @@ impl Server {
                 .merge(api::agent::authenticated_routes(state.clone()))
                 .merge(api::connections::authenticated_routes(state.clone()))
                 .layer(from_fn_with_state(state.clone(), check_authentication))
-                .layer(from_fn(check_api_key))
+                .layer(from_fn_with_state(state.clone(), check_api_key))
         );
 
         // all routes are nested under the /api/v1 path
-        Router::new().nest("/api/v1", routes.merge(auth_routes))
+        Router::new()
+            .nest("/api/v1", routes.merge(auth_routes))
+            .layer(from_fn(check_conditional_get))
+            .layer(from_fn(check_request_timeout))
+            .layer(from_fn(log_slow_request))
     }
 
     /// Run the server.
     ///
     /// This function will start the server and will not return until the server is stopped.
-    async fn run(&mut self, listener: TcpListener) -> Result<()> {
+    /// If a TLS configuration is given, the API is served over HTTPS.
+    async fn run(&mut self, listener: TcpListener, tls_config: Option<TlsConfig>) -> Result<()> {
         // create the server state
         let state = ServerState::new();
+        tokio::spawn(api::connections::expire_idle_transactions(state.clone()));
+        tokio::spawn(api::connections::flush_connections_usage());
 
         // Get the router that will handle all the requests for the REST API.
         let api = Self::api(&state).layer(
@@ -116,7 +135,34 @@ impl Server {
 
         // start the server
         info!("Listening on {}", listener.local_addr().unwrap().to_string());
-        axum::serve(listener, layers).with_graceful_shutdown(shutdown_signal()).await?;
+        match tls_config {
+            Some(tls_config) => {
+                info!("TLS certificate fingerprint (SHA-256): {}", tls_config.fingerprint);
+                let config = axum_server::tls_openssl::OpenSSLConfig
+                    ::from_pem_file(&tls_config.cert_file, &tls_config.key_file)
+                    .context("Error while configuring TLS.")?;
+                let handle = axum_server::Handle::new();
+                let shutdown_handle = handle.clone();
+                tokio::spawn(async move {
+                    shutdown_signal().await;
+                    shutdown_handle.graceful_shutdown(None);
+                });
+                axum_server
+                    ::from_tcp_openssl(listener.into_std()?, config)
+                    .handle(handle)
+                    .serve(layers.into_make_service_with_connect_info::<SocketAddr>()).await?;
+            }
+            None => {
+                axum
+                    ::serve(listener, layers.into_make_service_with_connect_info::<SocketAddr>())
+                    .with_graceful_shutdown(shutdown_signal()).await?;
+            }
+        }
+
+        // The usage recorded since the last periodic flush would be lost otherwise.
+        if let Err(e) = resources::connections::flush_connections_usage() {
+            warn!("Unable to write the usage of the connections: {}", e);
+        }
         Ok(())
     }
 
@@ -174,9 +220,12 @@ fn get_cors_layer() -> Result<CorsLayer> {
             CONTENT_TYPE,
             http::HeaderName::from_str(X_API_KEY_HEADER).unwrap(),
             http::HeaderName::from_str(X_REQUEST_ID_HEADER).unwrap(),
+            http::HeaderName::from_str(X_REQUEST_TIMEOUT_HEADER).unwrap(),
+            IF_NONE_MATCH,
+            IF_MODIFIED_SINCE,
         ])
         .max_age(settings::get_cors_max_age())
-        .expose_headers([http::HeaderName::from_str(X_REQUEST_ID_HEADER).unwrap()]);
+        .expose_headers([http::HeaderName::from_str(X_REQUEST_ID_HEADER).unwrap(), ETAG, LAST_MODIFIED]);
 
     let cors_allowed_origins_setting = settings::get_cors_allowed_origins();
     if cors_allowed_origins_setting.contains(&"*".to_string()) {
@@ -208,28 +257,62 @@ fn get_cors_layer() -> Result<CorsLayer> {
 
 /// Check the API key.
 ///
-/// The API key is passed in the X-API-Key header and is required for all requests.
+/// The API key is passed in the X-API-Key header and is required for all requests, except the ones authenticated with
+/// a valid personal access token (so scripts can call the API without sharing the API key of the agent).
 /// If the API key is not provided or invalid, the request will be rejected with a 403 Forbidden error.
-async fn check_api_key(mut req: Request, next: Next) -> ServerResult<Response> {
-    let api_key_header = req.headers().get(X_API_KEY_HEADER);
-    if let Some(api_key) = api_key_header {
-        let value = api_key.to_str();
-        if value.is_ok() && value.unwrap() == settings::get_api_key() {
-            // We've found the api key, before continuing to the next middleware, we need to add the context request
-            // TODO: If there is already a request id in the request header, we should not generate a new one.
-            let request_id = gen_request_id();
-            let context = RequestContext::new(&request_id);
-            req.extensions_mut().insert(Result::<RequestContext, Error>::Ok(context));
-            let mut response = next.run(req).await;
-            response.headers_mut().insert(X_REQUEST_ID_HEADER, HeaderValue::from_str(&request_id)?);
-            return Ok(response);
-        }
+/// After too many failures, the client address is locked out and the requests are rejected with a 429 Too Many
+/// Requests error until the lockout expires (the clients connected through a loopback address are never locked out).
+async fn check_api_key(State(state): State<ServerState>, mut req: Request, next: Next) -> ServerResult<Response> {
+    // The address of the client is only known when the server has been started with the connect info.
+    let client_keys = ServerState::get_auth_failure_keys(
+        req
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip()),
+        None
+    );
+    if let Some(retry_after) = client_keys.iter().filter_map(|key| state.get_lockout(key)).max() {
+        return Err(Error::TooManyRequests(retry_after));
+    }
+    let valid_api_key = req
+        .headers()
+        .get(X_API_KEY_HEADER)
+        .is_some_and(|api_key| api_key.to_str().is_ok_and(|value| value == settings::get_api_key()));
+    if valid_api_key || has_valid_access_token(&req)? {
+        client_keys.iter().for_each(|key| state.clear_auth_failures(key));
+
+        // We've found the api key, before continuing to the next middleware, we need to add the context request
+        // TODO: If there is already a request id in the request header, we should not generate a new one.
+        let request_id = gen_request_id();
+        let context = RequestContext::new(&request_id);
+        req.extensions_mut().insert(Result::<RequestContext, Error>::Ok(context));
+        let mut response = next.run(req).await;
+        response.headers_mut().insert(X_REQUEST_ID_HEADER, HeaderValue::from_str(&request_id)?);
+        return Ok(response);
     }
     warn!("Invalid or missing API key.");
-    debug!("headers: {:?}", req.headers());
+    debug!("headers: {:?}", redact_headers(req.headers()));
+    for key in client_keys.iter() {
+        if state.add_auth_failure(key) {
+            warn!("Too many authentication failures, '{}' is locked out.", key);
+        }
+    }
     Err(Error::Forbidden)
 }
 
+/// Check if the request is authenticated with a valid personal access token (`Authorization: Bearer sqp_...`).
+///
+/// The token is fully checked (scope, owner...) later on by `check_authentication`.
+fn has_valid_access_token(req: &Request) -> ServerResult<bool> {
+    let Some(authorization_header) = req.headers().get(http::header::AUTHORIZATION) else {
+        return Ok(false);
+    };
+    let Ok(token) = parse_authorization_header(AuthenticationMethod::UserPassword, authorization_header) else {
+        return Ok(false);
+    };
+    Ok(access_tokens::is_valid_access_token(&token)?)
+}
+
 /// Check the security token.
 ///
 /// The security token is passed in the Authorization header and is required for most of the requests.
@@ -251,9 +334,14 @@ async fn check_authentication(
         return Err(Error::BadRequest("(Invalid 'Authorization' header)".to_string()));
     };
 
-    let Some(user_session) = state.get_user_session(&security_token) else {
-        warn!("Invalid security token.");
-        return Err(Error::Forbidden);
+    let user_session = if security_token.starts_with(ACCESS_TOKEN_PREFIX) {
+        get_access_token_session(&security_token, req.method())?
+    } else {
+        let Some(user_session) = state.get_user_session(&security_token) else {
+            warn!("Invalid security token.");
+            return Err(Error::Forbidden);
+        };
+        user_session
     };
 
     // Add the user_session information to the context of the request.
@@ -264,6 +352,171 @@ async fn check_authentication(
     Ok(next.run(req).await)
 }
 
+/// Get the user session of a request authenticated with a personal access token.
+///
+/// Tokens with the `read` scope are only allowed to send GET (or HEAD) requests.
+/// The last time the token has been used is saved in the background, so the request does not wait for the file to be
+/// written.
+fn get_access_token_session(token: &str, method: &Method) -> ServerResult<Arc<UserSession>> {
+    let Some((username, access_token, save)) = access_tokens::authenticate(token)? else {
+        warn!("Invalid access token.");
+        return Err(Error::Forbidden);
+    };
+    if save {
+        // The users directory is resolved before moving to the blocking thread where the settings may differ (tests).
+        let users_dir = access_tokens::get_users_dir();
+        let username = username.clone();
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = access_tokens::save_access_tokens(&users_dir, &username) {
+                warn!("Unable to save the access tokens of the user '{}': {}", username, e);
+            }
+        });
+    }
+    if access_token.scope == AccessTokenScope::Read && method != Method::GET && method != Method::HEAD {
+        warn!("The access token '{}' of the user '{}' is read-only.", access_token.name, username);
+        return Err(Error::Forbidden);
+    }
+    let user = users::get_user(&username)?;
+    Ok(Arc::new(UserSession::from_access_token(username, &user.user_id)))
+}
+
+/// Enforce the deadline of the request.
+///
+/// The deadline is given in milliseconds by the optional X-Request-Timeout header. If the request has not been processed
+/// before the deadline, the processing is aborted (the future of the handler is dropped) and the request is rejected with
+/// a 408 Request Timeout error. If the header is not valid, the request will be rejected with a 400 Bad Request error.
+async fn check_request_timeout(req: Request, next: Next) -> ServerResult<Response> {
+    let Some(request_timeout_header) = req.headers().get(X_REQUEST_TIMEOUT_HEADER) else {
+        // No deadline.
+        return Ok(next.run(req).await);
+    };
+    let Some(timeout) = request_timeout_header.to_str().ok().and_then(|value| value.trim().parse::<u64>().ok()) else {
+        return Err(Error::BadRequest(format!("(Invalid '{}' header)", X_REQUEST_TIMEOUT_HEADER)));
+    };
+    match tokio::time::timeout(Duration::from_millis(timeout), next.run(req)).await {
+        Ok(response) => Ok(response),
+        Err(_) => {
+            warn!("Request aborted, the deadline of {}ms has been exceeded.", timeout);
+            Err(Error::RequestTimeout)
+        }
+    }
+}
+
+/// Format the headers of a request for the logs, masking the values of the headers carrying credentials.
+fn redact_headers(headers: &http::HeaderMap) -> Vec<String> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let sensitive = name == AUTHORIZATION || name == http::header::COOKIE;
+            if sensitive || name.as_str().eq_ignore_ascii_case(X_API_KEY_HEADER) {
+                format!("{}: ***", name)
+            } else {
+                format!("{}: {}", name, value.to_str().unwrap_or("<binary>"))
+            }
+        })
+        .collect()
+}
+
+/// Handle the conditional GET requests.
+///
+/// An ETag computed from the body is added to the successful responses of GET requests. If the request has an
+/// `If-None-Match` header matching this ETag, the body is not sent again and the response is a 304 Not Modified.
+/// The bodies of unknown size (streamed) or larger than `CONDITIONAL_GET_MAX_BODY_SIZE` are not buffered to compute an
+/// ETag, they are sent as is.
+///
+/// If the handler has set a `Last-Modified` header, the response is also a 304 Not Modified when the request has an
+/// `If-Modified-Since` header that is not older (this header is ignored if the request has an `If-None-Match` one).
+async fn check_conditional_get(req: Request, next: Next) -> Response {
+    if req.method() != Method::GET {
+        return next.run(req).await;
+    }
+    let if_none_match = req.headers().get(IF_NONE_MATCH).and_then(|value| value.to_str().ok()).map(str::to_string);
+    let if_modified_since = req.headers()
+        .get(IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| httpdate::parse_http_date(value).ok());
+    let response = next.run(req).await;
+    if response.status() != http::StatusCode::OK {
+        return response;
+    }
+
+    if if_none_match.is_none() {
+        let last_modified = response
+            .headers()
+            .get(LAST_MODIFIED)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| httpdate::parse_http_date(value).ok());
+        if let (Some(last_modified), Some(if_modified_since)) = (last_modified, if_modified_since) {
+            if last_modified <= if_modified_since {
+                return not_modified(response.headers());
+            }
+        }
+    }
+
+    if !response.body().size_hint().upper().is_some_and(|size| size <= CONDITIONAL_GET_MAX_BODY_SIZE) {
+        // A streamed body (e.g. server-sent events) or a body too large to be buffered.
+        return response;
+    }
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, CONDITIONAL_GET_MAX_BODY_SIZE as usize).await else {
+        return Error::InternalServerError.into_response();
+    };
+    let etag = format!("\"{}\"", hex::encode(&openssl::sha::sha256(&bytes)[..16]));
+    let Ok(etag_header) = HeaderValue::from_str(&etag) else {
+        return Error::InternalServerError.into_response();
+    };
+    parts.headers.insert(ETAG, etag_header);
+
+    let matches = if_none_match.is_some_and(|value| {
+        value.split(',').any(|candidate| {
+            let candidate = candidate.trim();
+            candidate == "*" || candidate.trim_start_matches("W/") == etag
+        })
+    });
+    if matches {
+        return not_modified(&parts.headers);
+    }
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+/// Create a 304 Not Modified response, keeping the validators and the request id of the original response.
+fn not_modified(headers: &http::HeaderMap) -> Response {
+    let mut response = http::StatusCode::NOT_MODIFIED.into_response();
+    for name in [ETAG, LAST_MODIFIED, http::HeaderName::from_static(X_REQUEST_ID_HEADER)] {
+        if let Some(value) = headers.get(&name) {
+            response.headers_mut().insert(name, value.clone());
+        }
+    }
+    response
+}
+
+/// Log the slow requests.
+///
+/// Any request taking longer than the setting `slow_request_threshold` to be processed is logged as a warning along with
+/// its request id, so UI freezes can be traced back to the agent.
+async fn log_slow_request(req: Request, next: Next) -> Response {
+    let method = req.method().clone();
+    let uri = req.uri().clone();
+    let started_at = Instant::now();
+    let response = next.run(req).await;
+    let latency = started_at.elapsed();
+    if latency >= settings::get_slow_request_threshold() {
+        warn!(
+            method = %method,
+            uri = %redact(&uri.to_string()),
+            status = response.status().as_u16(),
+            latency_ms = latency.as_millis() as u64,
+            request_id = response
+                .headers()
+                .get(X_REQUEST_ID_HEADER)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default(),
+            "Slow request."
+        );
+    }
+    response
+}
+
 /// Generate a request id.
 ///
 /// The request id is used to track a request through the system. It is generated using a random number and the current
@@ -307,7 +560,7 @@ mod tests {
     use common::pid_file::PidFile;
     use tempfile::tempdir;
     use std::io::Write;
-    use crate::{ resources::users::create_user, utils::tests::settings };
+    use crate::{ models::auth::AccessTokenRequest, resources::users::create_user, utils::tests::settings };
     use super::*;
     use tower::ServiceExt; // for `call`, `oneshot`, and `ready`
     use tokio::io::AsyncWriteExt;
@@ -325,6 +578,7 @@ mod tests {
             port: 1234,
             address: "127.0.0.1".to_string(),
             api_key: "cf55f65...".to_string(),
+            tls_fingerprint: None,
         };
         std::fs::File
             ::create(app_dir.path().join(PID_FILENAME))
@@ -352,9 +606,20 @@ mod tests {
             tcp_stream.write_all(b"HTTP/1.1 200 OK\r\n\r\n").await.unwrap();
             let (mut tcp_stream, _) = listener.accept().await.unwrap();
             tcp_stream.write_all(b"HTTP/1.1 500 Internal Server Error\r\n\r\n").await.unwrap();
+            let (mut tcp_stream, _) = listener.accept().await.unwrap();
+            tcp_stream.write_all(b"HTTP/1.1 403 Forbidden\r\n\r\n").await.unwrap();
         });
         assert!(Server::check_if_running().await.is_err()); // 200 OK -> the server is responding
         assert!(Server::check_if_running().await.is_ok()); // 500 Internal Server Error -> the server is running as expected
+
+        // 5. The server paired with the desktop application is already running (no API key in the pid file)
+        pid_file.api_key = String::new();
+        std::fs::File
+            ::create(app_dir.path().join(PID_FILENAME))
+            .unwrap()
+            .write_all(toml::to_string_pretty(&pid_file).unwrap().as_bytes())
+            .unwrap();
+        assert!(Server::check_if_running().await.is_err()); // 403 Forbidden -> the server is responding
         std::mem::drop(handle)
     }
 
@@ -380,7 +645,7 @@ mod tests {
         let http_client = reqwest::Client::new();
 
         // run the server as a task
-        let task_handle = tokio::spawn(async move { server.run(listener).await });
+        let task_handle = tokio::spawn(async move { server.run(listener, None).await });
 
         // Send a request to the server
         let result = http_client
@@ -466,6 +731,201 @@ mod tests {
         assert_eq!(response.status(), http::StatusCode::OK);
         assert!(response.headers().contains_key(X_REQUEST_ID_HEADER));
         assert!(!response.headers().get(X_REQUEST_ID_HEADER).unwrap().to_str().unwrap().is_empty());
+
+        // 4. Too many failures from the same address
+        let request = || {
+            let mut request = Request::builder()
+                .uri("/api/v1/agent")
+                .header(X_API_KEY_HEADER, "invalid_api_key")
+                .body(Body::empty())
+                .unwrap();
+            request.extensions_mut().insert(ConnectInfo(SocketAddr::from(([192, 168, 0, 1], 1234))));
+            request
+        };
+        for _ in 0..5 {
+            let response = super::Server::api(&state).oneshot(request()).await.unwrap();
+            assert_eq!(response.status(), http::StatusCode::FORBIDDEN);
+        }
+        let response = super::Server::api(&state).oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key(http::header::RETRY_AFTER));
+
+        // 5. A valid API key clears the failures of the address
+        let request = |address: [u8; 4], api_key: &str| {
+            let mut request = Request::builder()
+                .uri("/api/v1/agent")
+                .header(X_API_KEY_HEADER, api_key)
+                .body(Body::empty())
+                .unwrap();
+            request.extensions_mut().insert(ConnectInfo(SocketAddr::from((address, 1234))));
+            request
+        };
+        for api_key in ["invalid_api_key"; 4].iter().chain([settings::get_api_key().as_str()].iter()) {
+            super::Server::api(&state).oneshot(request([192, 168, 0, 2], api_key)).await.unwrap();
+        }
+        let response = super::Server::api(&state).oneshot(request([192, 168, 0, 2], "invalid_api_key")).await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::FORBIDDEN);
+
+        // 6. The loopback addresses are never locked out
+        for _ in 0..10 {
+            let response = super::Server
+                ::api(&state)
+                .oneshot(request([127, 0, 0, 1], "invalid_api_key")).await
+                .unwrap();
+            assert_eq!(response.status(), http::StatusCode::FORBIDDEN);
+        }
+        let response = super::Server
+            ::api(&state)
+            .oneshot(request([127, 0, 0, 1], &settings::get_api_key())).await
+            .unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_check_request_timeout() {
+        let router = Router::new()
+            .route(
+                "/slow",
+                axum::routing::get(|| async {
+                    tokio::time::sleep(Duration::from_millis(200)).await;
+                })
+            )
+            .layer(from_fn(check_request_timeout));
+
+        // 1. No deadline
+        let response = router
+            .clone()
+            .oneshot(Request::builder().uri("/slow").body(Body::empty()).unwrap()).await
+            .unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+
+        // 2. Deadline not exceeded
+        let response = router
+            .clone()
+            .oneshot(
+                Request::builder().uri("/slow").header(X_REQUEST_TIMEOUT_HEADER, "5000").body(Body::empty()).unwrap()
+            ).await
+            .unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+
+        // 3. Deadline exceeded
+        let response = router
+            .clone()
+            .oneshot(
+                Request::builder().uri("/slow").header(X_REQUEST_TIMEOUT_HEADER, "10").body(Body::empty()).unwrap()
+            ).await
+            .unwrap();
+        assert_eq!(response.status(), http::StatusCode::REQUEST_TIMEOUT);
+
+        // 4. Invalid header
+        let response = router
+            .clone()
+            .oneshot(
+                Request::builder().uri("/slow").header(X_REQUEST_TIMEOUT_HEADER, "1s").body(Body::empty()).unwrap()
+            ).await
+            .unwrap();
+        assert_eq!(response.status(), http::StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_redact_headers() {
+        let mut headers = http::HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer sqd_secret"));
+        headers.insert(X_API_KEY_HEADER, HeaderValue::from_static("secret"));
+        headers.insert(ACCEPT, HeaderValue::from_static("application/json"));
+        let redacted = redact_headers(&headers).join(", ");
+        assert!(!redacted.contains("secret"));
+        assert!(redacted.contains("authorization: ***"));
+        assert!(redacted.contains("accept: application/json"));
+    }
+
+    #[tokio::test]
+    async fn test_check_conditional_get() {
+        const LAST_MODIFIED_DATE: &str = "Wed, 21 Oct 2015 07:28:00 GMT";
+        let router = Router::new()
+            .route(
+                "/data",
+                axum::routing::get(|| async { "Hello World" }).post(|| async { "Hello World" })
+            )
+            .route(
+                "/dated",
+                axum::routing::get(|| async { ([(LAST_MODIFIED, LAST_MODIFIED_DATE)], "Hello World") })
+            )
+            .route(
+                "/stream",
+                axum::routing::get(|| async {
+                    Body::from_stream(futures::stream::iter(vec![Ok::<_, std::io::Error>("Hello World")]))
+                })
+            )
+            .route(
+                "/large",
+                axum::routing::get(|| async { "x".repeat(CONDITIONAL_GET_MAX_BODY_SIZE as usize + 1) })
+            )
+            .layer(from_fn(check_conditional_get));
+        let get = |uri: &str, headers: &[(http::HeaderName, &str)]| {
+            let mut request = Request::builder().uri(uri);
+            for (name, value) in headers {
+                request = request.header(name, *value);
+            }
+            router.clone().oneshot(request.body(Body::empty()).unwrap())
+        };
+
+        // 1. The ETag is added to the response of a GET request
+        let response = router
+            .clone()
+            .oneshot(Request::builder().uri("/data").body(Body::empty()).unwrap()).await
+            .unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+        let etag = response.headers().get(ETAG).unwrap().to_str().unwrap().to_string();
+
+        // 2. Not modified
+        let response = router
+            .clone()
+            .oneshot(Request::builder().uri("/data").header(IF_NONE_MATCH, &etag).body(Body::empty()).unwrap()).await
+            .unwrap();
+        assert_eq!(response.status(), http::StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers().get(ETAG).unwrap().to_str().unwrap(), etag);
+
+        // 3. Modified
+        let response = router
+            .clone()
+            .oneshot(
+                Request::builder().uri("/data").header(IF_NONE_MATCH, "\"0123\"").body(Body::empty()).unwrap()
+            ).await
+            .unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+
+        // 4. Not a GET request
+        let response = router
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/data")
+                    .method("POST")
+                    .header(IF_NONE_MATCH, &etag)
+                    .body(Body::empty())
+                    .unwrap()
+            ).await
+            .unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert!(!response.headers().contains_key(ETAG));
+
+        // 5. Last-Modified
+        let response = get("/dated", &[(IF_MODIFIED_SINCE, LAST_MODIFIED_DATE)]).await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers().get(LAST_MODIFIED).unwrap(), LAST_MODIFIED_DATE);
+        let response = get("/dated", &[(IF_MODIFIED_SINCE, "Tue, 20 Oct 2015 07:28:00 GMT")]).await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+        let response = get("/dated", &[(IF_MODIFIED_SINCE, LAST_MODIFIED_DATE), (IF_NONE_MATCH, "\"0123\"")]).await;
+        assert_eq!(response.unwrap().status(), http::StatusCode::OK);
+
+        // 6. Streamed and large bodies are not buffered
+        let response = get("/stream", &[]).await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert!(!response.headers().contains_key(ETAG));
+        let response = get("/large", &[]).await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert!(!response.headers().contains_key(ETAG));
     }
 
     #[tokio::test]
@@ -535,5 +995,54 @@ mod tests {
             ).await
             .unwrap();
         assert_eq!(response.status(), http::StatusCode::OK);
+
+        // 5. Valid access token
+        let access_token = access_tokens
+            ::create_access_token(&"local".into(), AccessTokenRequest {
+                name: "script".to_string(),
+                scope: AccessTokenScope::Read,
+                expires_in_days: None,
+            })
+            .unwrap();
+        let request = |method: &str, uri: &str| {
+            Request::builder()
+                .uri(uri)
+                .method(method)
+                .header(AUTHORIZATION, format!("Bearer {}", access_token.token))
+                .body(Body::empty())
+                .unwrap()
+        };
+        let response = super::Server::api(&state).oneshot(request("GET", "/api/v1/users/local/user")).await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::OK);
+
+        // 6. Invalid access token without the API key
+        let response = super::Server
+            ::api(&state)
+            .oneshot(
+                Request::builder()
+                    .uri("/api/v1/users/local/user")
+                    .method("GET")
+                    .header(AUTHORIZATION, format!("Bearer {}invalid", ACCESS_TOKEN_PREFIX))
+                    .body(Body::empty())
+                    .unwrap()
+            ).await
+            .unwrap();
+        assert_eq!(response.status(), http::StatusCode::FORBIDDEN);
+
+        // 7. The access tokens cannot be managed with an access token
+        let response = super::Server::api(&state).oneshot(request("GET", "/api/v1/users/local/tokens")).await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::FORBIDDEN);
+
+        // 8. Read-only access token used for a POST request
+        let response = super::Server
+            ::api(&state)
+            .oneshot(request("POST", "/api/v1/users/local/samples/reset")).await
+            .unwrap();
+        assert_eq!(response.status(), http::StatusCode::FORBIDDEN);
+
+        // 9. Revoked access token
+        access_tokens::revoke_access_token(&"local".into(), &access_token.access_token.id).unwrap();
+        let response = super::Server::api(&state).oneshot(request("GET", "/api/v1/users/local/user")).await.unwrap();
+        assert_eq!(response.status(), http::StatusCode::FORBIDDEN);
     }
 }
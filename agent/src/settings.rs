@@ -4,15 +4,28 @@ use crate::models::agent::{ AgentSettings, LogLevel };
 use crate::settings_getters;
 use std::fmt;
 use std::net::Ipv4Addr;
+use std::num::NonZeroUsize;
 use std::path::{ PathBuf, Path };
 use ini::Ini;
 use anyhow::{ anyhow, Context, Result };
+use common::constants::AGENT_API_KEY_ENV;
 
 #[cfg(not(test))]
 use ::{ lazy_static::lazy_static, tracing::error, rand::Rng, hex };
 
 const AGENT_CONF: &str = "agent.conf";
 
+/// The version of the format of the configuration file.
+///
+/// It must be incremented when a setting is renamed or its format is changed, so an agent loading a configuration file
+/// written for a newer version can warn that some settings may not be supported.
+const SETTINGS_VERSION: u32 = 1;
+
+/// The settings that have been renamed (deprecated name, new name).
+///
+/// The deprecated names are still accepted with a warning.
+const DEPRECATED_SETTINGS: &[(&str, &str)] = &[];
+
 /// Default address used to serve the API.
 const DEFAULT_LISTEN_ADDRESS: &str = "127.0.0.1";
 
@@ -37,6 +50,17 @@ settings_getters! {
     get_log_dir, log_dir: String,
     get_cors_allowed_origins, cors_allowed_origins: Vec<String>,
     get_cors_max_age, cors_max_age: std::time::Duration,
+    get_slow_request_threshold, slow_request_threshold: std::time::Duration,
+    get_connect_timeout, connect_timeout: std::time::Duration,
+    get_transaction_idle_timeout, transaction_idle_timeout: std::time::Duration,
+    get_catalog_strict_names, catalog_strict_names: bool,
+    get_production_write_confirmation, production_write_confirmation: bool,
+    get_auth_max_failures, auth_max_failures: u32,
+    get_auth_lockout_duration, auth_lockout_duration: std::time::Duration,
+    get_tls_enabled, tls_enabled: bool,
+    get_tls_cert_file, tls_cert_file: String,
+    get_tls_key_file, tls_key_file: String,
+    get_warnings, warnings: Vec<String>,
 }
 
 pub fn get_log_level() -> tracing::Level {
@@ -68,36 +92,199 @@ impl Default for AgentSettings {
             log_level: LogLevel::Info,
             cors_allowed_origins: vec!["*".to_string()],
             cors_max_age: std::time::Duration::from_secs(86400),
+            slow_request_threshold: std::time::Duration::from_millis(1000),
+            connect_timeout: std::time::Duration::from_secs(30),
+            transaction_idle_timeout: std::time::Duration::from_secs(600),
+            catalog_strict_names: false,
+            production_write_confirmation: true,
+            auth_max_failures: 5,
+            auth_lockout_duration: std::time::Duration::from_secs(60),
+            tls_enabled: false,
+            tls_cert_file: String::new(),
+            tls_key_file: String::new(),
+            warnings: Vec::new(),
         }
     }
 }
 
 impl AgentSettings {
+    /// Load the settings from a configuration file.
+    ///
+    /// Invalid values are errors, while unknown and deprecated settings are only reported in `warnings` so a
+    /// configuration file written for another version of the agent can still be used.
     pub fn load_from_file(&mut self, config_file: &Path) -> Result<()> {
         let ini = Ini::load_from_file(config_file)?;
         let section = ini.section(None::<String>).unwrap();
         for (key, value) in section.iter() {
-            match key {
-                "listen_address" => {
-                    let address: Ipv4Addr = value.parse().with_context(|| { format!("{key}={value}") })?;
-                    self.listen_address = address.to_string();
-                }
-                "port" => {
-                    self.port = value.parse::<u16>().with_context(|| { format!("{key}={value}") })?;
-                }
-                "base_dir" => {
-                    self.base_dir = value.to_string();
-                }
-                "api_key" => {
-                    self.api_key = value.to_string();
-                }
-                _ => {
-                    return Err(anyhow!("Invalid entry: {}={}", key, value));
-                }
+            if let Some(warning) = self.set(key, value)? {
+                self.warnings.push(format!("{}: {}", config_file.display(), warning));
             }
         }
         Ok(())
     }
+
+    /// Set a setting from an entry of the configuration file.
+    ///
+    /// Returns a warning if the entry has been accepted but should be fixed (deprecated, unknown...).
+    fn set(&mut self, key: &str, value: &str) -> Result<Option<String>> {
+        self.set_with_deprecated(key, value, DEPRECATED_SETTINGS)
+    }
+
+    /// Set a setting from an entry of the configuration file, accepting the given deprecated names.
+    fn set_with_deprecated(
+        &mut self,
+        key: &str,
+        value: &str,
+        deprecated_settings: &[(&str, &str)]
+    ) -> Result<Option<String>> {
+        let (key, mut warning) = match deprecated_settings.iter().find(|(deprecated, _)| *deprecated == key) {
+            Some((deprecated, key)) => (*key, Some(format!("'{}' is deprecated, use '{}' instead.", deprecated, key))),
+            None => (key, None),
+        };
+        match key {
+            "version" => {
+                let version = value.parse::<u32>().with_context(|| { format!("{key}={value}") })?;
+                if version > SETTINGS_VERSION {
+                    warning = Some(
+                        format!(
+                            "The configuration file is for a newer version of the agent (version={}), {}",
+                            version,
+                            "some settings may not be supported."
+                        )
+                    );
+                }
+            }
+            "listen_address" => {
+                let address: Ipv4Addr = value.parse().with_context(|| { format!("{key}={value}") })?;
+                self.listen_address = address.to_string();
+            }
+            "port" => {
+                self.port = value.parse::<u16>().with_context(|| { format!("{key}={value}") })?;
+            }
+            "base_dir" => {
+                self.base_dir = value.to_string();
+            }
+            "api_key" => {
+                self.api_key = value.to_string();
+            }
+            "max_user_sessions" => {
+                self.max_user_sessions = value
+                    .parse::<NonZeroUsize>()
+                    .with_context(|| { format!("{key}={value}") })?
+                    .get();
+            }
+            "max_refresh_tokens" => {
+                self.max_refresh_tokens = value
+                    .parse::<NonZeroUsize>()
+                    .with_context(|| { format!("{key}={value}") })?
+                    .get();
+            }
+            "token_expiration" => {
+                let secs = value.parse::<u64>().with_context(|| { format!("{key}={value}") })?;
+                self.token_expiration = std::time::Duration::from_secs(secs);
+            }
+            "log_collector" => {
+                self.log_collector = value.parse::<bool>().with_context(|| { format!("{key}={value}") })?;
+            }
+            "log_dir" => {
+                self.log_dir = value.to_string();
+            }
+            "log_level" => {
+                self.log_level = match value.to_lowercase().as_str() {
+                    "error" => LogLevel::Error,
+                    "warning" => LogLevel::Warning,
+                    "info" => LogLevel::Info,
+                    "debug" => LogLevel::Debug,
+                    "trace" => LogLevel::Trace,
+                    _ => {
+                        return Err(anyhow!("{key}={value}"));
+                    }
+                };
+            }
+            "cors_allowed_origins" => {
+                self.cors_allowed_origins = value
+                    .split(',')
+                    .map(|origin| origin.trim().to_string())
+                    .filter(|origin| !origin.is_empty())
+                    .collect();
+            }
+            "cors_max_age" => {
+                let secs = value.parse::<u64>().with_context(|| { format!("{key}={value}") })?;
+                self.cors_max_age = std::time::Duration::from_secs(secs);
+            }
+            "slow_request_threshold" => {
+                let millis = value.parse::<u64>().with_context(|| { format!("{key}={value}") })?;
+                self.slow_request_threshold = std::time::Duration::from_millis(millis);
+            }
+            "connect_timeout" => {
+                let secs = value.parse::<u64>().with_context(|| { format!("{key}={value}") })?;
+                self.connect_timeout = std::time::Duration::from_secs(secs);
+            }
+            "transaction_idle_timeout" => {
+                let secs = value.parse::<u64>().with_context(|| { format!("{key}={value}") })?;
+                self.transaction_idle_timeout = std::time::Duration::from_secs(secs);
+            }
+            "catalog_strict_names" => {
+                self.catalog_strict_names = value.parse::<bool>().with_context(|| { format!("{key}={value}") })?;
+            }
+            "production_write_confirmation" => {
+                self.production_write_confirmation = value
+                    .parse::<bool>()
+                    .with_context(|| { format!("{key}={value}") })?;
+            }
+            "auth_max_failures" => {
+                self.auth_max_failures = value.parse::<u32>().with_context(|| { format!("{key}={value}") })?;
+            }
+            "auth_lockout_duration" => {
+                let secs = value.parse::<u64>().with_context(|| { format!("{key}={value}") })?;
+                self.auth_lockout_duration = std::time::Duration::from_secs(secs);
+            }
+            "tls_enabled" => {
+                self.tls_enabled = value.parse::<bool>().with_context(|| { format!("{key}={value}") })?;
+            }
+            "tls_cert_file" => {
+                self.tls_cert_file = value.to_string();
+            }
+            "tls_key_file" => {
+                self.tls_key_file = value.to_string();
+            }
+            _ => {
+                warning = Some(format!("Unknown setting ignored: {}={}", key, value));
+            }
+        }
+        Ok(warning)
+    }
+}
+
+/// Validate a configuration file (`agent.conf` in the app directory if not specified).
+///
+/// Unlike `AgentSettings::load_from_file`, all the entries are checked. The errors and warnings are printed and an
+/// error is returned if the file is not valid.
+pub fn validate_config_file(config_file: Option<&Path>) -> Result<()> {
+    let config_file = config_file.map(Path::to_path_buf).unwrap_or_else(|| get_app_dir().join(AGENT_CONF));
+    let ini = Ini::load_from_file(&config_file).with_context(|| {
+        format!("{}: unable to read the configuration file.", config_file.display())
+    })?;
+    let mut settings = AgentSettings::default();
+    let mut errors = 0;
+    for (key, value) in ini.section(None::<String>).unwrap().iter() {
+        match settings.set(key, value) {
+            Ok(None) => {}
+            Ok(Some(warning)) => println!("warning: {}", warning),
+            Err(err) => {
+                println!("error: invalid value: {}", err);
+                errors += 1;
+            }
+        }
+    }
+    for (section, _) in ini.iter().filter(|(section, _)| section.is_some()) {
+        println!("warning: Unknown section ignored: [{}]", section.unwrap_or_default());
+    }
+    if errors > 0 {
+        return Err(anyhow!("{}: {} invalid setting(s).", config_file.display(), errors));
+    }
+    println!("{}: the configuration is valid.", config_file.display());
+    Ok(())
 }
 
 impl fmt::Display for AgentSettings {
@@ -120,11 +307,37 @@ impl fmt::Display for AgentSettings {
 /// This is intended to be used to display the current configuration when using the command line argument --show-config.
 fn get_config(settings: &AgentSettings) -> Ini {
     let mut ini = Ini::new();
+    let log_level = match settings.log_level {
+        LogLevel::Error => "error",
+        LogLevel::Warning => "warning",
+        LogLevel::Info => "info",
+        LogLevel::Debug => "debug",
+        LogLevel::Trace => "trace",
+    };
     ini.with_section(None::<String>)
+        .set("version", SETTINGS_VERSION.to_string())
         .set("listen_address", &settings.listen_address)
         .set("port", settings.port.to_string())
         .set("base_dir", &settings.base_dir)
-        .set("api_key", &settings.api_key);
+        .set("api_key", &settings.api_key)
+        .set("max_user_sessions", settings.max_user_sessions.to_string())
+        .set("max_refresh_tokens", settings.max_refresh_tokens.to_string())
+        .set("token_expiration", settings.token_expiration.as_secs().to_string())
+        .set("log_collector", settings.log_collector.to_string())
+        .set("log_dir", &settings.log_dir)
+        .set("log_level", log_level)
+        .set("cors_allowed_origins", settings.cors_allowed_origins.join(","))
+        .set("cors_max_age", settings.cors_max_age.as_secs().to_string())
+        .set("slow_request_threshold", settings.slow_request_threshold.as_millis().to_string())
+        .set("connect_timeout", settings.connect_timeout.as_secs().to_string())
+        .set("transaction_idle_timeout", settings.transaction_idle_timeout.as_secs().to_string())
+        .set("catalog_strict_names", settings.catalog_strict_names.to_string())
+        .set("production_write_confirmation", settings.production_write_confirmation.to_string())
+        .set("auth_max_failures", settings.auth_max_failures.to_string())
+        .set("auth_lockout_duration", settings.auth_lockout_duration.as_secs().to_string())
+        .set("tls_enabled", settings.tls_enabled.to_string())
+        .set("tls_cert_file", &settings.tls_cert_file)
+        .set("tls_key_file", &settings.tls_key_file);
     ini
 }
 
@@ -170,6 +383,10 @@ fn make_settings(args: &commandline::Args) -> Result<AgentSettings> {
         if port.is_some() {
             settings.port = (*port).unwrap();
         }
+        if let Ok(paired_api_key) = std::env::var(AGENT_API_KEY_ENV) {
+            // The agent is started by the desktop application with the key paired with it.
+            settings.api_key = paired_api_key;
+        }
         if api_key.is_some() {
             settings.api_key = api_key.clone().unwrap().to_string();
         }
@@ -233,10 +450,29 @@ mod tests {
             ..Default::default()
         };
         assert_eq!(
-            r"listen_address=127.0.0.1
+            r"version=1
+            listen_address=127.0.0.1
             port=0
             base_dir=/tmp
             api_key=cf55f65...
+            max_user_sessions=100
+            max_refresh_tokens=100
+            token_expiration=3600
+            log_collector=true
+            log_dir=/tmp/logs
+            log_level=info
+            cors_allowed_origins=*
+            cors_max_age=86400
+            slow_request_threshold=1000
+            connect_timeout=30
+            transaction_idle_timeout=600
+            catalog_strict_names=false
+            production_write_confirmation=true
+            auth_max_failures=5
+            auth_lockout_duration=60
+            tls_enabled=false
+            tls_cert_file=
+            tls_key_file=
             "
                 .to_string()
                 .replace(' ', ""),
@@ -328,7 +564,7 @@ mod tests {
             assert!(actual.unwrap_err().to_string().ends_with("unable to read the configuration file."));
             fs::remove_dir_all(app_dir).unwrap();
         }
-        // 5.2) invalid config file (invalid entry)
+        // 5.2) invalid config file (invalid syntax)
         {
             let app_dir = tempdir().unwrap();
             settings::set_app_dir(app_dir.path());
@@ -337,6 +573,18 @@ mod tests {
             assert!(actual.is_err());
             fs::remove_dir_all(app_dir).unwrap();
         }
+
+        // 6) unknown entries are only raising warnings
+        {
+            let app_dir = tempdir().unwrap();
+            settings::set_app_dir(app_dir.path());
+            std::fs::write(app_dir.path().join(AGENT_CONF), "port=1234\nxyz=123").unwrap();
+            let actual = make_settings(&Args::parse_from(["agent", "start"])).unwrap();
+            assert_eq!(actual.port, 1234);
+            assert_eq!(actual.warnings.len(), 1);
+            assert!(actual.warnings[0].ends_with("Unknown setting ignored: xyz=123"));
+            fs::remove_dir_all(app_dir).unwrap();
+        }
     }
 
     #[test]
@@ -357,10 +605,82 @@ mod tests {
         assert!(result.is_err());
         assert_eq!("port=123456", result.unwrap_err().to_string());
 
+        // invalid slow_request_threshold
+        std::fs::write(&file, "slow_request_threshold = 1s").unwrap();
+        let result = settings.load_from_file(&file);
+        assert!(result.is_err());
+        assert_eq!("slow_request_threshold=1s", result.unwrap_err().to_string());
+
+        // invalid log_level
+        std::fs::write(&file, "log_level = verbose").unwrap();
+        let result = settings.load_from_file(&file);
+        assert!(result.is_err());
+        assert_eq!("log_level=verbose", result.unwrap_err().to_string());
+
+        // invalid max_user_sessions
+        std::fs::write(&file, "max_user_sessions = 0").unwrap();
+        let result = settings.load_from_file(&file);
+        assert!(result.is_err());
+        assert_eq!("max_user_sessions=0", result.unwrap_err().to_string());
+
+        // valid entries
+        std::fs::write(
+            &file,
+            "log_level = debug\ncors_allowed_origins = http://a.com, http://b.com\ntoken_expiration = 60"
+        ).unwrap();
+        assert!(settings.load_from_file(&file).is_ok());
+        assert_eq!(settings.log_level, LogLevel::Debug);
+        assert_eq!(settings.cors_allowed_origins, vec!["http://a.com", "http://b.com"]);
+        assert_eq!(settings.token_expiration, std::time::Duration::from_secs(60));
+        assert!(settings.warnings.is_empty());
+
         // unknown entry
         std::fs::write(&file, "xyz = 123").unwrap();
-        let result = settings.load_from_file(&file);
+        assert!(settings.load_from_file(&file).is_ok());
+        assert_eq!(settings.warnings.len(), 1);
+        assert!(settings.warnings[0].ends_with("Unknown setting ignored: xyz=123"));
+
+        // newer version
+        std::fs::write(&file, format!("version = {}", SETTINGS_VERSION + 1)).unwrap();
+        assert!(settings.load_from_file(&file).is_ok());
+        assert_eq!(settings.warnings.len(), 2);
+        assert!(settings.warnings[1].contains("newer version of the agent"));
+    }
+
+    #[test]
+    fn test_set_deprecated_setting() {
+        let deprecated_settings = [("listen_port", "port")];
+        let mut settings = AgentSettings::default();
+
+        // 1) deprecated name
+        let warning = settings.set_with_deprecated("listen_port", "1234", &deprecated_settings).unwrap();
+        assert_eq!(settings.port, 1234);
+        assert_eq!(warning.unwrap(), "'listen_port' is deprecated, use 'port' instead.");
+
+        // 2) new name
+        assert!(settings.set_with_deprecated("port", "4321", &deprecated_settings).unwrap().is_none());
+        assert_eq!(settings.port, 4321);
+
+        // 3) invalid value for a deprecated name
+        assert!(settings.set_with_deprecated("listen_port", "abc", &deprecated_settings).is_err());
+    }
+
+    #[test]
+    fn test_validate_config_file() {
+        let app_dir = tempdir().unwrap();
+        let file = app_dir.path().join(AGENT_CONF);
+
+        // 1) missing file
+        assert!(validate_config_file(Some(&file)).is_err());
+
+        // 2) valid file (with warnings)
+        std::fs::write(&file, format!("version={}\nport=1234\nxyz=123", SETTINGS_VERSION)).unwrap();
+        assert!(validate_config_file(Some(&file)).is_ok());
+
+        // 3) invalid values
+        std::fs::write(&file, "port=abc\nlog_level=verbose").unwrap();
+        let result = validate_config_file(Some(&file));
         assert!(result.is_err());
-        assert_eq!("Invalid entry: xyz=123", result.unwrap_err().to_string());
+        assert!(result.unwrap_err().to_string().ends_with("2 invalid setting(s)."));
     }
 }
@@ -10,9 +10,30 @@ pub const USER_DATA_DIRNAME: &str = "data";
 /// File extension of the catalog entries & workspaces.
 pub const CATALOG_ENTRY_FILE_EXTENSION: &str = "json";
 
+/// Maximum size of the body of a response to a GET request buffered to compute its ETag (in bytes).
+pub const CONDITIONAL_GET_MAX_BODY_SIZE: u64 = 1024 * 1024;
+
 /// Username used for unauthenticated requests.
 pub const USERNAME_ANONYMOUS: &str = "anonymous";
 
+/// Name of the file used to store the personal access tokens of a user (in the user directory).
+pub const ACCESS_TOKENS_FILENAME: &str = "access_tokens.json";
+
+/// Prefix of the personal access tokens, distinguishing them from the security tokens of the user sessions.
+pub const ACCESS_TOKEN_PREFIX: &str = "sqp_";
+
+/// Name of the file used to store the master key encrypting the secrets (in the base directory).
+pub const MASTER_KEY_FILENAME: &str = "master.key";
+
+/// Prefix of the secrets encrypted with the master key (e.g. the passwords of the connections).
+pub const ENCRYPTED_SECRET_PREFIX: &str = "enc:v1:";
+
+/// Minimum number of seconds between two updates of the last time an access token has been used.
+pub const ACCESS_TOKEN_LAST_USED_PRECISION: u64 = 60;
+
+/// Maximum number of clients (addresses & usernames) for which the authentication failures are tracked.
+pub const AUTH_FAILURES_CACHE_SIZE: usize = 1000;
+
 ///
 /// Workspaces
 ///
@@ -23,6 +44,52 @@ pub const DEFAULT_WORKSPACE_NAME: &str = "My Workspace";
 /// Name of the file used to store the workspace settings.
 pub const WORKSPACE_SETTINGS_FILENAME: &str = ".workspace.json";
 
+/// Default name of the application reported to the servers when connecting (see `Connection::application_name`).
+pub const DEFAULT_APPLICATION_NAME: &str = "squill ({username})";
+
+/// Default number of rows returned when previewing a table.
+pub const PEEK_DEFAULT_ROWS: u64 = 100;
+
+/// Maximum number of rows returned when previewing a table.
+pub const PEEK_MAX_ROWS: u64 = 1000;
+
+/// Number of seconds between two checks for the transactions that have been idle for too long.
+pub const TRANSACTION_EXPIRY_CHECK_INTERVAL: u64 = 30;
+
+/// Name of the connection to the sample database created for each new user.
+pub const SAMPLE_CONNECTION_NAME: &str = "Sample Database";
+
+/// Name of the file used to store the sample database (in the data directory of the user).
+pub const SAMPLE_DATABASE_FILENAME: &str = "sample.db";
+
+/// Name of the file used to store the identifier of the sample connection (in the data directory of the user).
+pub const SAMPLE_CONNECTION_ID_FILENAME: &str = "sample.id";
+
+/// Name of the file used to store the keyboard macros of a user (in the user directory).
+pub const MACROS_FILENAME: &str = "macros.json";
+
+/// Maximum number of changes of the keyboard macros buffered for a client watching them before it must read them again.
+pub const MACROS_CHANGES_CAPACITY: usize = 64;
+
+/// Name of the file used to store the usage statistics of the connections (in the user directory).
+pub const CONNECTIONS_USAGE_FILENAME: &str = "connections_usage.json";
+
+/// Number of seconds between two writes of the usage of the connections recorded in memory into the usage files.
+pub const CONNECTIONS_USAGE_FLUSH_INTERVAL: u64 = 60;
+
+//
+// TLS
+//
+
+/// Name of the file used to store the self-signed certificate generated by the agent.
+pub const SELF_SIGNED_CERT_FILENAME: &str = "agent.crt";
+
+/// Name of the file used to store the private key of the self-signed certificate generated by the agent.
+pub const SELF_SIGNED_KEY_FILENAME: &str = "agent.key";
+
+/// Number of days the self-signed certificate generated by the agent is valid.
+pub const SELF_SIGNED_CERT_VALIDITY_DAYS: u32 = 365;
+
 //
 // Environment variables
 //
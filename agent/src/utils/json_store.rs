@@ -0,0 +1,56 @@
+use std::path::Path;
+use anyhow::{ Context, Result };
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Read a JSON file of the user data (e.g. the macros of a user).
+///
+/// If the file does not exist yet, the default value is returned (e.g. an empty map).
+pub fn read_json_file<T>(file: &Path) -> Result<T> where T: DeserializeOwned + Default {
+    if !file.exists() {
+        return Ok(T::default());
+    }
+    let content = std::fs
+        ::read_to_string(file)
+        .with_context(|| format!("Unable to read the file: {}", file.display()))?;
+    serde_json::from_str(&content).with_context(|| format!("Unable to parse the file: {}", file.display()))
+}
+
+/// Write a JSON file of the user data.
+///
+/// The content is written into a temporary file first, then renamed, so a reader never sees a partially written file.
+pub fn write_json_file<T>(file: &Path, value: &T) -> Result<()> where T: Serialize + ?Sized {
+    let temp_file = file.with_extension("tmp");
+    std::fs
+        ::write(&temp_file, serde_json::to_string_pretty(value)?)
+        .with_context(|| format!("Unable to write the file: {}", temp_file.display()))?;
+    std::fs
+        ::rename(&temp_file, file)
+        .with_context(|| format!("Unable to write the file: {}", file.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_json_file() {
+        // setup
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file = temp_dir.path().join("values.json");
+
+        // 1) a missing file is read as the default value
+        assert!(read_json_file::<BTreeMap<String, u64>>(&file).unwrap().is_empty());
+
+        // 2) write & read
+        let values = BTreeMap::from([("a".to_string(), 1_u64), ("b".to_string(), 2_u64)]);
+        write_json_file(&file, &values).unwrap();
+        assert_eq!(read_json_file::<BTreeMap<String, u64>>(&file).unwrap(), values);
+        assert!(!file.with_extension("tmp").exists());
+
+        // 3) invalid content
+        std::fs::write(&file, "not json").unwrap();
+        assert!(read_json_file::<BTreeMap<String, u64>>(&file).is_err());
+    }
+}
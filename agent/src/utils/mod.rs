@@ -2,6 +2,8 @@ pub mod macros;
 pub mod validators;
 pub mod constants;
 pub mod user_error;
+pub mod json_store;
+pub mod time;
 
 #[cfg(test)]
 pub mod tests;
@@ -69,6 +69,11 @@ pub mod settings {
     settings_setters!(set_log_dir, log_dir: String);
     settings_setters!(set_log_level, log_level: crate::models::agent::LogLevel);
     settings_setters!(set_log_collector, log_collector: bool);
+    settings_setters!(set_catalog_strict_names, catalog_strict_names: bool);
+    settings_setters!(set_production_write_confirmation, production_write_confirmation: bool);
+    settings_setters!(set_auth_max_failures, auth_max_failures: u32);
+    settings_setters!(set_tls_cert_file, tls_cert_file: String);
+    settings_setters!(set_tls_key_file, tls_key_file: String);
 
     pub fn set_app_dir(new_app_dir: &Path) {
         common::set_app_dir(new_app_dir);
@@ -85,6 +90,7 @@ pub mod settings {
     settings_getters! {
         get_api_key, api_key: String,
         get_port, port: u16,
+        get_auth_lockout_duration, auth_lockout_duration: std::time::Duration,
     }
 
     /// Get the directory used to store the files for the specified user.
@@ -0,0 +1,6 @@
+use anyhow::Result;
+
+/// Get the current time in seconds since the UNIX epoch.
+pub fn now() -> Result<u64> {
+    Ok(std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_secs())
+}
@@ -3,6 +3,8 @@ use std::fmt;
 #[derive(Debug, Clone)]
 pub enum UserError {
     Conflict(String),
+    /// The request must be confirmed by the user (e.g. a write statement on a production connection).
+    ConfirmationRequired(String),
     InvalidParameter(String),
     NotFound(String),
 }
@@ -13,6 +15,7 @@ impl fmt::Display for UserError {
             UserError::NotFound(message) => write!(f, "Not Found: {}", message),
             UserError::InvalidParameter(message) => write!(f, "Invalid Parameter: {}", message),
             UserError::Conflict(message) => write!(f, "Conflict: {}", message),
+            UserError::ConfirmationRequired(message) => write!(f, "Confirmation Required: {}", message),
         }
     }
 }
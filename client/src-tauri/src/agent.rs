@@ -3,13 +3,20 @@ use lazy_static::lazy_static;
 use anyhow::Result;
 use notify::{ Config, Event, RecommendedWatcher, RecursiveMode, Watcher };
 use futures::channel::mpsc::channel;
+use tauri::http::{ Request, Response, ResponseBuilder };
 use tauri::Window;
 use tracing::{ error, info, warn };
 use common::get_app_dir;
-use common::pid_file::{ get_agent_status, get_pid_file_path, load_pid_file, AgentStatus, PidFile };
+use common::constants::AGENT_API_KEY_ENV;
+use common::pid_file::{ get_agent_status, get_agent_url, get_pid_file_path, load_pid_file, AgentStatus, PidFile };
+use common::tls::send_pinned_request;
 use futures::{ StreamExt, SinkExt };
+use crate::keychain::{ self, AGENT_API_KEY_SECRET };
 use crate::models::agent::AgentEndpoint;
 
+/// The URI scheme used by the web ui to reach an agent serving the API over HTTPS.
+pub const AGENT_PROTOCOL: &str = "agent";
+
 /*
  * The latest content of the agent.pid file.
  * If there is no agent running, this value is None.
@@ -136,11 +143,37 @@ impl AgentWatcher {
      * Load the agent.pid file and update the AGENT_PID_FILE singleton.
      */
     fn load_pid_file() {
-        let pid_file = load_pid_file(&get_app_dir());
+        let pid_file = load_pid_file(&get_app_dir()).map(Self::pair_api_key);
         let mut agent_pid_file = AGENT_PID_FILE.lock().unwrap();
         *agent_pid_file = pid_file;
     }
 
+    /**
+     * Pair the API key of the agent with the desktop application.
+     *
+     * An agent started with the paired key does not write it in the agent.pid file, the key is then taken from the OS
+     * keychain. An API key found in plain text in the agent.pid file (agent started from the command line or by a
+     * previous version of the desktop application) is moved into the OS keychain, so the next agent started by the
+     * desktop application will be paired with it.
+     */
+    fn pair_api_key(mut pid_file: PidFile) -> PidFile {
+        let paired_api_key = keychain::get_secret(AGENT_API_KEY_SECRET).unwrap_or_else(|err| {
+            error!("Unable to get the API key of the agent from the keychain: {}", err);
+            None
+        });
+        if pid_file.api_key.is_empty() {
+            match paired_api_key {
+                Some(api_key) => pid_file.api_key = api_key,
+                None => warn!("The agent.pid file has no API key and no key is paired with the agent."),
+            }
+        } else if paired_api_key.as_ref() != Some(&pid_file.api_key) {
+            if let Err(err) = keychain::set_secret(AGENT_API_KEY_SECRET, &pid_file.api_key) {
+                error!("Unable to store the API key of the agent in the keychain: {}", err);
+            }
+        }
+        pid_file
+    }
+
     /**
      * Emit an event to the web ui notifying the agent endpoint has changed.
      */
@@ -167,7 +200,19 @@ impl AgentWatcher {
             error!("Agent executable not found: {:?}", agent_exe);
         } else {
             let agent_exe = agent_exe.to_str().unwrap();
-            match std::process::Command::new(agent_exe).arg("start").spawn() {
+            let mut command = std::process::Command::new(agent_exe);
+            command.arg("start");
+            match keychain::get_secret(AGENT_API_KEY_SECRET) {
+                Ok(Some(api_key)) => {
+                    // The paired key is given to the agent so it is not written in the agent.pid file.
+                    command.env(AGENT_API_KEY_ENV, api_key);
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    error!("Unable to get the API key of the agent from the keychain: {}", err);
+                }
+            }
+            match command.spawn() {
                 Ok(child) => {
                     info!("Agent started (pid={})", child.id());
                 }
@@ -179,8 +224,70 @@ impl AgentWatcher {
     }
 }
 
+/**
+ * Get the base URL of the agent protocol as seen by the web ui.
+ *
+ * On Windows, the custom protocols are exposed by the webview as `https://<protocol>.localhost`.
+ */
+fn get_agent_protocol_url() -> String {
+    #[cfg(target_os = "windows")]
+    {
+        format!("https://{}.localhost", AGENT_PROTOCOL)
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        format!("{}://localhost", AGENT_PROTOCOL)
+    }
+}
+
+/**
+ * Forward a request of the web ui to an agent serving the API over HTTPS.
+ *
+ * The webview cannot be told to trust the self-signed certificate of the agent, so the requests are sent through the
+ * agent protocol instead and the certificate is pinned against the fingerprint of the agent.pid file.
+ */
+pub fn handle_agent_protocol(request: &Request) -> Result<Response, Box<dyn std::error::Error>> {
+    let Some(pid_file) = AgentWatcher::get_pid_file() else {
+        return ResponseBuilder::new().status(503).body(Vec::new());
+    };
+    let uri = request.uri();
+    let path = uri
+        .split_once("://")
+        .and_then(|(_, rest)| rest.find('/').map(|index| &rest[index..]))
+        .unwrap_or("/");
+    let headers: Vec<(String, String)> = request
+        .headers()
+        .iter()
+        .filter(|(name, _)| !name.as_str().eq_ignore_ascii_case("host"))
+        .filter_map(|(name, value)| value.to_str().ok().map(|value| (name.to_string(), value.to_string())))
+        .collect();
+    match send_pinned_request(&pid_file, request.method().as_str(), path, &headers, request.body()) {
+        Ok(agent_response) => {
+            let mut response = ResponseBuilder::new().status(agent_response.status);
+            for (name, value) in agent_response.headers.iter().filter(|(name, _)| is_forwarded_header(name)) {
+                response = response.header(name.as_str(), value.as_str());
+            }
+            response.body(agent_response.body)
+        }
+        Err(err) => {
+            error!("Unable to forward the request to the agent: {}", err);
+            ResponseBuilder::new().status(502).body(err.to_string().into_bytes())
+        }
+    }
+}
+
+/**
+ * Check if a header of the response of the agent should be forwarded to the web ui.
+ *
+ * The body is decoded by `send_pinned_request`, so the headers describing the transfer are not relevant anymore.
+ */
+fn is_forwarded_header(name: &str) -> bool {
+    !["content-length", "transfer-encoding", "connection"].iter().any(|header| name.eq_ignore_ascii_case(header))
+}
+
 impl From<PidFile> for AgentEndpoint {
     fn from(pid_file: PidFile) -> Self {
-        Self { url: format!("http://{}:{}", pid_file.address, pid_file.port), api_key: pid_file.api_key }
+        let url = if pid_file.tls_fingerprint.is_some() { get_agent_protocol_url() } else { get_agent_url(&pid_file) };
+        Self { url, api_key: pid_file.api_key, tls_fingerprint: pid_file.tls_fingerprint }
     }
 }
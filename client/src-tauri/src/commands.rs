@@ -1,4 +1,4 @@
-use crate::{ agent::AgentWatcher, models::agent::AgentEndpoint };
+use crate::{ agent::AgentWatcher, keychain, models::agent::AgentEndpoint };
 
 /// Show/Hide the developer tools
 ///
@@ -19,9 +19,33 @@ pub fn get_agent_endpoint() -> Option<AgentEndpoint> {
     agent_endpoint
 }
 
+/// Store a secret (API key, refresh token...) in the OS keychain.
+#[tauri::command]
+pub fn set_secret(name: String, value: String) -> Result<(), String> {
+    keychain::set_secret(&name, &value).map_err(|err| err.to_string())
+}
+
+/// Get a secret from the OS keychain (`None` if there is no such secret).
+#[tauri::command]
+pub fn get_secret(name: String) -> Result<Option<String>, String> {
+    keychain::get_secret(&name).map_err(|err| err.to_string())
+}
+
+/// Delete a secret from the OS keychain.
+#[tauri::command]
+pub fn delete_secret(name: String) -> Result<(), String> {
+    keychain::delete_secret(&name).map_err(|err| err.to_string())
+}
+
 #[macro_export]
 macro_rules! generate_commands_handler {
     () => {
-        tauri::generate_handler![commands::toggle_devtools, commands::get_agent_endpoint]
+        tauri::generate_handler![
+            commands::toggle_devtools,
+            commands::get_agent_endpoint,
+            commands::set_secret,
+            commands::get_secret,
+            commands::delete_secret
+        ]
     };
 }
@@ -0,0 +1,37 @@
+use anyhow::Result;
+use keyring::Entry;
+
+/// The name of the service used to store the secrets in the OS keychain.
+const KEYCHAIN_SERVICE: &str = "squill";
+
+/// The name of the secret holding the API key paired with the agent started by the desktop application.
+pub const AGENT_API_KEY_SECRET: &str = "agent_api_key";
+
+/// Store a secret in the OS keychain (Keychain on macOS, Credential Manager on Windows, Secret Service on Linux).
+///
+/// If a secret with the same name already exists, it is replaced.
+pub fn set_secret(name: &str, value: &str) -> Result<()> {
+    Entry::new(KEYCHAIN_SERVICE, name)?.set_password(value)?;
+    Ok(())
+}
+
+/// Get a secret from the OS keychain.
+///
+/// Returns `None` if there is no secret with the given name.
+pub fn get_secret(name: &str) -> Result<Option<String>> {
+    match Entry::new(KEYCHAIN_SERVICE, name)?.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Delete a secret from the OS keychain.
+///
+/// Deleting a secret that does not exist is not an error.
+pub fn delete_secret(name: &str) -> Result<()> {
+    match Entry::new(KEYCHAIN_SERVICE, name)?.delete_password() {
+        Ok(_) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(err) => Err(err.into()),
+    }
+}
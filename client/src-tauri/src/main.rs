@@ -2,13 +2,14 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use std::sync::Mutex;
-use agent::AgentWatcher;
+use agent::{ handle_agent_protocol, AgentWatcher, AGENT_PROTOCOL };
 use tauri::App;
 use tauri::Manager;
 use tracing::trace;
 
 mod commands;
 mod agent;
+mod keychain;
 mod models;
 
 fn setup(app: &App) {
@@ -36,6 +37,7 @@ fn main() {
             agent_watcher: Mutex::new(AgentWatcher::new()),
         })
         .invoke_handler(generate_commands_handler!())
+        .register_uri_scheme_protocol(AGENT_PROTOCOL, |_app, request| handle_agent_protocol(request))
         .setup(|app| {
             setup(app);
             Ok(())
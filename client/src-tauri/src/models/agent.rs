@@ -7,4 +7,9 @@ pub struct AgentEndpoint {
 
     /// The API key used to authenticate the client applications.
     pub api_key: String,
+
+    /// The SHA-256 fingerprint of the certificate of the agent when the API is served over HTTPS.
+    ///
+    /// In such case, the URL is using the agent protocol which pins the certificate of the agent to this fingerprint.
+    pub tls_fingerprint: Option<String>,
 }
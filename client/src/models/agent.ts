@@ -9,6 +9,12 @@ export class AgentEndpoint {
   @serializable("string", { snakeCase: "property" })
   apiKey: string;
 
+  /// The SHA-256 fingerprint of the certificate of the agent when the API is served over HTTPS.
+  ///
+  /// In such case, the URL is using the agent protocol of the desktop application which pins the certificate.
+  @serializable("string", { snakeCase: "property" })
+  tlsFingerprint?: string;
+
   constructor(object: Partial<AgentEndpoint>) {
     Object.assign(this, object);
   }
@@ -5,6 +5,64 @@ import { Datasource as Datasources } from "@/models/datasources";
 const CONNECTION_MODE = ["host", "socket", "connection_string", "file"] as const;
 export type ConnectionMode = (typeof CONNECTION_MODE)[number];
 
+const CONNECTION_STAGE = ["production", "staging", "development"] as const;
+export type ConnectionStage = (typeof CONNECTION_STAGE)[number];
+
+/**
+ * Safety labels of a connection, helping users to notice they are working on a sensitive server.
+ */
+export class ConnectionLabels {
+  [immerable] = true;
+
+  /**
+   * The stage of the server.
+   */
+  @serializable("string", { format: formatRegExp(CONNECTION_STAGE) })
+  stage?: ConnectionStage;
+
+  /**
+   * A color used to highlight the connection (e.g. "#d32f2f").
+   */
+  @serializable("string")
+  color?: string;
+
+  /**
+   * The text of a warning banner displayed when using the connection.
+   */
+  @serializable("string")
+  banner?: string;
+
+  constructor(object?: Partial<ConnectionLabels>) {
+    Object.assign(this, object ?? {});
+  }
+}
+
+/**
+ * A SOCKS5 proxy used to reach the server of a connection (e.g. a bastion host).
+ */
+export class ConnectionProxy {
+  [immerable] = true;
+
+  @serializable("string", { required: true })
+  host!: string;
+
+  @serializable("integer", { required: true })
+  port!: number;
+
+  /**
+   * The username used to authenticate to the proxy (no authentication if empty).
+   */
+  @serializable("string")
+  username?: string;
+
+  @serializable("string")
+  password?: string;
+
+  constructor(object?: Partial<ConnectionProxy>) {
+    Object.assign(this, object ?? {});
+  }
+}
+
 export class Connection {
   [immerable] = true;
 
@@ -65,6 +123,18 @@ export class Connection {
   @serializable("object")
   options?: Record<string, string>;
 
+  /**
+   * Safety labels displayed when the connection is used.
+   */
+  @serializable("object", { factory: ConnectionLabels })
+  labels?: ConnectionLabels;
+
+  /**
+   * The SOCKS5 proxy used to reach the server.
+   */
+  @serializable("object", { factory: ConnectionProxy })
+  proxy?: ConnectionProxy;
+
   constructor(object: Partial<Connection>) {
     Object.assign(this, object);
   }
@@ -34,6 +34,9 @@ export class Driver {
   @serializable("array", { items: { type: "string" } })
   readonly capabilities!: Capability[];
 
+  @serializable("array", { items: { type: "string" }, snakeCase: "property" })
+  readonly uriSchemes: string[] = [];
+
   @serializable("object", { deserializer: defaultsDeserializer })
   readonly defaults: Record<string, string>;
 }
@@ -33,4 +33,33 @@ export class Tauri {
       callback(deserialize<AgentEndpoint>(event.payload, AgentEndpoint));
     });
   }
+
+  /**
+   * Store a secret in the OS keychain.
+   *
+   * @param name The name of the secret.
+   * @param value The value of the secret (replaces the previous value if any).
+   */
+  static async setSecret(name: string, value: string): Promise<void> {
+    return invoke<void>("set_secret", { name, value });
+  }
+
+  /**
+   * Get a secret from the OS keychain.
+   *
+   * @param name The name of the secret.
+   * @returns The value of the secret or `null` if there is no such secret.
+   */
+  static async getSecret(name: string): Promise<string | null> {
+    return invoke<string | null>("get_secret", { name });
+  }
+
+  /**
+   * Delete a secret from the OS keychain.
+   *
+   * @param name The name of the secret.
+   */
+  static async deleteSecret(name: string): Promise<void> {
+    return invoke<void>("delete_secret", { name });
+  }
 }
@@ -1,3 +1,11 @@
 // HTTP Headers
 pub const X_API_KEY_HEADER: &str = "X-Api-Key";
 pub const X_REQUEST_ID_HEADER: &str = "X-Request-ID";
+pub const X_REQUEST_TIMEOUT_HEADER: &str = "X-Request-Timeout";
+
+// Environment variables
+
+/// The API key expected by the agent, set by the desktop application when starting an agent paired with it.
+///
+/// The key is then not written in the pid file, the desktop application keeps it in the OS keychain.
+pub const AGENT_API_KEY_ENV: &str = "SQUILL_AGENT_API_KEY";
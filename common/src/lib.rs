@@ -8,6 +8,7 @@ use lazy_static::lazy_static;
 
 pub mod constants;
 pub mod pid_file;
+pub mod tls;
 
 /// Name of the environment variable used to specify the app directory.
 pub const ENV_VAR_APP_DIR: &str = "SQUILL_APP_DIR";
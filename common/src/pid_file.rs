@@ -5,6 +5,7 @@ use tracing::{ debug, trace, warn };
 use sysinfo::{ Pid, ProcessRefreshKind, RefreshKind, System };
 
 use crate::constants::X_API_KEY_HEADER;
+use crate::tls::send_pinned_request;
 
 pub const PID_FILENAME: &str = "agent.pid";
 
@@ -13,7 +14,17 @@ pub struct PidFile {
     pub pid: u32,
     pub address: String,
     pub port: u16,
+
+    /// The API key of the agent.
+    ///
+    /// Empty when the agent has been started by the desktop application with a paired key (see `AGENT_API_KEY_ENV`),
+    /// the key is then only known by the desktop application.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub api_key: String,
+
+    /// The SHA-256 fingerprint of the certificate when the agent is serving the API over HTTPS.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_fingerprint: Option<String>,
 }
 
 pub fn get_pid_file_path(dir: &Path) -> PathBuf {
@@ -50,13 +61,19 @@ pub fn delete_pid_file(dir: &Path) -> Result<()> {
     Ok(())
 }
 
-pub fn save_pid_file(dir: &Path, local_addr: &std::net::SocketAddr, api_key: &str) -> Result<()> {
+pub fn save_pid_file(
+    dir: &Path,
+    local_addr: &std::net::SocketAddr,
+    api_key: Option<&str>,
+    tls_fingerprint: Option<&str>
+) -> Result<()> {
     // save the port and the current pid to a file
     let content = PidFile {
         pid: std::process::id(),
         address: local_addr.ip().to_string(),
         port: local_addr.port(),
-        api_key: api_key.to_string(),
+        api_key: api_key.unwrap_or_default().to_string(),
+        tls_fingerprint: tls_fingerprint.map(|fingerprint| fingerprint.to_string()),
     };
     let file_path = get_pid_file_path(dir);
     let mut file = std::fs::File::create(&file_path)?;
@@ -69,7 +86,8 @@ pub fn save_pid_file(dir: &Path, local_addr: &std::net::SocketAddr, api_key: &st
 
 /// Get the URL of the agent described by the pid file.
 pub fn get_agent_url(pid_file: &PidFile) -> String {
-    format!("http://{}:{}", pid_file.address, pid_file.port)
+    let scheme = if pid_file.tls_fingerprint.is_some() { "https" } else { "http" };
+    format!("{}://{}:{}", scheme, pid_file.address, pid_file.port)
 }
 
 /// The running status of the agent described by the pid file.
@@ -89,6 +107,9 @@ pub enum AgentStatus {
 
 /// Check if the agent described by the pid file is running.
 ///
+/// If the pid file does not have the API key (paired agent), the request is rejected by the agent with a 403 Forbidden
+/// which is enough to know that the agent is responding (the loopback addresses are never locked out by the agent).
+///
 /// This function will return an error if the server is already running.
 /// In order to check if the server is running, this function will check if there is a pid file present on the file
 /// system and if so will use it to try to connect the exiting running agent. If there is an agent running and
@@ -103,15 +124,24 @@ pub async fn get_agent_status(pid_file: &PidFile) -> AgentStatus {
     }
 
     // Check of the server is responding to an API request
-    let http_client = reqwest::Client::new();
+    // When served over HTTPS, the certificate of the agent is pinned against the fingerprint of the pid file.
+    if pid_file.tls_fingerprint.is_some() {
+        let headers = [(X_API_KEY_HEADER.to_string(), pid_file.api_key.clone())];
+        return match send_pinned_request(pid_file, "GET", "/api/v1/agent", &headers, &[]) {
+            Ok(response) if is_responding(pid_file, response.status) => AgentStatus::Running(pid_file.pid),
+            Ok(response) => AgentStatus::NotResponding(pid_file.pid, response.status.to_string()),
+            Err(err) => AgentStatus::NotResponding(pid_file.pid, err.to_string()),
+        };
+    }
     match
-        http_client
+        reqwest::Client
+            ::new()
             .get(format!("{}/api/v1/agent", get_agent_url(pid_file)))
             .header(X_API_KEY_HEADER, &pid_file.api_key)
             .send().await
     {
         Ok(response) => {
-            if response.status().is_success() {
+            if is_responding(pid_file, response.status().as_u16()) {
                 AgentStatus::Running(pid_file.pid)
             } else {
                 AgentStatus::NotResponding(pid_file.pid, response.status().to_string())
@@ -121,6 +151,11 @@ pub async fn get_agent_status(pid_file: &PidFile) -> AgentStatus {
     }
 }
 
+/// Check if the status of the response to the health check request means that the agent is responding.
+fn is_responding(pid_file: &PidFile, status: u16) -> bool {
+    (200..300).contains(&status) || (pid_file.api_key.is_empty() && status == 403)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -131,7 +166,7 @@ mod tests {
         let app_dir = tempfile::tempdir().unwrap();
         let local_addr = std::net::SocketAddr::new("127.0.0.1".parse().unwrap(), 1234);
         let api_key = "cf55f65...";
-        save_pid_file(app_dir.path(), &local_addr, api_key).unwrap();
+        save_pid_file(app_dir.path(), &local_addr, Some(api_key), None).unwrap();
         assert!(app_dir.path().join(PID_FILENAME).exists());
 
         // 2) Load the pid file just created
@@ -139,11 +174,27 @@ mod tests {
         assert_eq!(pid_file.pid, std::process::id());
         assert_eq!(pid_file.port, 1234);
         assert_eq!(pid_file.api_key, api_key);
+        assert_eq!(get_agent_url(&pid_file), "http://127.0.0.1:1234");
+
+        // 3) Save the pid file of an agent serving the API over HTTPS
+        save_pid_file(app_dir.path(), &local_addr, Some(api_key), Some("AB:CD")).unwrap();
+        let pid_file = load_pid_file(app_dir.path()).unwrap();
+        assert_eq!(pid_file.tls_fingerprint, Some("AB:CD".to_string()));
+        assert_eq!(get_agent_url(&pid_file), "https://127.0.0.1:1234");
+
+        // 4) Save the pid file of an agent paired with the desktop application (no API key)
+        save_pid_file(app_dir.path(), &local_addr, None, None).unwrap();
+        assert!(!std::fs::read_to_string(app_dir.path().join(PID_FILENAME)).unwrap().contains("api_key"));
+        let pid_file = load_pid_file(app_dir.path()).unwrap();
+        assert!(pid_file.api_key.is_empty());
+        assert!(is_responding(&pid_file, 403)); // the status returned by the agent for a missing API key
+        assert!(!is_responding(&pid_file, 500));
+        assert!(!is_responding(&PidFile { api_key: api_key.to_string(), ..pid_file }, 403));
 
-        // 3) Delete the pid file
+        // 5) Delete the pid file
         assert!(delete_pid_file(app_dir.path()).is_ok());
 
-        // 4) Try to delete a PID file that does not exists
+        // 6) Try to delete a PID file that does not exists
         let another_dir = tempfile::tempdir().unwrap();
         assert!(delete_pid_file(another_dir.path()).is_ok());
     }
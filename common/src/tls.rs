@@ -0,0 +1,249 @@
+use std::io::{ Read, Write };
+use std::net::{ TcpStream, ToSocketAddrs };
+use std::time::Duration;
+use anyhow::{ anyhow, Context, Result };
+use openssl::hash::MessageDigest;
+use openssl::ssl::{ SslConnector, SslMethod, SslVerifyMode };
+use openssl::x509::X509Ref;
+
+use crate::pid_file::PidFile;
+
+/// The timeout used to connect, read from and write to the agent.
+const AGENT_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Get the SHA-256 fingerprint of a certificate formatted as colon separated hexadecimal bytes (e.g. "AB:CD:...").
+pub fn get_certificate_fingerprint(cert: &X509Ref) -> Result<String> {
+    let digest = cert.digest(MessageDigest::sha256())?;
+    Ok(
+        digest
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect::<Vec<_>>()
+            .join(":")
+    )
+}
+
+/// A response received from the agent by `send_pinned_request`.
+pub struct AgentResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+/// Send a request to an agent serving the API over HTTPS.
+///
+/// The agent is usually using a self-signed certificate that cannot be verified against the trust store, so instead
+/// the certificate presented by the server must match the fingerprint of the pid file. The request (and the API key it
+/// carries) is never sent if the certificate does not match.
+pub fn send_pinned_request(
+    pid_file: &PidFile,
+    method: &str,
+    path: &str,
+    headers: &[(String, String)],
+    body: &[u8]
+) -> Result<AgentResponse> {
+    let Some(fingerprint) = &pid_file.tls_fingerprint else {
+        return Err(anyhow!("The agent is not serving the API over HTTPS."));
+    };
+    let address = (pid_file.address.as_str(), pid_file.port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| anyhow!("Invalid agent address: {}", pid_file.address))?;
+    let stream = TcpStream::connect_timeout(&address, AGENT_REQUEST_TIMEOUT).with_context(||
+        format!("Unable to connect to the agent: {}", address)
+    )?;
+    stream.set_read_timeout(Some(AGENT_REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(AGENT_REQUEST_TIMEOUT))?;
+
+    // The chain of trust is not verified, the certificate is pinned instead.
+    let mut connector = SslConnector::builder(SslMethod::tls_client())?;
+    connector.set_verify(SslVerifyMode::NONE);
+    let mut stream = connector
+        .build()
+        .configure()?
+        .verify_hostname(false)
+        .connect(&pid_file.address, stream)
+        .map_err(|e| anyhow!("TLS handshake with the agent failed: {}", e))?;
+    let cert = stream.ssl().peer_certificate().ok_or_else(|| anyhow!("The agent did not present a certificate."))?;
+    if !get_certificate_fingerprint(&cert)?.eq_ignore_ascii_case(fingerprint) {
+        return Err(anyhow!("The certificate of the agent does not match the fingerprint of the pid file."));
+    }
+
+    let mut request = format!("{} {} HTTP/1.1\r\nHost: {}:{}\r\n", method, path, pid_file.address, pid_file.port);
+    for (name, value) in headers {
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    request.push_str(&format!("Content-Length: {}\r\nConnection: close\r\n\r\n", body.len()));
+    stream.write_all(request.as_bytes())?;
+    stream.write_all(body)?;
+    stream.flush()?;
+
+    // The connection is closed by the agent once the response is sent, but not always with a TLS close notify.
+    let mut response = Vec::new();
+    if let Err(e) = stream.read_to_end(&mut response) {
+        if response.is_empty() {
+            return Err(e.into());
+        }
+    }
+    parse_response(&response)
+}
+
+/// Parse a HTTP/1.1 response.
+fn parse_response(response: &[u8]) -> Result<AgentResponse> {
+    let invalid_response = || anyhow!("Invalid response from the agent.");
+    let header_end = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or_else(invalid_response)?;
+    let head = std::str::from_utf8(&response[..header_end]).map_err(|_| invalid_response())?;
+    let mut lines = head.split("\r\n");
+    let status = lines
+        .next()
+        .and_then(|status_line| status_line.split(' ').nth(1))
+        .and_then(|status| status.parse::<u16>().ok())
+        .ok_or_else(invalid_response)?;
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect();
+    let get_header = |name: &str| {
+        headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    };
+
+    let mut body = &response[header_end + 4..];
+    let body = if get_header("Transfer-Encoding").is_some_and(|value| value.eq_ignore_ascii_case("chunked")) {
+        let mut decoded = Vec::new();
+        loop {
+            let size_end = body
+                .windows(2)
+                .position(|window| window == b"\r\n")
+                .ok_or_else(invalid_response)?;
+            let size = std::str
+                ::from_utf8(&body[..size_end])
+                .ok()
+                .and_then(|size| usize::from_str_radix(size.split(';').next().unwrap_or("").trim(), 16).ok())
+                .ok_or_else(invalid_response)?;
+            if size == 0 {
+                break;
+            }
+            let chunk = body.get(size_end + 2..size_end + 2 + size).ok_or_else(invalid_response)?;
+            decoded.extend_from_slice(chunk);
+            body = body.get(size_end + 4 + size..).ok_or_else(invalid_response)?;
+        }
+        decoded
+    } else if let Some(len) = get_header("Content-Length").and_then(|len| len.parse::<usize>().ok()) {
+        body.get(..len).ok_or_else(invalid_response)?.to_vec()
+    } else {
+        body.to_vec()
+    };
+    Ok(AgentResponse { status, headers, body })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use openssl::asn1::Asn1Time;
+    use openssl::ec::{ EcGroup, EcKey };
+    use openssl::nid::Nid;
+    use openssl::pkey::{ PKey, Private };
+    use openssl::ssl::SslAcceptor;
+    use openssl::x509::{ X509, X509NameBuilder };
+
+    fn generate_certificate() -> (X509, PKey<Private>) {
+        let key = PKey::from_ec_key(
+            EcKey::generate(&EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap()).unwrap()
+        ).unwrap();
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_nid(Nid::COMMONNAME, "localhost").unwrap();
+        let name = name.build();
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(&name).unwrap();
+        builder.set_pubkey(&key).unwrap();
+        builder.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+        builder.set_not_after(&Asn1Time::days_from_now(1).unwrap()).unwrap();
+        builder.sign(&key, MessageDigest::sha256()).unwrap();
+        (builder.build(), key)
+    }
+
+    #[test]
+    fn test_send_pinned_request() {
+        // setup: a HTTPS server forwarding the requests it receives
+        let (cert, key) = generate_certificate();
+        let fingerprint = get_certificate_fingerprint(&cert).unwrap();
+        let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls_server()).unwrap();
+        acceptor.set_certificate(&cert).unwrap();
+        acceptor.set_private_key(&key).unwrap();
+        let acceptor = acceptor.build();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (tx, rx) = mpsc::channel::<String>();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(2) {
+                let Ok(mut stream) = acceptor.accept(stream.unwrap()) else {
+                    continue;
+                };
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    match stream.read(&mut buffer) {
+                        Ok(len) if len > 0 => request.extend_from_slice(&buffer[..len]),
+                        _ => break,
+                    }
+                }
+                if !request.is_empty() {
+                    stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok").unwrap();
+                    stream.shutdown().ok();
+                }
+                tx.send(String::from_utf8_lossy(&request).to_string()).unwrap();
+            }
+        });
+        let pid_file = PidFile {
+            pid: std::process::id(),
+            address: "127.0.0.1".to_string(),
+            port,
+            api_key: "secret".to_string(),
+            tls_fingerprint: Some(fingerprint.to_lowercase()),
+        };
+        let headers = [("X-Api-Key".to_string(), "secret".to_string())];
+
+        // 1) the certificate matches the fingerprint
+        let response = send_pinned_request(&pid_file, "GET", "/api/v1/agent", &headers, &[]).unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, b"ok");
+        assert!(rx.recv().unwrap().contains("X-Api-Key: secret"));
+
+        // 2) the certificate does not match the fingerprint, the request is not sent
+        let pid_file = PidFile { tls_fingerprint: Some("AB:CD".to_string()), ..pid_file };
+        assert!(send_pinned_request(&pid_file, "GET", "/api/v1/agent", &headers, &[]).is_err());
+        assert!(rx.recv().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_response() {
+        // 1) content length
+        let response = parse_response(
+            b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\ncontent-type: text/plain\r\n\r\nok"
+        ).unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, b"ok");
+        assert!(response.headers.contains(&("content-type".to_string(), "text/plain".to_string())));
+
+        // 2) chunked
+        let response = parse_response(
+            b"HTTP/1.1 404 Not Found\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nnot\r\n9\r\n found...\r\n0\r\n\r\n"
+        ).unwrap();
+        assert_eq!(response.status, 404);
+        assert_eq!(response.body, b"not found...");
+
+        // 3) invalid
+        assert!(parse_response(b"HTTP/1.1 200 OK\r\n").is_err());
+        assert!(parse_response(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nok").is_err());
+    }
+}
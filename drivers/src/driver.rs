@@ -1,23 +1,97 @@
 use std::pin::Pin;
+use std::time::Duration;
 use anyhow::Result;
 use futures::{ future::BoxFuture, Stream, TryStreamExt };
+use crate::socks::Socks5Proxy;
 use crate::value::DriverValue;
 
+/// The server supports `LIMIT n OFFSET m`.
+pub const FEATURE_LIMIT_OFFSET: &str = "limit_offset";
+
+/// The server supports `OFFSET m ROWS FETCH FIRST n ROWS ONLY`.
+pub const FEATURE_FETCH_FIRST: &str = "fetch_first";
+
+/// The server supports `INSERT/UPDATE/DELETE ... RETURNING`.
+pub const FEATURE_RETURNING: &str = "returning";
+
+/// The server returns the plan of a query as a JSON document with `EXPLAIN (FORMAT JSON)` (PostgreSQL).
+pub const FEATURE_EXPLAIN_JSON: &str = "explain_json";
+
+/// The server returns the plan of a query as one row per node with `EXPLAIN QUERY PLAN` (SQLite).
+pub const FEATURE_EXPLAIN_QUERY_PLAN: &str = "explain_query_plan";
+
+/// The server has no `information_schema`, the catalog is read from `sqlite_master` and the pragma functions.
+pub const FEATURE_PRAGMA_CATALOG: &str = "pragma_catalog";
+
+/// Information about the server a driver is connected to.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DriverInfo {
+    /// The version of the server (e.g. "16.2").
+    pub server_version: String,
+
+    /// The edition of the server (e.g. "PostgreSQL", "Redshift").
+    pub edition: String,
+
+    /// The features supported by the server (see the `FEATURE_*` constants).
+    pub features: Vec<String>,
+}
+
+/// The error returned when the connection to the server cannot be established before the connect timeout.
+#[derive(Debug)]
+pub struct ConnectTimeoutError(pub Duration);
+
+impl std::fmt::Display for ConnectTimeoutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Unable to connect to the server within {} seconds.", self.0.as_secs_f32())
+    }
+}
+
+impl std::error::Error for ConnectTimeoutError {}
+
 pub trait DriverConnection {
     /// Connect to the dataset.
     fn connect(&mut self) -> BoxFuture<'_, Result<()>>;
 
     /// Close the connection to the dataset.
     fn close(&mut self) -> BoxFuture<'_, Result<()>>;
+
+    /// Get the information about the server (version, edition & features).
+    fn get_info(&mut self) -> BoxFuture<'_, Result<DriverInfo>>;
+
+    /// Route the connection to the server through a SOCKS5 proxy (must be called before `connect`).
+    fn set_proxy(&mut self, _proxy: Socks5Proxy) -> Result<()> {
+        Err(anyhow::anyhow!("SOCKS5 proxies are not supported by this driver."))
+    }
 }
 
 pub trait DriverStream: Stream<Item = Result<DriverValue>> + std::marker::Send {
     fn affected_rows(&self) -> Option<u64>;
 }
 
+/// The parameters bound to a query.
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryParams {
+    /// Parameters referenced by their position in the query (`$1`, `$2`... for PostgreSQL, `?` or `?1`... for SQLite).
+    Positional(Vec<DriverValue>),
+
+    /// Parameters referenced by their name in the query (`:name`).
+    Named(Vec<(String, DriverValue)>),
+}
+
 pub trait DriverExecutor {
     /// Execute a query and return the stream of the result.
     fn query<'e>(&'e mut self, query: &'e str) -> BoxFuture<'e, Result<Pin<Box<dyn DriverStream + 'e>>>>;
+
+    /// Execute a query with parameters bound to its placeholders and return the stream of the result.
+    ///
+    /// Unlike `query`, the query must be a single statement.
+    fn query_with_params<'e>(
+        &'e mut self,
+        _query: &'e str,
+        _params: QueryParams
+    ) -> BoxFuture<'e, Result<Pin<Box<dyn DriverStream + 'e>>>> {
+        Box::pin(async move { Err(anyhow::anyhow!("Query parameters are not supported by this driver.")) })
+    }
 }
 
 pub async fn execute_query<'e>(executor: &'e mut dyn DriverExecutor, query: &'e str) -> Result<u64> {
@@ -35,4 +109,34 @@ pub async fn get_query<'e>(executor: &'e mut dyn DriverExecutor, query: &'e str)
     Ok(value)
 }
 
+/// Parse the leading numeric part of a version string.
+///
+/// Everything after the first character that is neither a digit nor a dot is ignored, so `"16.2 (Debian 16.2-1)"`
+/// gives `[16, 2]`. The result can be compared with another version, e.g. `parse_version(v) >= vec![3, 35]`.
+pub fn parse_version(version: &str) -> Vec<u32> {
+    version
+        .trim()
+        .split(|c: char| !c.is_ascii_digit() && c != '.')
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .filter_map(|part| part.parse::<u32>().ok())
+        .collect()
+}
+
 pub trait Driver: DriverConnection + DriverExecutor + Send {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("16.2 (Debian 16.2-1.pgdg120+2)"), vec![16, 2]);
+        assert_eq!(parse_version("3.45.1"), vec![3, 45, 1]);
+        assert_eq!(parse_version("8.0.2"), vec![8, 0, 2]);
+        assert_eq!(parse_version("unknown"), Vec::<u32>::new());
+        assert!(parse_version("3.45.1") >= vec![3, 35]);
+        assert!(parse_version("3.8.2") < vec![3, 35]);
+    }
+}
@@ -1,36 +1,91 @@
 use std::pin::Pin;
+use std::time::Duration;
 use futures::future::BoxFuture;
 use anyhow::Result;
 use crate::{
-    driver::{ Driver, DriverConnection, DriverExecutor, DriverStream },
+    driver::{ ConnectTimeoutError, Driver, DriverConnection, DriverExecutor, DriverInfo, DriverStream, QueryParams },
+    mock::MockDriver,
     postgres::PostgresDriver,
+    redshift::RedshiftDriver,
+    socks::Socks5Proxy,
     sqlite::SqliteDriver,
+    trino::TrinoDriver,
 };
 
 pub struct AnyDriver {
     driver: Box<dyn Driver>,
+    connect_timeout: Option<Duration>,
+    proxy: Option<Socks5Proxy>,
 }
 
 impl AnyDriver {
     pub fn new(driver: Box<dyn Driver>) -> Self {
-        Self { driver }
+        Self { driver, connect_timeout: None, proxy: None }
+    }
+
+    /// Set the maximum duration of `connect()`.
+    ///
+    /// If the connection is not established in time, the attempt is cancelled and `connect()` returns a
+    /// `ConnectTimeoutError`.
+    pub fn with_connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = Some(connect_timeout);
+        self
+    }
+
+    /// Route the connection to the server through a SOCKS5 proxy.
+    ///
+    /// The proxy is given to the driver when connecting, so `connect()` fails if the driver does not support proxies.
+    pub fn with_proxy(mut self, proxy: Socks5Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
     }
 }
 
 impl DriverConnection for AnyDriver {
     fn connect(&mut self) -> BoxFuture<'_, Result<()>> {
-        self.driver.connect()
+        if let Some(proxy) = self.proxy.clone() {
+            if let Err(e) = self.driver.set_proxy(proxy) {
+                return Box::pin(async move { Err(e) });
+            }
+        }
+        match self.connect_timeout {
+            None => self.driver.connect(),
+            Some(connect_timeout) =>
+                Box::pin(async move {
+                    match tokio::time::timeout(connect_timeout, self.driver.connect()).await {
+                        Ok(result) => result,
+                        // The future of the driver has been dropped, cancelling the pending connection attempt.
+                        Err(_) => Err(ConnectTimeoutError(connect_timeout).into()),
+                    }
+                }),
+        }
     }
 
     fn close(&mut self) -> BoxFuture<'_, Result<()>> {
         self.driver.close()
     }
+
+    fn get_info(&mut self) -> BoxFuture<'_, Result<DriverInfo>> {
+        self.driver.get_info()
+    }
+
+    fn set_proxy(&mut self, proxy: Socks5Proxy) -> Result<()> {
+        self.driver.set_proxy(proxy)
+    }
 }
 
 impl DriverExecutor for AnyDriver {
     fn query<'e>(&'e mut self, _query: &'e str) -> BoxFuture<'e, Result<Pin<Box<dyn DriverStream + 'e>>>> {
         self.driver.query(_query)
     }
+
+    fn query_with_params<'e>(
+        &'e mut self,
+        query: &'e str,
+        params: QueryParams
+    ) -> BoxFuture<'e, Result<Pin<Box<dyn DriverStream + 'e>>>> {
+        self.driver.query_with_params(query, params)
+    }
 }
 
 pub struct DriverFactory;
@@ -40,6 +95,9 @@ impl DriverFactory {
         match driver {
             "sqlite" => Ok(Box::new(SqliteDriver::new(connection_string))),
             "postgresql" => Ok(Box::new(PostgresDriver::new(connection_string))),
+            "redshift" => Ok(Box::new(RedshiftDriver::new(connection_string))),
+            "trino" => Ok(Box::new(TrinoDriver::new(connection_string))),
+            "mock" => Ok(Box::new(MockDriver::new(connection_string))),
             _ => Err(anyhow::format_err!("Unsupported driver: {}", driver)),
         }
     }
@@ -64,4 +122,32 @@ mod tests {
         drop(stream);
         assert!(driver.close().await.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_connect_timeout() {
+        // setup: a local server accepting the connections but never answering, so the connection attempt hangs until
+        // the timeout (whatever the network the tests are running on).
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                sockets.push(socket);
+            }
+        });
+
+        let connection_string = format!("host=127.0.0.1 port={} user=postgres connect_timeout=60", port);
+        let mut driver = AnyDriver::new(DriverFactory::create("postgresql", connection_string).unwrap())
+            .with_connect_timeout(Duration::from_millis(100));
+        let result = driver.connect().await;
+        assert!(result.unwrap_err().downcast_ref::<ConnectTimeoutError>().is_some());
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_proxy_not_supported() {
+        let mut driver = AnyDriver::new(DriverFactory::create("sqlite", "sqlite::memory:".to_string()).unwrap())
+            .with_proxy(Socks5Proxy { host: "localhost".to_string(), port: 1080, ..Default::default() });
+        assert!(driver.connect().await.is_err());
+    }
 }
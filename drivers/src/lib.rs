@@ -1,6 +1,13 @@
 pub mod driver;
 pub mod value;
 pub mod postgres;
+pub mod redshift;
 pub mod sqlite;
+pub mod trino;
+pub mod mock;
 pub mod factory;
 pub mod pool;
+pub mod redact;
+pub mod sql;
+pub mod plan;
+pub mod socks;
@@ -0,0 +1,302 @@
+use std::pin::Pin;
+use std::time::Duration;
+use anyhow::{ anyhow, Result };
+use futures::{ future::BoxFuture, Stream };
+use crate::{
+    driver::{ Driver, DriverConnection, DriverExecutor, DriverInfo, DriverStream, QueryParams },
+    value::DriverValue,
+};
+
+/// The edition reported by `DriverInfo` when connected to the mock driver.
+const MOCK_EDITION: &str = "Mock";
+
+/// The number of rows returned by a query if not specified by the connection string.
+const DEFAULT_ROWS: u64 = 100;
+
+/// The number of columns returned by a query if not specified by the connection string.
+const DEFAULT_COLUMNS: usize = 4;
+
+/// The step where a failure is injected (see the option `fail` of the connection string).
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Failure {
+    Connect,
+    Query,
+}
+
+/// The parameters of the mock driver, parsed from the connection string.
+#[derive(Clone, Debug, PartialEq)]
+struct MockConfig {
+    rows: u64,
+    columns: usize,
+    latency: Duration,
+    fail: Option<Failure>,
+    fail_after: Option<u64>,
+}
+
+impl Default for MockConfig {
+    fn default() -> Self {
+        Self {
+            rows: DEFAULT_ROWS,
+            columns: DEFAULT_COLUMNS,
+            latency: Duration::ZERO,
+            fail: None,
+            fail_after: None,
+        }
+    }
+}
+
+impl MockConfig {
+    /// Parse a connection string.
+    ///
+    /// The connection string is `mock://[?option=value&...]` where the options are:
+    /// - `rows`: the number of rows returned by each query (default: 100).
+    /// - `columns`: the number of columns of the rows (default: 4).
+    /// - `latency`: a delay in milliseconds applied when connecting and before each query (default: 0).
+    /// - `fail`: `connect` or `query` to make the connection or every query fail.
+    /// - `fail_after`: the number of rows returned before the stream of a query fails.
+    fn parse(connection_string: &str) -> Result<Self> {
+        let options = connection_string
+            .strip_prefix("mock:")
+            .ok_or_else(|| anyhow!("Invalid connection string, expecting 'mock://[?option=value&...]'."))?;
+        let mut config = MockConfig::default();
+        let Some((_, options)) = options.split_once('?') else {
+            return Ok(config);
+        };
+        for option in options.split('&').filter(|option| !option.is_empty()) {
+            let (key, value) = option.split_once('=').unwrap_or((option, ""));
+            let invalid = || anyhow!("Invalid value for the option '{}': '{}'", key, value);
+            match key {
+                "rows" => {
+                    config.rows = value.parse().map_err(|_| invalid())?;
+                }
+                "columns" => {
+                    config.columns = value.parse().map_err(|_| invalid())?;
+                }
+                "latency" => {
+                    config.latency = Duration::from_millis(value.parse().map_err(|_| invalid())?);
+                }
+                "fail" => {
+                    config.fail = match value {
+                        "connect" => Some(Failure::Connect),
+                        "query" => Some(Failure::Query),
+                        _ => {
+                            return Err(invalid());
+                        }
+                    };
+                }
+                "fail_after" => {
+                    config.fail_after = Some(value.parse().map_err(|_| invalid())?);
+                }
+                _ => {
+                    return Err(anyhow!("Unknown option '{}'", key));
+                }
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// A driver returning a deterministic dataset without connecting to any server.
+///
+/// The driver is meant for demos, the development of the clients and integration tests. Every query is returning the
+/// same rows (see `get_value`), except the ones starting with `INSERT`, `UPDATE` or `DELETE` returning the number of
+/// rows as the count of affected rows.
+pub struct MockDriver {
+    connection_string: String,
+    config: Option<MockConfig>,
+}
+
+impl MockDriver {
+    pub fn new(connection_string: String) -> Self {
+        Self { connection_string, config: None }
+    }
+}
+
+impl DriverConnection for MockDriver {
+    fn connect(&mut self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            let config = MockConfig::parse(&self.connection_string)?;
+            tokio::time::sleep(config.latency).await;
+            if config.fail == Some(Failure::Connect) {
+                return Err(anyhow!("Connection refused (injected failure)."));
+            }
+            self.config = Some(config);
+            Ok(())
+        })
+    }
+
+    fn close(&mut self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            self.config = None;
+            Ok(())
+        })
+    }
+
+    fn get_info(&mut self) -> BoxFuture<'_, Result<DriverInfo>> {
+        Box::pin(async move {
+            self.config.as_ref().ok_or_else(|| anyhow!("Not connected"))?;
+            // The mock driver is ignoring the clauses of the queries, so no feature can be reported.
+            Ok(DriverInfo {
+                server_version: env!("CARGO_PKG_VERSION").to_string(),
+                edition: MOCK_EDITION.to_string(),
+                features: vec![],
+            })
+        })
+    }
+}
+
+/// Get the value of a column of a row.
+///
+/// Column types are cycling through integer, text, float and boolean (with a null every 5 rows) so the values only
+/// depend on the position of the row (starting at 1) and the column.
+fn get_value(row: u64, column: usize) -> DriverValue {
+    match column % 4 {
+        0 => DriverValue::Int64(row as i64),
+        1 => DriverValue::Text(format!("row {}", row)),
+        2 => DriverValue::Float64((row as f64) * 0.5),
+        _ if row % 5 == 0 => DriverValue::Null,
+        _ => DriverValue::Bool(row % 2 == 0),
+    }
+}
+
+struct MockDriverStream {
+    config: MockConfig,
+    next_row: u64,
+    affected_rows: Option<u64>,
+    is_update: bool,
+}
+
+impl Stream for MockDriverStream {
+    type Item = Result<DriverValue>;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>
+    ) -> std::task::Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.affected_rows.is_some() {
+            return std::task::Poll::Ready(None);
+        }
+        if this.config.fail_after.is_some_and(|fail_after| this.next_row >= fail_after) {
+            this.affected_rows = Some(0);
+            return std::task::Poll::Ready(Some(Err(anyhow!("Connection lost (injected failure)."))));
+        }
+        if this.is_update || this.next_row >= this.config.rows {
+            this.affected_rows = Some(if this.is_update { this.config.rows } else { 0 });
+            return std::task::Poll::Ready(None);
+        }
+        this.next_row += 1;
+        let row = this.next_row;
+        let values = (0..this.config.columns).map(|column| get_value(row, column)).collect();
+        std::task::Poll::Ready(Some(Ok(DriverValue::Array(values))))
+    }
+}
+
+impl DriverStream for MockDriverStream {
+    fn affected_rows(&self) -> Option<u64> {
+        self.affected_rows
+    }
+}
+
+impl DriverExecutor for MockDriver {
+    fn query<'e>(&'e mut self, query: &'e str) -> BoxFuture<'e, Result<Pin<Box<dyn DriverStream + 'e>>>> {
+        Box::pin(async move {
+            let config = self.config.as_ref().ok_or_else(|| anyhow!("Not connected"))?;
+            tokio::time::sleep(config.latency).await;
+            if config.fail == Some(Failure::Query) {
+                return Err(anyhow!("Query failed (injected failure)."));
+            }
+            let keyword = query.split_whitespace().next().unwrap_or_default().to_uppercase();
+            let driver_stream = MockDriverStream {
+                config: config.clone(),
+                next_row: 0,
+                affected_rows: None,
+                is_update: matches!(keyword.as_str(), "INSERT" | "UPDATE" | "DELETE"),
+            };
+            Ok(Box::pin(driver_stream) as Pin<Box<dyn DriverStream>>)
+        })
+    }
+
+    fn query_with_params<'e>(
+        &'e mut self,
+        query: &'e str,
+        _params: QueryParams
+    ) -> BoxFuture<'e, Result<Pin<Box<dyn DriverStream + 'e>>>> {
+        self.query(query)
+    }
+}
+
+impl Driver for MockDriver {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::execute_query;
+    use futures::TryStreamExt;
+
+    #[test]
+    fn test_parse_connection_string() {
+        assert_eq!(MockConfig::parse("mock://").unwrap(), MockConfig::default());
+        assert_eq!(
+            MockConfig::parse("mock://?rows=10&columns=2&latency=50&fail=query&fail_after=5").unwrap(),
+            MockConfig {
+                rows: 10,
+                columns: 2,
+                latency: Duration::from_millis(50),
+                fail: Some(Failure::Query),
+                fail_after: Some(5),
+            }
+        );
+        assert!(MockConfig::parse("mock://?rows=many").is_err());
+        assert!(MockConfig::parse("mock://?fail=always").is_err());
+        assert!(MockConfig::parse("mock://?unknown=1").is_err());
+        assert!(MockConfig::parse("sqlite::memory:").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mock_query() {
+        let mut driver = MockDriver::new("mock://?rows=3&columns=5".to_string());
+        assert!(driver.query("SELECT 1").await.is_err());
+        assert!(driver.connect().await.is_ok());
+        assert_eq!(driver.get_info().await.unwrap().edition, MOCK_EDITION);
+
+        // 1) the rows are deterministic
+        let rows: Vec<DriverValue> = driver.query("SELECT * FROM t").await.unwrap().try_collect().await.unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(
+            rows[1].as_array(),
+            &[
+                DriverValue::Int64(2),
+                DriverValue::Text("row 2".to_string()),
+                DriverValue::Float64(1.0),
+                DriverValue::Bool(true),
+                DriverValue::Int64(2),
+            ]
+        );
+
+        // 2) updates are returning the affected rows
+        assert_eq!(execute_query(&mut driver, "delete from t").await.unwrap(), 3);
+        assert!(driver.close().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_mock_failures() {
+        // 1) connect
+        let mut driver = MockDriver::new("mock://?fail=connect".to_string());
+        assert!(driver.connect().await.is_err());
+
+        // 2) query
+        let mut driver = MockDriver::new("mock://?fail=query".to_string());
+        assert!(driver.connect().await.is_ok());
+        assert!(driver.query("SELECT 1").await.is_err());
+
+        // 3) after some rows
+        let mut driver = MockDriver::new("mock://?rows=10&fail_after=2".to_string());
+        assert!(driver.connect().await.is_ok());
+        let mut stream = driver.query("SELECT 1").await.unwrap();
+        assert!(stream.try_next().await.unwrap().is_some());
+        assert!(stream.try_next().await.unwrap().is_some());
+        assert!(stream.try_next().await.is_err());
+        assert!(stream.try_next().await.unwrap().is_none());
+    }
+}
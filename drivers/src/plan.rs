@@ -0,0 +1,214 @@
+use anyhow::{ anyhow, Result };
+use serde_json::Value;
+use crate::driver::{ DriverInfo, FEATURE_EXPLAIN_JSON, FEATURE_EXPLAIN_QUERY_PLAN };
+use crate::sql::is_single_statement;
+use crate::value::DriverValue;
+
+/// A node of a query plan.
+///
+/// The plans returned by the servers are normalized into a tree of nodes so they can be displayed the same way
+/// whatever the driver.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlanNode {
+    /// The operation performed by the node (e.g. "Seq Scan", "SCAN users").
+    pub operation: String,
+
+    /// The relation (table or index) read by the node if any.
+    pub relation: Option<String>,
+
+    /// The estimated total cost of the node (if reported by the server).
+    pub cost: Option<f64>,
+
+    /// The estimated number of rows returned by the node (if reported by the server).
+    pub rows: Option<f64>,
+
+    pub children: Vec<PlanNode>,
+}
+
+/// The formats of the query plans supported by the drivers.
+enum PlanFormat {
+    Json,
+    QueryPlan,
+}
+
+/// Get the format of the query plans returned by the server from the features it supports.
+fn get_plan_format(info: &DriverInfo) -> Result<PlanFormat> {
+    if info.features.iter().any(|feature| feature == FEATURE_EXPLAIN_JSON) {
+        Ok(PlanFormat::Json)
+    } else if info.features.iter().any(|feature| feature == FEATURE_EXPLAIN_QUERY_PLAN) {
+        Ok(PlanFormat::QueryPlan)
+    } else {
+        Err(anyhow!("Query plans are not supported for {}.", info.edition))
+    }
+}
+
+/// Get the query returning the plan of a query.
+///
+/// The query must be a single statement, otherwise the statements following the first one would be executed along
+/// with the `EXPLAIN` by the servers accepting several statements in a single query (e.g. SQLite).
+pub fn get_explain_query(query: &str, info: &DriverInfo) -> Result<String> {
+    let format = get_plan_format(info)?;
+    if !is_single_statement(query) {
+        return Err(anyhow!("The plan can only be requested for a single statement."));
+    }
+    match format {
+        PlanFormat::Json => Ok(format!("EXPLAIN (FORMAT JSON) {}", query)),
+        PlanFormat::QueryPlan => Ok(format!("EXPLAIN QUERY PLAN {}", query)),
+    }
+}
+
+/// Normalize the rows returned by the query built with `get_explain_query` into a tree of nodes.
+pub fn parse_plan(rows: Vec<DriverValue>, info: &DriverInfo) -> Result<PlanNode> {
+    match get_plan_format(info)? {
+        PlanFormat::Json => parse_postgres_plan(rows),
+        PlanFormat::QueryPlan => parse_sqlite_plan(rows),
+    }
+}
+
+/// Get the values of a row returned by a driver.
+fn into_values(row: DriverValue) -> Vec<DriverValue> {
+    match row {
+        DriverValue::Array(values) => values,
+        value => vec![value],
+    }
+}
+
+/// PostgreSQL is returning a single row with a JSON document: `[{ "Plan": { "Node Type": ..., "Plans": [...] } }]`.
+fn parse_postgres_plan(rows: Vec<DriverValue>) -> Result<PlanNode> {
+    let value = rows.into_iter().next().and_then(|row| into_values(row).into_iter().next());
+    let Some(DriverValue::Text(json)) = value else {
+        return Err(anyhow!("Unexpected query plan format."));
+    };
+    let document: Value = serde_json::from_str(&json)?;
+    let plan = document
+        .get(0)
+        .and_then(|document| document.get("Plan"))
+        .ok_or_else(|| anyhow!("Unexpected query plan format."))?;
+    Ok(parse_postgres_node(plan))
+}
+
+fn parse_postgres_node(node: &Value) -> PlanNode {
+    PlanNode {
+        operation: node["Node Type"].as_str().unwrap_or_default().to_string(),
+        relation: node
+            .get("Relation Name")
+            .or_else(|| node.get("Index Name"))
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        cost: node["Total Cost"].as_f64(),
+        rows: node["Plan Rows"].as_f64(),
+        children: node["Plans"]
+            .as_array()
+            .map(|plans| plans.iter().map(parse_postgres_node).collect())
+            .unwrap_or_default(),
+    }
+}
+
+/// SQLite is returning one row per node: `id, parent, notused, detail` where `parent` is 0 for the top level nodes.
+///
+/// Since there can be several top level nodes, they are the children of a root node.
+fn parse_sqlite_plan(rows: Vec<DriverValue>) -> Result<PlanNode> {
+    let mut nodes = Vec::new();
+    for row in rows {
+        let values = into_values(row);
+        let (Some(DriverValue::Int64(id)), Some(DriverValue::Int64(parent)), Some(DriverValue::Text(detail))) = (
+            values.first(),
+            values.get(1),
+            values.get(3),
+        ) else {
+            return Err(anyhow!("Unexpected query plan format."));
+        };
+        nodes.push((*id, *parent, detail.clone()));
+    }
+    Ok(PlanNode {
+        operation: "QUERY PLAN".to_string(),
+        children: get_sqlite_children(&nodes, 0),
+        ..Default::default()
+    })
+}
+
+fn get_sqlite_children(nodes: &[(i64, i64, String)], parent: i64) -> Vec<PlanNode> {
+    nodes
+        .iter()
+        .filter(|(id, node_parent, _)| *node_parent == parent && *id != parent)
+        .map(|(id, _, detail)| {
+            // e.g. "SCAN users" or "SEARCH users USING INDEX idx_name (name=?)"
+            let relation = match detail.split_whitespace().collect::<Vec<_>>()[..] {
+                ["SCAN" | "SEARCH", relation, ..] if relation != "CONSTANT" => Some(relation.to_string()),
+                _ => None,
+            };
+            PlanNode {
+                operation: detail.clone(),
+                relation,
+                children: get_sqlite_children(nodes, *id),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_explain_query() {
+        let info = DriverInfo { features: vec![FEATURE_EXPLAIN_JSON.to_string()], ..Default::default() };
+        assert_eq!(get_explain_query("SELECT 1", &info).unwrap(), "EXPLAIN (FORMAT JSON) SELECT 1");
+        let info = DriverInfo { features: vec![FEATURE_EXPLAIN_QUERY_PLAN.to_string()], ..Default::default() };
+        assert_eq!(get_explain_query("SELECT 1;", &info).unwrap(), "EXPLAIN QUERY PLAN SELECT 1;");
+        assert!(get_explain_query("SELECT 1; DELETE FROM t", &info).is_err());
+        let info = DriverInfo { edition: "Redshift".to_string(), ..Default::default() };
+        assert!(get_explain_query("SELECT 1", &info).is_err());
+    }
+
+    #[test]
+    fn test_parse_postgres_plan() {
+        let info = DriverInfo { features: vec![FEATURE_EXPLAIN_JSON.to_string()], ..Default::default() };
+        let json =
+            r#"[{"Plan": {"Node Type": "Hash Join", "Total Cost": 42.5, "Plan Rows": 10, "Plans": [
+                {"Node Type": "Seq Scan", "Relation Name": "users", "Total Cost": 20.0, "Plan Rows": 100},
+                {"Node Type": "Index Scan", "Index Name": "orders_pkey", "Total Cost": 8.0, "Plan Rows": 1}
+            ]}}]"#;
+        let plan = parse_plan(vec![DriverValue::Array(vec![DriverValue::Text(json.to_string())])], &info).unwrap();
+        assert_eq!(plan.operation, "Hash Join");
+        assert_eq!(plan.cost, Some(42.5));
+        assert_eq!(plan.rows, Some(10.0));
+        assert_eq!(plan.children.len(), 2);
+        assert_eq!(plan.children[0].relation.as_deref(), Some("users"));
+        assert_eq!(plan.children[1].relation.as_deref(), Some("orders_pkey"));
+        assert!(parse_plan(vec![DriverValue::Text("{}".to_string())], &info).is_err());
+    }
+
+    #[test]
+    fn test_parse_sqlite_plan() {
+        let info = DriverInfo { features: vec![FEATURE_EXPLAIN_QUERY_PLAN.to_string()], ..Default::default() };
+        let row = |id: i64, parent: i64, detail: &str| {
+            DriverValue::Array(
+                vec![
+                    DriverValue::Int64(id),
+                    DriverValue::Int64(parent),
+                    DriverValue::Int64(0),
+                    DriverValue::Text(detail.to_string())
+                ]
+            )
+        };
+        let plan = parse_plan(
+            vec![
+                row(2, 0, "SCAN users"),
+                row(5, 0, "SEARCH orders USING INDEX idx_user (user_id=?)"),
+                row(7, 0, "USE TEMP B-TREE FOR ORDER BY"),
+                row(9, 7, "SCAN CONSTANT ROW")
+            ],
+            &info
+        ).unwrap();
+        assert_eq!(plan.operation, "QUERY PLAN");
+        assert_eq!(plan.children.len(), 3);
+        assert_eq!(plan.children[0].relation.as_deref(), Some("users"));
+        assert_eq!(plan.children[1].relation.as_deref(), Some("orders"));
+        assert_eq!(plan.children[2].relation, None);
+        assert_eq!(plan.children[2].children[0].operation, "SCAN CONSTANT ROW");
+        assert_eq!(plan.children[2].children[0].relation, None);
+        assert!(parse_plan(vec![DriverValue::Text("SCAN users".to_string())], &info).is_err());
+    }
+}
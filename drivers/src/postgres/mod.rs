@@ -3,17 +3,37 @@ use futures::{ future::BoxFuture, Stream };
 use anyhow::Result;
 use openssl::ssl::{ SslConnector, SslMethod };
 use postgres_openssl::MakeTlsConnector;
+use tokio_postgres::{ config::Host, tls::MakeTlsConnect };
 use crate::{
-    driver::{ Driver, DriverConnection, DriverExecutor, DriverStream },
+    driver::{
+        parse_version,
+        Driver,
+        DriverConnection,
+        DriverExecutor,
+        DriverInfo,
+        DriverStream,
+        QueryParams,
+        FEATURE_EXPLAIN_JSON,
+        FEATURE_FETCH_FIRST,
+        FEATURE_LIMIT_OFFSET,
+        FEATURE_RETURNING,
+    },
     postgres::value::get_value,
+    redact::redact,
+    socks::{ self, Socks5Proxy },
+    sql::to_positional_params,
     value::DriverValue,
 };
 
 mod value;
 
+/// The port used when not specified by the connection string.
+const DEFAULT_PORT: u16 = 5432;
+
 pub struct PostgresDriver {
     connection_string: String,
     client: Option<tokio_postgres::Client>,
+    proxy: Option<Socks5Proxy>,
 }
 
 impl PostgresDriver {
@@ -21,6 +41,7 @@ impl PostgresDriver {
         PostgresDriver {
             connection_string,
             client: None,
+            proxy: None,
         }
     }
 }
@@ -30,7 +51,13 @@ impl DriverConnection for PostgresDriver {
         Box::pin(async move {
             let builder = SslConnector::builder(SslMethod::tls())?;
             let connector = MakeTlsConnector::new(builder.build());
-            let (client, connection) = tokio_postgres::connect(&self.connection_string, connector).await?;
+            if let Some(proxy) = &self.proxy {
+                self.client = Some(connect_through_proxy(&self.connection_string, proxy, connector).await?);
+                return Ok(());
+            }
+            let (client, connection) = tokio_postgres
+                ::connect(&self.connection_string, connector).await
+                .map_err(|e| anyhow::anyhow!(redact(&e.to_string())))?;
             tokio::spawn(connection);
             self.client = Some(client);
             Ok(())
@@ -43,6 +70,73 @@ impl DriverConnection for PostgresDriver {
             Ok(())
         })
     }
+
+    fn get_info(&mut self) -> BoxFuture<'_, Result<DriverInfo>> {
+        Box::pin(async move {
+            let client = self.client.as_ref().ok_or_else(|| anyhow::anyhow!("Not connected"))?;
+            let row = client.query_one("SELECT version(), current_setting('server_version')", &[]).await?;
+            Ok(get_driver_info(row.get(0), row.get(1)))
+        })
+    }
+
+    fn set_proxy(&mut self, proxy: Socks5Proxy) -> Result<()> {
+        self.proxy = Some(proxy);
+        Ok(())
+    }
+}
+
+/// Connect to the server through a SOCKS5 proxy.
+///
+/// Only the first host of the connection string is used (the proxy is resolving its name) and unix sockets are not
+/// supported.
+async fn connect_through_proxy(
+    connection_string: &str,
+    proxy: &Socks5Proxy,
+    mut connector: MakeTlsConnector
+) -> Result<tokio_postgres::Client> {
+    let config: tokio_postgres::Config = connection_string
+        .parse()
+        .map_err(|e: tokio_postgres::Error| anyhow::anyhow!(redact(&e.to_string())))?;
+    let Some(Host::Tcp(host)) = config.get_hosts().first() else {
+        return Err(anyhow::anyhow!("A host name is required to connect through a SOCKS5 proxy."));
+    };
+    let port = config.get_ports().first().copied().unwrap_or(DEFAULT_PORT);
+    let stream = socks::connect(proxy, host, port).await?;
+    let tls = MakeTlsConnect::<tokio::net::TcpStream>::make_tls_connect(&mut connector, host)?;
+    let (client, connection) = config
+        .connect_raw(stream, tls).await
+        .map_err(|e| anyhow::anyhow!(redact(&e.to_string())))?;
+    tokio::spawn(connection);
+    Ok(client)
+}
+
+/// Build the driver info from the result of `version()` and the setting `server_version`.
+///
+/// Forks of PostgreSQL such as Redshift are reporting themselves in the result of `version()`, e.g.
+/// "PostgreSQL 8.0.2 on i686-pc-linux-gnu, compiled by GCC gcc (GCC) 3.4.2 20041017 (Red Hat 3.4.2-6.fc3), Redshift 1.0.7".
+fn get_driver_info(version: &str, server_version: &str) -> DriverInfo {
+    let edition = if version.contains("Redshift") {
+        "Redshift"
+    } else {
+        version.split_whitespace().next().unwrap_or("PostgreSQL")
+    };
+    let parsed_version = parse_version(server_version);
+    let mut features = vec![FEATURE_LIMIT_OFFSET.to_string()];
+    if parsed_version >= vec![8, 2] {
+        features.push(FEATURE_RETURNING.to_string());
+    }
+    if parsed_version >= vec![8, 4] {
+        features.push(FEATURE_FETCH_FIRST.to_string());
+    }
+    if parsed_version >= vec![9, 0] && edition == "PostgreSQL" {
+        // The JSON format of EXPLAIN is available since PostgreSQL 9.0, but not in the forks such as Redshift.
+        features.push(FEATURE_EXPLAIN_JSON.to_string());
+    }
+    DriverInfo {
+        server_version: server_version.split_whitespace().next().unwrap_or_default().to_string(),
+        edition: edition.to_string(),
+        features,
+    }
 }
 
 struct PostgresDriverStream<'e> {
@@ -96,6 +190,28 @@ impl DriverExecutor for PostgresDriver {
             Ok(Box::pin(driver_stream) as Pin<Box<dyn DriverStream>>)
         })
     }
+
+    fn query_with_params<'e>(
+        &'e mut self,
+        query: &'e str,
+        params: QueryParams
+    ) -> BoxFuture<'e, Result<Pin<Box<dyn DriverStream + 'e>>>> {
+        Box::pin(async move {
+            let client = self.client.as_ref().ok_or_else(|| anyhow::anyhow!("Not connected"))?;
+            let (query, values) = to_positional_params(query, params, |position| format!("${}", position))?;
+            let stream = Box::pin(
+                client.query_raw(
+                    query.as_str(),
+                    values.iter().map(|value| value as &(dyn tokio_postgres::types::ToSql + std::marker::Sync))
+                ).await?
+            );
+            let driver_stream = PostgresDriverStream {
+                pg_row_stream: stream,
+                _marker: std::marker::PhantomData,
+            };
+            Ok(Box::pin(driver_stream) as Pin<Box<dyn DriverStream>>)
+        })
+    }
 }
 
 impl Driver for PostgresDriver {}
@@ -129,6 +245,36 @@ mod tests {
         assert!(driver.close().await.is_ok());
     }
 
+    #[test]
+    fn test_get_driver_info() {
+        let info = get_driver_info(
+            "PostgreSQL 16.2 (Debian 16.2-1.pgdg120+2) on x86_64-pc-linux-gnu",
+            "16.2 (Debian 16.2-1.pgdg120+2)"
+        );
+        assert_eq!(info.server_version, "16.2");
+        assert_eq!(info.edition, "PostgreSQL");
+        assert!(info.features.contains(&FEATURE_FETCH_FIRST.to_string()));
+        assert!(info.features.contains(&FEATURE_EXPLAIN_JSON.to_string()));
+
+        let info = get_driver_info(
+            "PostgreSQL 8.0.2 on i686-pc-linux-gnu, compiled by GCC gcc (GCC) 3.4.2 20041017, Redshift 1.0.7",
+            "8.0.2"
+        );
+        assert_eq!(info.edition, "Redshift");
+        assert!(!info.features.contains(&FEATURE_FETCH_FIRST.to_string()));
+        assert!(!info.features.contains(&FEATURE_EXPLAIN_JSON.to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_postgres_get_info() {
+        let mut driver = create_postgres_driver!();
+        assert!(driver.connect().await.is_ok());
+        let info = driver.get_info().await.unwrap();
+        assert_eq!(info.edition, "PostgreSQL");
+        assert!(!info.server_version.is_empty());
+        assert!(driver.close().await.is_ok());
+    }
+
     #[tokio::test]
     async fn test_postgres_query() {
         let mut driver = create_postgres_driver!();
@@ -164,4 +310,30 @@ mod tests {
         drop(stream);
         assert!(driver.close().await.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_postgres_query_with_params() {
+        let mut driver = create_postgres_driver!();
+        assert!(driver.connect().await.is_ok());
+
+        // 1) positional parameters
+        let params = QueryParams::Positional(vec![DriverValue::Int64(1), DriverValue::Text("one".to_string())]);
+        let mut stream = driver.query_with_params("SELECT $1::int4 + 1, $2::text", params).await.unwrap();
+        let row = stream.try_next().await.unwrap().unwrap();
+        assert_eq!(row.as_array(), &[DriverValue::Int32(2), DriverValue::Text("one".to_string())]);
+        drop(stream);
+
+        // 2) named parameters
+        let params = QueryParams::Named(vec![("id".to_string(), DriverValue::Int32(3))]);
+        let mut stream = driver.query_with_params("SELECT :id::int8 * :id", params).await.unwrap();
+        let row = stream.try_next().await.unwrap().unwrap();
+        assert_eq!(row.as_array(), &[DriverValue::Int64(9)]);
+        drop(stream);
+
+        // 3) missing parameter
+        let params = QueryParams::Named(vec![]);
+        assert!(driver.query_with_params("SELECT :id", params).await.is_err());
+
+        assert!(driver.close().await.is_ok());
+    }
 }
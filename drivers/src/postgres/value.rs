@@ -1,3 +1,5 @@
+use std::error::Error;
+use bytes::BytesMut;
 use tokio_postgres::types::*;
 use tokio_postgres::Row;
 use crate::value::DriverValue;
@@ -12,6 +14,97 @@ pub fn get_value(row: &Row, type_: Type, idx: usize) -> DriverValue {
         Type::OID => { DriverValue::Int32(row.get(idx)) }
         Type::FLOAT4 => { DriverValue::Float32(row.get(idx)) }
         Type::FLOAT8 => { DriverValue::Float64(row.get(idx)) }
+        Type::JSON | Type::JSONB => { DriverValue::Text(row.get::<_, JsonText>(idx).0) }
         _ => { DriverValue::UnsupportedType(type_.name().to_string()) }
     }
 }
+
+/// A JSON document decoded as text.
+struct JsonText(String);
+
+impl<'a> FromSql<'a> for JsonText {
+    fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        // The binary format of `jsonb` is the text prefixed by a version number (1).
+        let raw = match *ty {
+            Type::JSONB => raw.strip_prefix(&[1]).ok_or("Unsupported jsonb version.")?,
+            _ => raw,
+        };
+        Ok(JsonText(std::str::from_utf8(raw)?.to_string()))
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        matches!(*ty, Type::JSON | Type::JSONB)
+    }
+}
+
+/// Bind a `DriverValue` to a query parameter.
+///
+/// The type of the parameter is inferred by the server, so numeric values are converted to the expected type when
+/// possible (e.g. an `Int64` can be bound to an `int4` parameter as long as it fits).
+impl ToSql for DriverValue {
+    fn to_sql(&self, ty: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        match self {
+            DriverValue::Null => Ok(IsNull::Yes),
+            DriverValue::Bool(value) => value.to_sql_checked(ty, out),
+            DriverValue::Int16(value) => integer_to_sql(*value as i64, ty, out),
+            DriverValue::Int32(value) => integer_to_sql(*value as i64, ty, out),
+            DriverValue::Int64(value) => integer_to_sql(*value, ty, out),
+            DriverValue::Float32(value) => float_to_sql(*value as f64, ty, out),
+            DriverValue::Float64(value) => float_to_sql(*value, ty, out),
+            DriverValue::Text(value) => value.to_sql_checked(ty, out),
+            DriverValue::ByteArray(value) => value.to_sql_checked(ty, out),
+            DriverValue::Array(_) | DriverValue::UnsupportedType(_) => {
+                Err(format!("Cannot bind the value {:?} to a parameter of type '{}'.", self, ty).into())
+            }
+        }
+    }
+
+    fn accepts(_ty: &Type) -> bool {
+        true
+    }
+
+    to_sql_checked!();
+}
+
+fn integer_to_sql(value: i64, ty: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+    match *ty {
+        Type::INT2 => i16::try_from(value)?.to_sql(ty, out),
+        Type::INT4 | Type::OID => i32::try_from(value)?.to_sql(ty, out),
+        Type::FLOAT4 => (value as f32).to_sql(ty, out),
+        Type::FLOAT8 => (value as f64).to_sql(ty, out),
+        _ => value.to_sql_checked(ty, out),
+    }
+}
+
+fn float_to_sql(value: f64, ty: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+    match *ty {
+        Type::FLOAT4 => (value as f32).to_sql(ty, out),
+        _ => value.to_sql_checked(ty, out),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_driver_value_to_sql() {
+        let mut out = BytesMut::new();
+
+        // 1) integers are converted to the type of the parameter
+        assert!(DriverValue::Int64(42).to_sql_checked(&Type::INT4, &mut out).is_ok());
+        assert_eq!(out.as_ref(), &42i32.to_be_bytes());
+        out.clear();
+        assert!(DriverValue::Int64(i64::MAX).to_sql_checked(&Type::INT2, &mut out).is_err());
+        assert!(DriverValue::Int16(1).to_sql_checked(&Type::FLOAT8, &mut out).is_ok());
+        assert_eq!(out.as_ref(), &1f64.to_be_bytes());
+        out.clear();
+
+        // 2) NULL can be bound to any type
+        assert!(matches!(DriverValue::Null.to_sql_checked(&Type::INT4, &mut out), Ok(IsNull::Yes)));
+
+        // 3) incompatible types
+        assert!(DriverValue::Text("1".to_string()).to_sql_checked(&Type::INT4, &mut out).is_err());
+        assert!(DriverValue::Array(vec![]).to_sql_checked(&Type::TEXT, &mut out).is_err());
+    }
+}
@@ -0,0 +1,88 @@
+use std::pin::Pin;
+use futures::future::BoxFuture;
+use anyhow::Result;
+use crate::{
+    driver::{ Driver, DriverConnection, DriverExecutor, DriverInfo, DriverStream, QueryParams },
+    postgres::PostgresDriver,
+    socks::Socks5Proxy,
+};
+
+/// The edition reported by `DriverInfo` when connected to Amazon Redshift.
+const REDSHIFT_EDITION: &str = "Redshift";
+
+/// A driver for Amazon Redshift.
+///
+/// Redshift is speaking the PostgreSQL protocol, so this driver is built on top of the PostgreSQL driver and is only
+/// checking that the server is actually a Redshift cluster when connecting.
+pub struct RedshiftDriver {
+    postgres: PostgresDriver,
+}
+
+impl RedshiftDriver {
+    pub fn new(connection_string: String) -> Self {
+        RedshiftDriver {
+            postgres: PostgresDriver::new(connection_string),
+        }
+    }
+}
+
+impl DriverConnection for RedshiftDriver {
+    fn connect(&mut self) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            self.postgres.connect().await?;
+            let info = self.postgres.get_info().await?;
+            if info.edition != REDSHIFT_EDITION {
+                self.postgres.close().await?;
+                return Err(anyhow::anyhow!("The server is not a Redshift cluster (found: {}).", info.edition));
+            }
+            Ok(())
+        })
+    }
+
+    fn close(&mut self) -> BoxFuture<'_, Result<()>> {
+        self.postgres.close()
+    }
+
+    fn get_info(&mut self) -> BoxFuture<'_, Result<DriverInfo>> {
+        self.postgres.get_info()
+    }
+
+    fn set_proxy(&mut self, proxy: Socks5Proxy) -> Result<()> {
+        self.postgres.set_proxy(proxy)
+    }
+}
+
+impl DriverExecutor for RedshiftDriver {
+    fn query<'e>(&'e mut self, query: &'e str) -> BoxFuture<'e, Result<Pin<Box<dyn DriverStream + 'e>>>> {
+        self.postgres.query(query)
+    }
+
+    fn query_with_params<'e>(
+        &'e mut self,
+        query: &'e str,
+        params: QueryParams
+    ) -> BoxFuture<'e, Result<Pin<Box<dyn DriverStream + 'e>>>> {
+        self.postgres.query_with_params(query, params)
+    }
+}
+
+impl Driver for RedshiftDriver {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The environment variable CI_POSTGRES_CONNECTION_STRING must be set to run the tests.
+    const ENV_CI_POSTGRES_CONNECTION_STRING: &str = "CI_POSTGRES_CONNECTION_STRING";
+
+    #[tokio::test]
+    async fn test_redshift_connect_to_postgres() {
+        // A regular PostgreSQL server must be rejected.
+        let mut driver = RedshiftDriver::new(
+            std::env
+                ::var(ENV_CI_POSTGRES_CONNECTION_STRING)
+                .unwrap_or_else(|_| panic!("The environment variable {} is not set.", ENV_CI_POSTGRES_CONNECTION_STRING))
+        );
+        assert!(driver.connect().await.is_err());
+    }
+}
@@ -0,0 +1,201 @@
+use std::net::IpAddr;
+use anyhow::{ anyhow, Result };
+use tokio::io::{ AsyncReadExt, AsyncWriteExt };
+use tokio::net::TcpStream;
+
+/// The settings of a SOCKS5 proxy used to reach a server.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Socks5Proxy {
+    pub host: String,
+    pub port: u16,
+
+    /// The username used to authenticate to the proxy (no authentication if empty).
+    pub username: String,
+    pub password: String,
+}
+
+/// The error returned when the proxy itself cannot be used (unreachable, authentication failure...).
+///
+/// The failures to reach the server through the proxy are not reported as a `ProxyError`, so the clients can tell
+/// whether the proxy or the server should be checked.
+#[derive(Debug)]
+pub struct ProxyError(pub String);
+
+impl std::fmt::Display for ProxyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "SOCKS5 proxy error: {}", self.0)
+    }
+}
+
+impl std::error::Error for ProxyError {}
+
+const SOCKS_VERSION: u8 = 5;
+const AUTH_NONE: u8 = 0x00;
+const AUTH_USERNAME_PASSWORD: u8 = 0x02;
+const AUTH_NO_ACCEPTABLE_METHOD: u8 = 0xff;
+const COMMAND_CONNECT: u8 = 0x01;
+const ADDRESS_IPV4: u8 = 0x01;
+const ADDRESS_DOMAIN_NAME: u8 = 0x03;
+const ADDRESS_IPV6: u8 = 0x04;
+
+/// Open a TCP connection to `host:port` through a SOCKS5 proxy (RFC 1928 & RFC 1929).
+pub async fn connect(proxy: &Socks5Proxy, host: &str, port: u16) -> Result<TcpStream> {
+    let mut stream = TcpStream::connect((proxy.host.as_str(), proxy.port)).await.map_err(|e| {
+        ProxyError(format!("Unable to connect to the proxy {}:{}: {}", proxy.host, proxy.port, e))
+    })?;
+
+    // 1) negotiate the authentication method
+    let method = if proxy.username.is_empty() { AUTH_NONE } else { AUTH_USERNAME_PASSWORD };
+    stream.write_all(&[SOCKS_VERSION, 1, method]).await?;
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).await?;
+    if reply[0] != SOCKS_VERSION {
+        return Err(ProxyError("The server is not a SOCKS5 proxy.".to_string()).into());
+    }
+    if reply[1] == AUTH_NO_ACCEPTABLE_METHOD || reply[1] != method {
+        return Err(ProxyError("The authentication method is not accepted by the proxy.".to_string()).into());
+    }
+
+    // 2) authenticate
+    if method == AUTH_USERNAME_PASSWORD {
+        if proxy.username.len() > 255 || proxy.password.len() > 255 {
+            return Err(ProxyError("The username and password must not exceed 255 bytes.".to_string()).into());
+        }
+        let mut request = vec![1, proxy.username.len() as u8];
+        request.extend_from_slice(proxy.username.as_bytes());
+        request.push(proxy.password.len() as u8);
+        request.extend_from_slice(proxy.password.as_bytes());
+        stream.write_all(&request).await?;
+        stream.read_exact(&mut reply).await?;
+        if reply[1] != 0 {
+            return Err(ProxyError("Authentication failed.".to_string()).into());
+        }
+    }
+
+    // 3) connect to the server
+    let mut request = vec![SOCKS_VERSION, COMMAND_CONNECT, 0];
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(address)) => {
+            request.push(ADDRESS_IPV4);
+            request.extend_from_slice(&address.octets());
+        }
+        Ok(IpAddr::V6(address)) => {
+            request.push(ADDRESS_IPV6);
+            request.extend_from_slice(&address.octets());
+        }
+        Err(_) if host.len() <= 255 => {
+            request.push(ADDRESS_DOMAIN_NAME);
+            request.push(host.len() as u8);
+            request.extend_from_slice(host.as_bytes());
+        }
+        Err(_) => {
+            return Err(anyhow!("The host name must not exceed 255 bytes."));
+        }
+    }
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[1] != 0 {
+        return Err(anyhow!("The proxy was unable to reach {}:{}: {}.", host, port, get_reply_message(reply[1])));
+    }
+
+    // 4) skip the address bound by the proxy
+    let address_len = match reply[3] {
+        ADDRESS_IPV4 => 4,
+        ADDRESS_IPV6 => 16,
+        ADDRESS_DOMAIN_NAME => stream.read_u8().await? as usize,
+        _ => {
+            return Err(ProxyError("Invalid reply from the proxy.".to_string()).into());
+        }
+    };
+    let mut address = vec![0u8; address_len + 2];
+    stream.read_exact(&mut address).await?;
+    Ok(stream)
+}
+
+/// Get the message of a reply code (see RFC 1928, section 6).
+fn get_reply_message(code: u8) -> &'static str {
+    match code {
+        0x02 => "connection not allowed by ruleset",
+        0x03 => "network unreachable",
+        0x04 => "host unreachable",
+        0x05 => "connection refused",
+        0x06 => "TTL expired",
+        0x07 => "command not supported",
+        0x08 => "address type not supported",
+        _ => "general failure",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// A minimal SOCKS5 proxy accepting a single connection and replying with the given code.
+    async fn start_proxy(credentials: Option<(&'static str, &'static str)>, reply_code: u8) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut greeting = [0u8; 3];
+            stream.read_exact(&mut greeting).await.unwrap();
+            let expected = if credentials.is_some() { AUTH_USERNAME_PASSWORD } else { AUTH_NONE };
+            if greeting[2] != expected {
+                stream.write_all(&[SOCKS_VERSION, AUTH_NO_ACCEPTABLE_METHOD]).await.unwrap();
+                return;
+            }
+            stream.write_all(&[SOCKS_VERSION, expected]).await.unwrap();
+            if let Some((username, password)) = credentials {
+                let mut buffer = [0u8; 512];
+                let len = stream.read(&mut buffer).await.unwrap();
+                let mut expected = vec![1, username.len() as u8];
+                expected.extend_from_slice(username.as_bytes());
+                expected.push(password.len() as u8);
+                expected.extend_from_slice(password.as_bytes());
+                let status = if buffer[..len] == expected[..] { 0 } else { 1 };
+                stream.write_all(&[1, status]).await.unwrap();
+                if status != 0 {
+                    return;
+                }
+            }
+            let mut request = [0u8; 5];
+            stream.read_exact(&mut request).await.unwrap();
+            let mut address = vec![0u8; request[4] as usize + 2];
+            stream.read_exact(&mut address).await.unwrap();
+            stream.write_all(&[SOCKS_VERSION, reply_code, 0, ADDRESS_IPV4, 127, 0, 0, 1, 0, 80]).await.unwrap();
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn test_connect() {
+        // 1) no authentication
+        let port = start_proxy(None, 0).await;
+        let proxy = Socks5Proxy { host: "127.0.0.1".to_string(), port, ..Default::default() };
+        assert!(connect(&proxy, "db.example.com", 5432).await.is_ok());
+
+        // 2) authentication
+        let port = start_proxy(Some(("marty", "1985")), 0).await;
+        let proxy = Socks5Proxy {
+            host: "127.0.0.1".to_string(),
+            port,
+            username: "marty".to_string(),
+            password: "1985".to_string(),
+        };
+        assert!(connect(&proxy, "db.example.com", 5432).await.is_ok());
+
+        // 3) authentication failure (proxy error)
+        let port = start_proxy(Some(("marty", "1985")), 0).await;
+        let proxy = Socks5Proxy { password: "2015".to_string(), port, ..proxy };
+        assert!(connect(&proxy, "db.example.com", 5432).await.unwrap_err().downcast_ref::<ProxyError>().is_some());
+
+        // 4) the server cannot be reached through the proxy (not a proxy error)
+        let port = start_proxy(None, 0x05).await;
+        let proxy = Socks5Proxy { host: "127.0.0.1".to_string(), port, ..Default::default() };
+        let err = connect(&proxy, "db.example.com", 5432).await.unwrap_err();
+        assert!(err.downcast_ref::<ProxyError>().is_none());
+        assert_eq!(err.to_string(), "The proxy was unable to reach db.example.com:5432: connection refused.");
+    }
+}
@@ -0,0 +1,575 @@
+use anyhow::Result;
+use crate::driver::{ DriverInfo, QueryParams, FEATURE_FETCH_FIRST, FEATURE_LIMIT_OFFSET, FEATURE_PRAGMA_CATALOG };
+use crate::value::DriverValue;
+
+/// A column used to sort the rows of a query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderBy {
+    pub column: String,
+    pub descending: bool,
+}
+
+/// Quote an identifier (table, column, schema...) using the SQL standard double quotes.
+///
+/// Double quotes within the identifier are escaped by doubling them.
+pub fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+/// Quote a string literal using single quotes.
+///
+/// Single quotes within the string are escaped by doubling them.
+pub fn quote_literal(literal: &str) -> String {
+    format!("'{}'", literal.replace('\'', "''"))
+}
+
+/// Check if the catalog of the server must be read from `sqlite_master` and the pragma functions.
+fn has_pragma_catalog(info: &DriverInfo) -> bool {
+    info.features.iter().any(|feature| feature == FEATURE_PRAGMA_CATALOG)
+}
+
+/// Get the query listing the schemas of a datasource.
+///
+/// The query returns one row per schema with a single column: the name of the schema.
+pub fn get_schemas_query(info: &DriverInfo) -> String {
+    if has_pragma_catalog(info) {
+        "SELECT name FROM pragma_database_list ORDER BY seq".to_string()
+    } else {
+        "SELECT schema_name FROM information_schema.schemata ORDER BY schema_name".to_string()
+    }
+}
+
+/// Get the query listing the tables and views of a schema (or all the schemas if `schema` is `None`).
+///
+/// The query returns one row per table with the columns: schema, name & type of the table (the type contains `VIEW`
+/// for the views). The name of the schema is bound to the parameter `:schema` of the query.
+pub fn get_tables_query(schema: Option<&str>, info: &DriverInfo) -> (String, QueryParams) {
+    let mut params = Vec::new();
+    let query = if has_pragma_catalog(info) {
+        let schema = schema.unwrap_or("main");
+        params.push(("schema".to_string(), DriverValue::Text(schema.to_string())));
+        format!(
+            "SELECT :schema, name, type FROM {}.sqlite_master WHERE type IN ('table', 'view') ORDER BY name",
+            quote_identifier(schema)
+        )
+    } else {
+        let filter = match schema {
+            Some(schema) => {
+                params.push(("schema".to_string(), DriverValue::Text(schema.to_string())));
+                " WHERE table_schema = :schema"
+            }
+            None => "",
+        };
+        format!(
+            "SELECT table_schema, table_name, table_type FROM information_schema.tables{} \
+             ORDER BY table_schema, table_name",
+            filter
+        )
+    };
+    (query, QueryParams::Named(params))
+}
+
+/// Get the query listing the columns of a table.
+///
+/// The query returns one row per column in their ordinal order with the columns: name, data type & nullable (either a
+/// boolean, an integer or `YES`/`NO` depending on the server). The names of the table and the schema are bound to the
+/// parameters `:table` and `:schema` of the query.
+pub fn get_columns_query(schema: Option<&str>, table: &str, info: &DriverInfo) -> (String, QueryParams) {
+    let mut params = vec![("table".to_string(), DriverValue::Text(table.to_string()))];
+    let query = if has_pragma_catalog(info) {
+        params.push(("schema".to_string(), DriverValue::Text(schema.unwrap_or("main").to_string())));
+        "SELECT name, type, \"notnull\" = 0 FROM pragma_table_info(:table, :schema) ORDER BY cid".to_string()
+    } else {
+        let filter = match schema {
+            Some(schema) => {
+                params.push(("schema".to_string(), DriverValue::Text(schema.to_string())));
+                " AND table_schema = :schema"
+            }
+            None => "",
+        };
+        format!(
+            "SELECT column_name, data_type, is_nullable FROM information_schema.columns \
+             WHERE table_name = :table{} ORDER BY ordinal_position",
+            filter
+        )
+    };
+    (query, QueryParams::Named(params))
+}
+
+/// Check if the server supports limiting the number of rows returned by a query (and skipping the first ones).
+pub fn supports_paging(info: &DriverInfo) -> bool {
+    info.features.iter().any(|feature| feature == FEATURE_LIMIT_OFFSET || feature == FEATURE_FETCH_FIRST)
+}
+
+/// Get the query used to read a page of rows from a table.
+///
+/// The clause used to limit the number of rows depends on the features supported by the server. If the server does
+/// not support paging (see `supports_paging`), the query returns all the rows and the paging must be done while
+/// fetching.
+pub fn get_page_query(
+    schema: Option<&str>,
+    table: &str,
+    order_by: &[OrderBy],
+    offset: u64,
+    limit: u64,
+    info: &DriverInfo
+) -> String {
+    let mut query = match schema {
+        Some(schema) => format!("SELECT * FROM {}.{}", quote_identifier(schema), quote_identifier(table)),
+        None => format!("SELECT * FROM {}", quote_identifier(table)),
+    };
+    if !order_by.is_empty() {
+        query.push_str(" ORDER BY ");
+        query.push_str(
+            &order_by
+                .iter()
+                .map(|order_by| {
+                    let direction = if order_by.descending { " DESC" } else { "" };
+                    format!("{}{}", quote_identifier(&order_by.column), direction)
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    if info.features.iter().any(|feature| feature == FEATURE_LIMIT_OFFSET) {
+        query.push_str(&format!(" LIMIT {}", limit));
+        if offset > 0 {
+            query.push_str(&format!(" OFFSET {}", offset));
+        }
+    } else if info.features.iter().any(|feature| feature == FEATURE_FETCH_FIRST) {
+        if offset > 0 {
+            query.push_str(&format!(" OFFSET {} ROWS", offset));
+        }
+        query.push_str(&format!(" FETCH FIRST {} ROWS ONLY", limit));
+    }
+    query
+}
+
+/// Convert the parameters of a query into positional parameters.
+///
+/// Named parameters (`:name`) are replaced in the query by the placeholder returned by `placeholder` for their
+/// position (starting at 1), a parameter referenced several times is bound only once. Names found in string literals,
+/// quoted identifiers or comments are ignored, and so are PostgreSQL casts (`::type`).
+///
+/// Within the brackets of an array subscript, a colon following an operand is the separator of a slice
+/// (`tags[1:n]`, `tags[:lower:upper]` is the slice between the parameters `:lower` and the column `upper`).
+pub fn to_positional_params(
+    query: &str,
+    params: QueryParams,
+    placeholder: impl Fn(usize) -> String
+) -> Result<(String, Vec<DriverValue>)> {
+    let named_params = match params {
+        QueryParams::Positional(values) => {
+            return Ok((query.to_string(), values));
+        }
+        QueryParams::Named(named_params) => named_params,
+    };
+
+    let bytes = query.as_bytes();
+    let mut sql = String::with_capacity(query.len());
+    let mut names: Vec<&str> = Vec::new();
+    let mut values: Vec<DriverValue> = Vec::new();
+    let mut copied = 0;
+    let mut brackets: usize = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        if let Some(end) = skip_literal_or_comment(query, i) {
+            i = end;
+            continue;
+        }
+        let next = bytes.get(i + 1).copied().unwrap_or_default();
+        match bytes[i] {
+            b'[' => {
+                brackets += 1;
+                i += 1;
+            }
+            b']' => {
+                brackets = brackets.saturating_sub(1);
+                i += 1;
+            }
+            b':' if next == b':' => {
+                i += 2;
+            }
+            b':' if brackets > 0 && !matches!(query[..i].trim_end().as_bytes().last(), Some(b'[' | b':')) => {
+                // the separator of an array slice
+                i += 1;
+            }
+            b':' if next.is_ascii_alphabetic() || next == b'_' => {
+                let start = i + 1;
+                let mut end = start;
+                while end < bytes.len() && (bytes[end].is_ascii_alphanumeric() || bytes[end] == b'_') {
+                    end += 1;
+                }
+                let name = &query[start..end];
+                let position = match names.iter().position(|n| *n == name) {
+                    Some(index) => index + 1,
+                    None => {
+                        let (_, value) = named_params
+                            .iter()
+                            .find(|(n, _)| n == name)
+                            .ok_or_else(|| anyhow::anyhow!("Missing value for the query parameter ':{}'.", name))?;
+                        names.push(name);
+                        values.push(value.clone());
+                        names.len()
+                    }
+                };
+                sql.push_str(&query[copied..i]);
+                sql.push_str(&placeholder(position));
+                copied = end;
+                i = end;
+            }
+            _ => {
+                i += 1;
+            }
+        }
+    }
+    sql.push_str(&query[copied..]);
+    Ok((sql, values))
+}
+
+/// Get the position following the string literal, quoted identifier or comment starting at the position `i` of a
+/// query (`None` if there is none starting at this position).
+///
+/// Besides the standard literals, PostgreSQL escape strings (`E'it\'s'`) and dollar-quoted strings (`$$it's$$` or
+/// `$tag$it's$tag$`) are recognized. An unterminated literal or comment extends to the end of the query.
+fn skip_literal_or_comment(query: &str, i: usize) -> Option<usize> {
+    let bytes = query.as_bytes();
+    let next = bytes.get(i + 1).copied().unwrap_or_default();
+    let follows_identifier = i > 0 && is_identifier_byte(bytes[i - 1]);
+    match bytes[i] {
+        quote @ (b'\'' | b'"' | b'`') => {
+            let end = query[i + 1..].find(quote as char).map_or(bytes.len(), |end| i + 1 + end + 1);
+            Some(end)
+        }
+        b'E' | b'e' if next == b'\'' && !follows_identifier => {
+            // a backslash escapes the following character, including a single quote
+            let mut j = i + 2;
+            while j < bytes.len() {
+                match bytes[j] {
+                    b'\\' => {
+                        j += 2;
+                    }
+                    b'\'' => {
+                        return Some(j + 1);
+                    }
+                    _ => {
+                        j += 1;
+                    }
+                }
+            }
+            Some(bytes.len())
+        }
+        b'$' if !follows_identifier && !next.is_ascii_digit() => {
+            // `$1` is a positional parameter, `a$b` an identifier
+            let tag_len = bytes[i + 1..]
+                .iter()
+                .take_while(|byte| byte.is_ascii_alphanumeric() || **byte == b'_')
+                .count();
+            if bytes.get(i + 1 + tag_len) != Some(&b'$') {
+                return None;
+            }
+            let tag = &query[i..i + tag_len + 2];
+            let start = i + tag.len();
+            Some(query[start..].find(tag).map_or(bytes.len(), |end| start + end + tag.len()))
+        }
+        b'-' if next == b'-' => Some(query[i..].find('\n').map_or(bytes.len(), |end| i + end)),
+        b'/' if next == b'*' => Some(query[i + 2..].find("*/").map_or(bytes.len(), |end| i + 2 + end + 2)),
+        _ => None,
+    }
+}
+
+/// Check if a byte can be part of an unquoted identifier (non-ASCII bytes are parts of UTF-8 letters).
+fn is_identifier_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'$' || !byte.is_ascii()
+}
+
+/// Check if a query contains a single statement.
+///
+/// A trailing semicolon is accepted, but any statement following it is not (e.g. `SELECT 1; DELETE FROM t`).
+/// Semicolons within string literals, quoted identifiers or comments are ignored.
+pub fn is_single_statement(query: &str) -> bool {
+    let bytes = query.as_bytes();
+    let mut end_of_statement = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        if let Some(end) = skip_literal_or_comment(query, i) {
+            // comments are accepted after the end of the statement, but not literals
+            if end_of_statement && !matches!(bytes[i], b'-' | b'/') {
+                return false;
+            }
+            i = end;
+            continue;
+        }
+        match bytes[i] {
+            b';' => {
+                end_of_statement = true;
+            }
+            byte if byte.is_ascii_whitespace() => {}
+            _ if end_of_statement => {
+                return false;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    true
+}
+
+/// The keywords starting the statements that only read data.
+const READ_ONLY_KEYWORDS: [&str; 8] = ["SELECT", "WITH", "VALUES", "TABLE", "SHOW", "DESCRIBE", "DESC", "EXPLAIN"];
+
+/// The keywords of the statements writing data (or changing the schema) that can be nested in a statement starting
+/// with a read-only keyword, e.g. `WITH d AS (DELETE FROM t RETURNING *) SELECT * FROM d` or `SELECT * INTO t2 FROM t`.
+const WRITE_KEYWORDS: [&str; 9] = [
+    "INSERT",
+    "UPDATE",
+    "DELETE",
+    "MERGE",
+    "INTO",
+    "CREATE",
+    "DROP",
+    "ALTER",
+    "TRUNCATE",
+];
+
+/// Check if a query is a single statement only reading data.
+///
+/// The check is conservative: a statement is read-only if it starts with a read-only keyword (`SELECT`, `SHOW`...)
+/// and does not contain any write keyword outside of the literals, quoted identifiers and comments (so `SELECT ... FOR
+/// UPDATE` is not considered read-only). The side effects of the functions called by a statement are not detected.
+pub fn is_read_only_statement(query: &str) -> bool {
+    if !is_single_statement(query) {
+        return false;
+    }
+    let bytes = query.as_bytes();
+    let mut first_keyword = true;
+    let mut i = 0;
+    while i < bytes.len() {
+        if let Some(end) = skip_literal_or_comment(query, i) {
+            i = end;
+            continue;
+        }
+        if !is_identifier_byte(bytes[i]) {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < bytes.len() && is_identifier_byte(bytes[i]) {
+            i += 1;
+        }
+        let word = query[start..i].to_ascii_uppercase();
+        if (first_keyword && !READ_ONLY_KEYWORDS.contains(&word.as_str())) || WRITE_KEYWORDS.contains(&word.as_str()) {
+            return false;
+        }
+        first_keyword = false;
+    }
+    !first_keyword
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_identifier() {
+        assert_eq!(quote_identifier("users"), "\"users\"");
+        assert_eq!(quote_identifier("my \"users\""), "\"my \"\"users\"\"\"");
+    }
+
+    #[test]
+    fn test_quote_literal() {
+        assert_eq!(quote_literal("users"), "'users'");
+        assert_eq!(quote_literal("marty's"), "'marty''s'");
+    }
+
+    #[test]
+    fn test_get_catalog_queries() {
+        // SQLite
+        let info = DriverInfo { features: vec![FEATURE_PRAGMA_CATALOG.to_string()], ..Default::default() };
+        assert_eq!(get_schemas_query(&info), "SELECT name FROM pragma_database_list ORDER BY seq");
+        let text = |value: &str| DriverValue::Text(value.to_string());
+        let (query, params) = get_tables_query(None, &info);
+        assert_eq!(
+            query,
+            "SELECT :schema, name, type FROM \"main\".sqlite_master WHERE type IN ('table', 'view') ORDER BY name"
+        );
+        assert_eq!(params, QueryParams::Named(vec![("schema".to_string(), text("main"))]));
+        assert_eq!(
+            get_columns_query(None, "users", &info),
+            (
+                "SELECT name, type, \"notnull\" = 0 FROM pragma_table_info(:table, :schema) ORDER BY cid".to_string(),
+                QueryParams::Named(vec![("table".to_string(), text("users")), ("schema".to_string(), text("main"))])
+            )
+        );
+
+        // information_schema
+        let info = DriverInfo { edition: "PostgreSQL".to_string(), ..Default::default() };
+        assert_eq!(
+            get_schemas_query(&info),
+            "SELECT schema_name FROM information_schema.schemata ORDER BY schema_name"
+        );
+        let (query, params) = get_tables_query(None, &info);
+        assert!(query.ends_with("FROM information_schema.tables ORDER BY table_schema, table_name"));
+        assert_eq!(params, QueryParams::Named(vec![]));
+        let (query, params) = get_tables_query(Some("o'neil"), &info);
+        assert!(query.contains("WHERE table_schema = :schema ORDER BY"));
+        assert_eq!(params, QueryParams::Named(vec![("schema".to_string(), text("o'neil"))]));
+        let (query, params) = get_columns_query(Some("public"), "users", &info);
+        assert!(query.contains("WHERE table_name = :table AND table_schema = :schema ORDER BY ordinal_position"));
+        assert_eq!(
+            params,
+            QueryParams::Named(vec![("table".to_string(), text("users")), ("schema".to_string(), text("public"))])
+        );
+    }
+
+    #[test]
+    fn test_get_page_query() {
+        let order_by = vec![
+            OrderBy { column: "name".to_string(), descending: false },
+            OrderBy { column: "id".to_string(), descending: true }
+        ];
+
+        // LIMIT/OFFSET
+        let info = DriverInfo {
+            features: vec![FEATURE_LIMIT_OFFSET.to_string()],
+            ..Default::default()
+        };
+        assert!(supports_paging(&info));
+        assert_eq!(
+            get_page_query(Some("public"), "users", &[], 0, 10, &info),
+            "SELECT * FROM \"public\".\"users\" LIMIT 10"
+        );
+        assert_eq!(
+            get_page_query(None, "users", &order_by, 20, 10, &info),
+            "SELECT * FROM \"users\" ORDER BY \"name\", \"id\" DESC LIMIT 10 OFFSET 20"
+        );
+
+        // FETCH FIRST
+        let info = DriverInfo {
+            features: vec![FEATURE_FETCH_FIRST.to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            get_page_query(None, "users", &[], 0, 10, &info),
+            "SELECT * FROM \"users\" FETCH FIRST 10 ROWS ONLY"
+        );
+        assert_eq!(
+            get_page_query(None, "users", &order_by[..1], 20, 10, &info),
+            "SELECT * FROM \"users\" ORDER BY \"name\" OFFSET 20 ROWS FETCH FIRST 10 ROWS ONLY"
+        );
+
+        // No paging
+        assert!(!supports_paging(&DriverInfo::default()));
+        assert_eq!(get_page_query(None, "users", &[], 20, 10, &DriverInfo::default()), "SELECT * FROM \"users\"");
+    }
+
+    #[test]
+    fn test_to_positional_params() {
+        let dollar = |position: usize| format!("${}", position);
+
+        // 1) positional parameters are left untouched
+        let params = QueryParams::Positional(vec![DriverValue::Int32(1)]);
+        assert_eq!(
+            to_positional_params("SELECT $1", params, dollar).unwrap(),
+            ("SELECT $1".to_string(), vec![DriverValue::Int32(1)])
+        );
+
+        // 2) named parameters, including one referenced twice
+        let params = QueryParams::Named(
+            vec![
+                ("name".to_string(), DriverValue::Text("bob".to_string())),
+                ("id".to_string(), DriverValue::Int64(2))
+            ]
+        );
+        let query = "SELECT * FROM t WHERE id = :id OR (name = :name AND id > :id)";
+        assert_eq!(
+            to_positional_params(query, params, dollar).unwrap(),
+            (
+                "SELECT * FROM t WHERE id = $1 OR (name = $2 AND id > $1)".to_string(),
+                vec![DriverValue::Int64(2), DriverValue::Text("bob".to_string())]
+            )
+        );
+
+        // 3) literals, identifiers, comments and casts are ignored
+        let params = QueryParams::Named(vec![("id".to_string(), DriverValue::Int32(1))]);
+        assert_eq!(
+            to_positional_params(
+                "SELECT ':x', \":y\", id::text -- :z\n/* :w */ FROM t WHERE id = :id",
+                params,
+                |position| format!("?{}", position)
+            ).unwrap().0,
+            "SELECT ':x', \":y\", id::text -- :z\n/* :w */ FROM t WHERE id = ?1"
+        );
+
+        // 4) missing parameter
+        let params = QueryParams::Named(vec![]);
+        assert!(to_positional_params("SELECT :id", params, dollar).is_err());
+
+        // 5) PostgreSQL escape strings and dollar-quoted strings are ignored
+        let params = || QueryParams::Named(vec![("id".to_string(), DriverValue::Int32(1))]);
+        assert_eq!(
+            to_positional_params("SELECT E'it\\'s :x', e'\\\\' WHERE id = :id", params(), dollar).unwrap().0,
+            "SELECT E'it\\'s :x', e'\\\\' WHERE id = $1"
+        );
+        assert_eq!(
+            to_positional_params("SELECT $$it's :x$$, $fn$ :y $$ :z $fn$, :id", params(), dollar).unwrap().0,
+            "SELECT $$it's :x$$, $fn$ :y $$ :z $fn$, $1"
+        );
+        assert_eq!(
+            to_positional_params("SELECT name$1, $1, :id FROM t", params(), dollar).unwrap().0,
+            "SELECT name$1, $1, $1 FROM t"
+        );
+
+        // 6) array slices
+        let params = QueryParams::Named(
+            vec![("lower".to_string(), DriverValue::Int32(1)), ("upper".to_string(), DriverValue::Int32(2))]
+        );
+        assert_eq!(
+            to_positional_params(
+                "SELECT tags[1:n], tags[ :lower : :upper ], tags[:lower:n], tags[i][2:3] FROM t",
+                params,
+                dollar
+            ).unwrap().0,
+            "SELECT tags[1:n], tags[ $1 : $2 ], tags[$1:n], tags[i][2:3] FROM t"
+        );
+    }
+
+    #[test]
+    fn test_is_single_statement() {
+        // 1) single statements
+        assert!(is_single_statement("SELECT 1"));
+        assert!(is_single_statement("SELECT 1;  "));
+        assert!(is_single_statement("SELECT ';', \";\" FROM t; -- the end"));
+        assert!(is_single_statement("SELECT 1 /* ; DELETE FROM t */;\n/* the end */"));
+
+        // 2) several statements
+        assert!(!is_single_statement("SELECT 1; DELETE FROM t"));
+        assert!(!is_single_statement("SELECT 1;DELETE FROM t;"));
+        assert!(!is_single_statement("SELECT 1; 'x'"));
+        assert!(!is_single_statement("SELECT 1; -- comment\nDELETE FROM t"));
+
+        // 3) PostgreSQL escape strings and dollar-quoted strings
+        assert!(is_single_statement("SELECT E'\\'; DELETE FROM t'"));
+        assert!(is_single_statement("DO $body$ BEGIN DELETE FROM t; END $body$;"));
+        assert!(!is_single_statement("SELECT 1; $$x$$"));
+    }
+
+    #[test]
+    fn test_is_read_only_statement() {
+        // 1) read-only statements
+        assert!(is_read_only_statement("SELECT * FROM users"));
+        assert!(is_read_only_statement("  -- comment\n(select 'DELETE', \"update\" FROM t);"));
+        assert!(is_read_only_statement("WITH a AS (SELECT 1) SELECT * FROM a"));
+        assert!(is_read_only_statement("EXPLAIN SELECT * FROM users"));
+
+        // 2) write statements
+        assert!(!is_read_only_statement("INSERT INTO users VALUES (1)"));
+        assert!(!is_read_only_statement("/* SELECT */ delete FROM users"));
+        assert!(!is_read_only_statement("WITH d AS (DELETE FROM t RETURNING *) SELECT * FROM d"));
+        assert!(!is_read_only_statement("SELECT * INTO t2 FROM t"));
+        assert!(!is_read_only_statement("EXPLAIN ANALYZE UPDATE t SET a = 1"));
+        assert!(!is_read_only_statement("SELECT 1; DROP TABLE t"));
+        assert!(!is_read_only_statement("DO $$ BEGIN PERFORM 1; END $$"));
+        assert!(!is_read_only_statement(""));
+    }
+}
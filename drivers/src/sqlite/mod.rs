@@ -7,24 +7,44 @@ use sqlx::sqlite::{ SqliteConnection, SqliteQueryResult, SqliteRow };
 use sqlx::{ Connection, Row };
 use sqlx::Either;
 use crate::value::DriverValue;
-use crate::driver::{ Driver, DriverConnection, DriverExecutor, DriverStream };
+use crate::redact::redact;
+use crate::sql::to_positional_params;
+use crate::driver::{
+    parse_version,
+    Driver,
+    DriverConnection,
+    DriverExecutor,
+    DriverInfo,
+    DriverStream,
+    QueryParams,
+    FEATURE_EXPLAIN_QUERY_PLAN,
+    FEATURE_LIMIT_OFFSET,
+    FEATURE_PRAGMA_CATALOG,
+    FEATURE_RETURNING,
+};
 mod value;
 
 pub struct SqliteDriver {
     connection_string: String,
     conn: Option<SqliteConnection>,
+
+    /// The last query executed by `query_with_params` once its named parameters have been replaced by positional ones
+    /// (the stream of the result is borrowing it).
+    statement: String,
 }
 
 impl SqliteDriver {
     pub fn new(connection_string: String) -> Self {
-        Self { connection_string, conn: None }
+        Self { connection_string, conn: None, statement: String::new() }
     }
 }
 
 impl DriverConnection for SqliteDriver {
     fn connect(&mut self) -> BoxFuture<'_, Result<()>> {
         Box::pin(async move {
-            let conn = SqliteConnection::connect(&self.connection_string).await?;
+            let conn = SqliteConnection::connect(&self.connection_string).await.map_err(|e|
+                anyhow::anyhow!(redact(&e.to_string()))
+            )?;
             self.conn = Some(conn);
             Ok(())
         })
@@ -38,6 +58,27 @@ impl DriverConnection for SqliteDriver {
             Ok(())
         })
     }
+
+    fn get_info(&mut self) -> BoxFuture<'_, Result<DriverInfo>> {
+        Box::pin(async move {
+            let conn = self.conn.as_mut().ok_or_else(|| anyhow::anyhow!("Not connected"))?;
+            let server_version: String = sqlx::query_scalar("SELECT sqlite_version()").fetch_one(conn).await?;
+            let mut features = vec![
+                FEATURE_LIMIT_OFFSET.to_string(),
+                FEATURE_EXPLAIN_QUERY_PLAN.to_string(),
+                FEATURE_PRAGMA_CATALOG.to_string()
+            ];
+            if parse_version(&server_version) >= vec![3, 35] {
+                // RETURNING is available since SQLite 3.35.0 (https://www.sqlite.org/lang_returning.html)
+                features.push(FEATURE_RETURNING.to_string());
+            }
+            Ok(DriverInfo {
+                server_version,
+                edition: "SQLite".to_string(),
+                features,
+            })
+        })
+    }
 }
 
 struct SqliteDriverStream<'e> {
@@ -90,6 +131,41 @@ impl DriverExecutor for SqliteDriver {
             Ok(driver_stream as Pin<Box<dyn DriverStream>>)
         })
     }
+
+    fn query_with_params<'e>(
+        &'e mut self,
+        query: &'e str,
+        params: QueryParams
+    ) -> BoxFuture<'e, Result<Pin<Box<dyn DriverStream + 'e>>>> {
+        Box::pin(async move {
+            let conn = self.conn.as_mut().ok_or_else(|| anyhow::anyhow!("Not connected"))?;
+            // sqlx does not support named parameters with SQLite, they are converted into `?NNN` parameters.
+            let (statement, values) = to_positional_params(query, params, |position| format!("?{}", position))?;
+            self.statement = statement;
+            let mut sqlite_query = sqlx::query(&self.statement);
+            for value in values {
+                sqlite_query = match value {
+                    DriverValue::Null => sqlite_query.bind(None::<String>),
+                    DriverValue::Bool(value) => sqlite_query.bind(value),
+                    DriverValue::Int16(value) => sqlite_query.bind(value),
+                    DriverValue::Int32(value) => sqlite_query.bind(value),
+                    DriverValue::Int64(value) => sqlite_query.bind(value),
+                    DriverValue::Float32(value) => sqlite_query.bind(value),
+                    DriverValue::Float64(value) => sqlite_query.bind(value),
+                    DriverValue::Text(value) => sqlite_query.bind(value),
+                    DriverValue::ByteArray(value) => sqlite_query.bind(value),
+                    DriverValue::Array(_) | DriverValue::UnsupportedType(_) => {
+                        return Err(anyhow::anyhow!("Cannot bind the value {:?} to a query parameter.", value));
+                    }
+                };
+            }
+            let driver_stream = Box::pin(SqliteDriverStream {
+                affected_rows: None,
+                stream: sqlite_query.fetch_many(conn),
+            });
+            Ok(driver_stream as Pin<Box<dyn DriverStream>>)
+        })
+    }
 }
 
 impl Driver for SqliteDriver {}
@@ -111,6 +187,19 @@ mod tests {
         assert!(driver.connect().await.is_err());
     }
 
+    #[tokio::test]
+    async fn test_sqlite_get_info() {
+        let mut driver = SqliteDriver::new("sqlite::memory:".to_string());
+        assert!(driver.get_info().await.is_err());
+        assert!(driver.connect().await.is_ok());
+        let info = driver.get_info().await.unwrap();
+        assert_eq!(info.edition, "SQLite");
+        assert!(parse_version(&info.server_version) >= vec![3]);
+        assert!(info.features.contains(&FEATURE_LIMIT_OFFSET.to_string()));
+        assert!(info.features.contains(&FEATURE_PRAGMA_CATALOG.to_string()));
+        assert!(driver.close().await.is_ok());
+    }
+
     #[tokio::test]
     async fn test_sqlite_query() {
         let mut driver = SqliteDriver::new("sqlite::memory:".to_string());
@@ -147,4 +236,33 @@ mod tests {
         drop(stream);
         assert!(driver.close().await.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_sqlite_query_with_params() {
+        let mut driver = SqliteDriver::new("sqlite::memory:".to_string());
+        assert!(driver.connect().await.is_ok());
+
+        // 1) positional parameters
+        let params = QueryParams::Positional(vec![DriverValue::Int64(1), DriverValue::Text("one".to_string())]);
+        let mut stream = driver.query_with_params("SELECT ? + 1, ?", params).await.unwrap();
+        let row = stream.try_next().await.unwrap().unwrap();
+        assert_eq!(row.as_array(), &[DriverValue::Int64(2), DriverValue::Text("one".to_string())]);
+        drop(stream);
+
+        // 2) named parameters
+        let params = QueryParams::Named(
+            vec![("id".to_string(), DriverValue::Int32(3)), ("name".to_string(), DriverValue::Null)]
+        );
+        let mut stream = driver.query_with_params("SELECT :id * :id, :name", params).await.unwrap();
+        let row = stream.try_next().await.unwrap().unwrap();
+        assert_eq!(row.as_array(), &[DriverValue::Int64(9), DriverValue::Null]);
+        drop(stream);
+
+        // 3) missing parameter & unsupported value
+        assert!(driver.query_with_params("SELECT :id", QueryParams::Named(vec![])).await.is_err());
+        let params = QueryParams::Positional(vec![DriverValue::Array(vec![])]);
+        assert!(driver.query_with_params("SELECT ?", params).await.is_err());
+
+        assert!(driver.close().await.is_ok());
+    }
 }
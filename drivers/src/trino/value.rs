@@ -0,0 +1,108 @@
+use anyhow::{ anyhow, Result };
+use serde_json::Value;
+use crate::sql::quote_literal;
+use crate::value::DriverValue;
+
+/// Convert a value returned by Trino into a `DriverValue`.
+///
+/// Trino is returning the values as JSON, the type of the column (e.g. "varchar(10)", "array(integer)") is used to
+/// convert it. Structural types (array, map & row) are returned as their JSON representation.
+pub fn get_value(value: Value, column_type: &str) -> DriverValue {
+    if value.is_null() {
+        return DriverValue::Null;
+    }
+    let base_type = column_type.split('(').next().unwrap_or_default().trim();
+    match (base_type, value) {
+        ("boolean", Value::Bool(value)) => DriverValue::Bool(value),
+        ("tinyint" | "smallint", Value::Number(value)) if value.as_i64().is_some() => {
+            DriverValue::Int16(value.as_i64().unwrap() as i16)
+        }
+        ("integer", Value::Number(value)) if value.as_i64().is_some() => {
+            DriverValue::Int32(value.as_i64().unwrap() as i32)
+        }
+        ("bigint", Value::Number(value)) if value.as_i64().is_some() => DriverValue::Int64(value.as_i64().unwrap()),
+        ("real", value) => {
+            match to_f64(&value) {
+                Some(value) => DriverValue::Float32(value as f32),
+                None => DriverValue::UnsupportedType(column_type.to_string()),
+            }
+        }
+        ("double", value) => {
+            match to_f64(&value) {
+                Some(value) => DriverValue::Float64(value),
+                None => DriverValue::UnsupportedType(column_type.to_string()),
+            }
+        }
+        ("array" | "map" | "row", value) => DriverValue::Text(value.to_string()),
+        ("varbinary", _) => DriverValue::UnsupportedType(column_type.to_string()),
+        // varchar, char, decimal, date, time, timestamp, uuid, json...
+        (_, Value::String(value)) => DriverValue::Text(value),
+        (_, value) => DriverValue::Text(value.to_string()),
+    }
+}
+
+/// Get a floating point value, special values (NaN, Infinity...) are returned as strings by Trino.
+fn to_f64(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(value) => value.as_f64(),
+        Value::String(value) => value.parse::<f64>().ok(),
+        _ => None,
+    }
+}
+
+/// Convert the value of a query parameter into a Trino literal.
+pub fn to_literal(value: &DriverValue) -> Result<String> {
+    match value {
+        DriverValue::Null => Ok("NULL".to_string()),
+        DriverValue::Bool(value) => Ok(value.to_string()),
+        DriverValue::Int16(value) => Ok(format!("SMALLINT '{}'", value)),
+        DriverValue::Int32(value) => Ok(format!("INTEGER '{}'", value)),
+        DriverValue::Int64(value) => Ok(format!("BIGINT '{}'", value)),
+        // Rust is formatting the infinity as `inf` while Trino expects `Infinity`.
+        DriverValue::Float32(value) => Ok(format!("REAL '{}'", value.to_string().replace("inf", "Infinity"))),
+        DriverValue::Float64(value) => Ok(format!("DOUBLE '{}'", value.to_string().replace("inf", "Infinity"))),
+        DriverValue::Text(value) => Ok(quote_literal(value)),
+        DriverValue::ByteArray(value) => {
+            Ok(format!("X'{}'", value.iter().map(|byte| format!("{:02X}", byte)).collect::<String>()))
+        }
+        DriverValue::Array(_) | DriverValue::UnsupportedType(_) => {
+            Err(anyhow!("Cannot bind the value {:?} to a query parameter.", value))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_get_value() {
+        assert_eq!(get_value(Value::Null, "integer"), DriverValue::Null);
+        assert_eq!(get_value(json!(true), "boolean"), DriverValue::Bool(true));
+        assert_eq!(get_value(json!(12), "smallint"), DriverValue::Int16(12));
+        assert_eq!(get_value(json!(42), "integer"), DriverValue::Int32(42));
+        assert_eq!(get_value(json!(42), "bigint"), DriverValue::Int64(42));
+        assert_eq!(get_value(json!(1.5), "real"), DriverValue::Float32(1.5));
+        assert_eq!(get_value(json!(1.5), "double"), DriverValue::Float64(1.5));
+        assert_eq!(get_value(json!("Infinity"), "double"), DriverValue::Float64(f64::INFINITY));
+        assert_eq!(get_value(json!("hello"), "varchar(10)"), DriverValue::Text("hello".to_string()));
+        assert_eq!(get_value(json!("12.30"), "decimal(10,2)"), DriverValue::Text("12.30".to_string()));
+        assert_eq!(get_value(json!([1, 2]), "array(integer)"), DriverValue::Text("[1,2]".to_string()));
+        assert_eq!(get_value(json!({ "a": 1 }), "map(varchar, integer)"), DriverValue::Text("{\"a\":1}".to_string()));
+        assert_eq!(get_value(json!("AQI="), "varbinary"), DriverValue::UnsupportedType("varbinary".to_string()));
+    }
+
+    #[test]
+    fn test_to_literal() {
+        assert_eq!(to_literal(&DriverValue::Null).unwrap(), "NULL");
+        assert_eq!(to_literal(&DriverValue::Bool(true)).unwrap(), "true");
+        assert_eq!(to_literal(&DriverValue::Int32(42)).unwrap(), "INTEGER '42'");
+        assert_eq!(to_literal(&DriverValue::Int64(-1)).unwrap(), "BIGINT '-1'");
+        assert_eq!(to_literal(&DriverValue::Float32(1.5)).unwrap(), "REAL '1.5'");
+        assert_eq!(to_literal(&DriverValue::Float64(f64::NEG_INFINITY)).unwrap(), "DOUBLE '-Infinity'");
+        assert_eq!(to_literal(&DriverValue::Text("o'neil".to_string())).unwrap(), "'o''neil'");
+        assert_eq!(to_literal(&DriverValue::ByteArray(vec![1, 171])).unwrap(), "X'01AB'");
+        assert!(to_literal(&DriverValue::Array(vec![])).is_err());
+    }
+}